@@ -1,6 +1,6 @@
 #![allow(unused_variables)]
 
-use std::{env, fs, path::PathBuf};
+use std::{env, path::PathBuf};
 use tracing::*;
 
 fn main() {
@@ -35,17 +35,12 @@ fn android(host: &str) -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rustc-link-lib=c++_shared");
 
     debug!("Emitted linker instruction for c++_shared");
-        
-    println!("cargo:rustc-link-lib=amidi");
-    println!("cargo:rustc-link-search=/home/loko/Android/Sdk/ndk/27/toolchains/llvm/prebuilt/linux-x86_64/sysroot/usr/lib/arm-linux-androideabi/30/");
-
-
-
-    // We'll retrieve both the final output path and the target triple from env vars
-    let output_path = env::var("CARGO_NDK_OUTPUT_PATH")
-        .unwrap_or_else(|_| "./target/ndk-output".into());
-    debug!(output_path, "Retrieved or defaulted CARGO_NDK_OUTPUT_PATH");
 
+    // libamidi is an OS library: we link against the NDK stub for the target
+    // ABI here, and the device provides the real one at runtime. It is never
+    // bundled into the APK. Packaging the cdylib and libc++_shared.so into
+    // gradle's jniLibs/<abi>/ layout is done by `cargo xtask jnilibs`.
+    println!("cargo:rustc-link-lib=amidi");
 
     let target_triple = env::var("TARGET")
         .expect("Missing TARGET environment variable");
@@ -54,9 +49,9 @@ fn android(host: &str) -> Result<(), Box<dyn std::error::Error>> {
     // Some NDK folder names differ from Rust’s target triple (e.g. Rust uses armv7-linux-androideabi,
     // but the folder in the NDK is arm-linux-androideabi). We fix up certain known cases below.
     let ndk_lib_subdir = fixup_target_triple(&target_triple);
-    debug!(?ndk_lib_subdir, "Resolved subdir name in the NDK for libc++_shared");
+    debug!(?ndk_lib_subdir, "Resolved subdir name in the NDK for the target ABI");
 
-    // We'll pick either `darwin-x86_64` or `linux-x86_64` for the path to libc++_shared
+    // We'll pick either `darwin-x86_64` or `linux-x86_64` for the path to the sysroot
     // under $NDK_HOME/toolchains/llvm/prebuilt/. If you're on Windows, adjust as needed.
     let host_prebuilt = if host.contains("apple-darwin") {
         "darwin-x86_64"
@@ -67,71 +62,30 @@ fn android(host: &str) -> Result<(), Box<dyn std::error::Error>> {
     };
     debug!(host_prebuilt, "Inferred prebuilt subdirectory name based on HOST");
 
-
     let ndk_home = env::var("ANDROID_NDK_HOME")
         .expect("Missing ANDROID_NDK_HOME");
     debug!(ndk_home, "Retrieved ANDROID_NDK_HOME");
 
-    // Construct the full path to libc++_shared.so
-    let libcxx_shared_path = PathBuf::from(&ndk_home)
+    // libamidi first shipped with API level 29; the stubs live in a per-API subdir.
+    let api_level = env::var("AMIDI_ANDROID_API_LEVEL").unwrap_or_else(|_| "30".into());
+    println!("cargo:rerun-if-env-changed=AMIDI_ANDROID_API_LEVEL");
+
+    let libamidi_dir = PathBuf::from(&ndk_home)
         .join("toolchains/llvm/prebuilt")
         .join(host_prebuilt)
         .join("sysroot")
         .join("usr")
         .join("lib")
         .join(ndk_lib_subdir)
-        .join("libc++_shared.so");
-
-    debug!(?libcxx_shared_path, "Constructed libc++_shared.so source path");
-
-    if !libcxx_shared_path.exists() {
-        error!(?libcxx_shared_path, "libc++_shared.so not found");
-        return Err(format!("Could not find libc++_shared.so at {:?}", libcxx_shared_path).into());
-    }
+        .join(&api_level);
 
-    let target_output_path = PathBuf::from(&output_path)
-        .join(&target_triple)
-        .join("libc++_shared.so");
-
-    debug!(?target_output_path, "Constructed libc++_shared.so target path");
-
-    fs::create_dir_all(target_output_path.parent().unwrap())?;
-    debug!(parent = ?target_output_path.parent(), "Ensured parent directory exists");
-
-    fs::copy(&libcxx_shared_path, &target_output_path)?;
-    info!(
-        from = ?libcxx_shared_path,
-        to = ?target_output_path,
-        "Copied libc++_shared.so successfully"
-    );
-
-//------
- let libamidi_path = PathBuf::from(&ndk_home)
-        .join("toolchains/llvm/prebuilt/linux-x86_64/sysroot/usr/lib")
-        .join("arm-linux-androideabi")
-        .join("30")
-        .join("libamidi.so");
-
-    debug!(?libamidi_path, "Constructed libamidi.so source path");
-
-    if !libamidi_path.exists() {
-        error!(?libamidi_path, "libamidi.so not found");
-        return Err(format!("Could not find libamidi.so at {:?}", libamidi_path).into());
+    if !libamidi_dir.join("libamidi.so").exists() {
+        error!(?libamidi_dir, "libamidi.so stub not found");
+        return Err(format!("Could not find libamidi.so in {:?}", libamidi_dir).into());
     }
 
-     target_output_path = PathBuf::from(output_path)
-        .join(&target_triple)
-        .join("libamidi.so");
-
-    debug!(?target_output_path, "Constructed libamidi.so target path");
-
-    fs::create_dir_all(target_output_path.parent().unwrap())?;
-    debug!(parent = ?target_output_path.parent(), "Ensured parent directory exists");
-
-    fs::copy(&libamidi_path, &target_output_path)?;
-    info!(from = ?libamidi_path, to = ?target_output_path, "Copied libamidi.so successfully");
-//_--
-
+    println!("cargo:rustc-link-search={}", libamidi_dir.display());
+    info!(?libamidi_dir, "Linking against libamidi stub");
 
     Ok(())
 }
@@ -143,8 +97,8 @@ fn fixup_target_triple(triple: &str) -> &str {
     match triple {
         // Rust says armv7, NDK folder says arm
         "armv7-linux-androideabi" | "thumbv7neon-linux-androideabi" => "arm-linux-androideabi",
-        // 64-bit ARM is typically aarch64-linux-android
-        // (this one usually matches what's on disk)
+        // aarch64-linux-android, i686-linux-android and x86_64-linux-android
+        // match what's on disk
         _ => triple,
     }
 }
@@ -5,8 +5,9 @@ crate::ix!();
 #[getset(get = "pub")]
 pub struct AmidiLibrary {
     /// Keep the dynamic library alive. If this is dropped, all function pointers
-    /// become invalid. So we store it in an Arc.
-    library: Arc<Library>,
+    /// become invalid. So we store it in an Arc. `None` when the function
+    /// pointers come from the in-process virtual backend instead.
+    pub(crate) library: Option<Arc<Library>>,
 
     /// Now each function pointer is a raw pointer function type, not a Symbol.
     /// This avoids self-referential lifetime issues.
@@ -94,7 +95,7 @@ impl AmidiLibrary {
         info!("Successfully loaded libamidi.so and all symbols.");
 
         Ok(Self {
-            library: lib_arc,
+            library: Some(lib_arc),
 
             amidi_device_from_java,
             amidi_device_release,
@@ -0,0 +1,32 @@
+// ---------------- [ File: basic-android-integration/src/amidi_signature_checks.rs ]
+crate::ix!();
+
+// Compile-time verification that every hand-declared `AMidi*Fn` alias in
+// amidi_library_function_pointer_types.rs matches the extern declaration
+// ndk-sys generated from `amidi/AMidi.h`.
+//
+// Each line coerces the ndk_sys function item to our function pointer type.
+// If a parameter or return type drifts (`isize` vs `i32`, `*const` vs `*mut`,
+// a different `JNIEnv`, ...), the coercion fails and so does the build, on
+// the Linux host as well as on android.
+//
+// The result is never used, so nothing here references the libamidi symbols
+// at link time.
+const _: () = {
+    let _: AMidiDeviceFromJavaFn             = ndk_sys::AMidiDevice_fromJava;
+    let _: AMidiDeviceReleaseFn              = ndk_sys::AMidiDevice_release;
+    let _: AMidiDeviceGetTypeFn              = ndk_sys::AMidiDevice_getType;
+    let _: AMidiDeviceGetNumInputPortsFn     = ndk_sys::AMidiDevice_getNumInputPorts;
+    let _: AMidiDeviceGetNumOutputPortsFn    = ndk_sys::AMidiDevice_getNumOutputPorts;
+    let _: AMidiDeviceGetDefaultProtocolFn   = ndk_sys::AMidiDevice_getDefaultProtocol;
+
+    let _: AMidiOutputPortOpenFn             = ndk_sys::AMidiOutputPort_open;
+    let _: AMidiOutputPortCloseFn            = ndk_sys::AMidiOutputPort_close;
+    let _: AMidiOutputPortReceiveFn          = ndk_sys::AMidiOutputPort_receive;
+
+    let _: AMidiInputPortOpenFn              = ndk_sys::AMidiInputPort_open;
+    let _: AMidiInputPortSendFn              = ndk_sys::AMidiInputPort_send;
+    let _: AMidiInputPortSendWithTimestampFn = ndk_sys::AMidiInputPort_sendWithTimestamp;
+    let _: AMidiInputPortSendFlushFn         = ndk_sys::AMidiInputPort_sendFlush;
+    let _: AMidiInputPortCloseFn             = ndk_sys::AMidiInputPort_close;
+};
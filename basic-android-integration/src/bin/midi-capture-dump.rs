@@ -0,0 +1,73 @@
+// ---------------- [ File: basic-android-integration/src/bin/midi-capture-dump.rs ]
+//! Print a MIDI capture (binary or JSON lines) as annotated, decoded messages.
+
+use basic_android_integration::*;
+
+const USAGE: &str = "\
+usage: midi-capture-dump <capture> [options]
+
+options:
+    --device <name>       only show packets from this device (repeatable)
+    --absolute            print raw AMidi timestamps instead of seconds from the first packet
+    --devices             list the devices in the capture and exit
+";
+
+struct DumpOptions {
+    path:     String,
+    devices:  Vec<String>,
+    absolute: bool,
+    list:     bool,
+}
+
+impl DumpOptions {
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut path    = None;
+        let mut devices = Vec::new();
+        let mut absolute = false;
+        let mut list     = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--device" => devices.push(args.next().ok_or("--device needs a value")?.clone()),
+                "--absolute" => absolute = true,
+                "--devices" => list = true,
+                other if other.starts_with("--") => return Err(format!("unknown option {}", other)),
+                other if path.is_none() => path = Some(other.to_owned()),
+                other => return Err(format!("unexpected argument {}", other)),
+            }
+        }
+
+        Ok(Self { path: path.ok_or("no capture file given")?, devices, absolute, list })
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match DumpOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let capture = match MidiCapture::load(&options.path) {
+        Ok(capture) => capture,
+        Err(e) => {
+            eprintln!("error: {}: {}", options.path, e);
+            std::process::exit(1);
+        }
+    };
+
+    if options.list {
+        for device in capture.devices() {
+            println!("{}", device);
+        }
+        return;
+    }
+
+    for line in capture.annotate(&options.devices, options.absolute) {
+        println!("{}", line);
+    }
+}
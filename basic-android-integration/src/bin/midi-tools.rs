@@ -0,0 +1,61 @@
+// ---------------- [ File: basic-android-integration/src/bin/midi-tools.rs ]
+//! Host-side MIDI toolbox: decode dumps and captures, convert SMF <-> JSON,
+//! check SysEx checksums, generate test streams. The work is done in the
+//! library's `midi_tools` module; this is only the I/O.
+
+use basic_android_integration::*;
+use std::io::Write;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match MidiToolsCommand::from_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, MIDI_TOOLS_USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(command) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(command: MidiToolsCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        MidiToolsCommand::Decode(bytes) => print_lines(decode_to_lines(&bytes)),
+        MidiToolsCommand::DecodeFile(path) => print_lines(decode_file_to_lines(&std::fs::read(path)?)?),
+        MidiToolsCommand::SmfToJson { input, output } => {
+            let json = smf_to_json(&std::fs::read(input)?)?;
+            match output {
+                Some(path) => std::fs::write(path, json + "\n")?,
+                None => println!("{}", json),
+            }
+        }
+        MidiToolsCommand::JsonToSmf { input, output } => std::fs::write(output, json_to_smf(&std::fs::read_to_string(input)?)?)?,
+        MidiToolsCommand::SysExCheck(bytes) => {
+            let (lines, ok) = check_roland_sysex(&bytes)?;
+            print_lines(lines);
+            if !ok {
+                return Err("checksum mismatch".into());
+            }
+        }
+        MidiToolsCommand::Generate(options) => {
+            let mut stdout = std::io::stdout().lock();
+            for bytes in options.encode(&options.messages()?) {
+                if options.binary {
+                    stdout.write_all(&bytes)?;
+                } else {
+                    writeln!(stdout, "{}", hex_string(&bytes))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_lines(lines: Vec<String>) {
+    for line in lines {
+        println!("{}", line);
+    }
+}
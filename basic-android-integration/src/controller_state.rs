@@ -0,0 +1,403 @@
+// ---------------- [ File: basic-android-integration/src/controller_state.rs ]
+crate::ix!();
+
+use std::collections::BTreeMap;
+
+pub const CC_BANK_SELECT_MSB:  u8 = 0;
+pub const CC_MODULATION:       u8 = 1;
+pub const CC_DATA_ENTRY_MSB:   u8 = 6;
+pub const CC_EXPRESSION:       u8 = 11;
+pub const CC_BANK_SELECT_LSB:  u8 = 32;
+pub const CC_DATA_ENTRY_LSB:   u8 = 38;
+pub const CC_DATA_INCREMENT:   u8 = 96;
+pub const CC_DATA_DECREMENT:   u8 = 97;
+pub const CC_NRPN_LSB:         u8 = 98;
+pub const CC_NRPN_MSB:         u8 = 99;
+pub const CC_RPN_LSB:          u8 = 100;
+pub const CC_RPN_MSB:          u8 = 101;
+
+/// What Reset All Controllers (CC 121) does to the cached state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControllerResetRule {
+    /// RP-015: pitch bend to center, pressures to 0, modulation to 0,
+    /// expression to 127, pedals (64-67) to 0, parameter numbers to null.
+    /// Volume, pan, bank, program and effect depths are kept.
+    #[default]
+    Rp015,
+    /// Forget every controller value, pressure and parameter; keep program.
+    ClearAll,
+    /// Leave the state alone.
+    Ignore,
+}
+
+/// A program change and the bank select it was made under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramSelection {
+    pub bank_msb: Option<U7>,
+    pub bank_lsb: Option<U7>,
+    pub program:  U7,
+}
+
+/// A registered or non-registered parameter number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ParameterNumber {
+    Registered(U14),
+    NonRegistered(U14),
+}
+
+impl ParameterNumber {
+    /// RPN 0: pitch bend sensitivity (MSB semitones, LSB cents).
+    pub const PITCH_BEND_SENSITIVITY: ParameterNumber = ParameterNumber::Registered(U14::MIN);
+
+    pub fn number(&self) -> U14 {
+        match *self {
+            ParameterNumber::Registered(n) | ParameterNumber::NonRegistered(n) => n,
+        }
+    }
+}
+
+/// Cached state of one channel. `None` means "never received".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelControllerState {
+    controllers:      [Option<U7>; 128],
+    program:          Option<ProgramSelection>,
+    pitch_bend:       U14,
+    channel_pressure: Option<U7>,
+    poly_pressure:    BTreeMap<U7, U7>,
+    parameters:       BTreeMap<ParameterNumber, U14>,
+    /// Parameter selected by the last RPN/NRPN number controllers; `None`
+    /// after the null parameter (127/127) or before any selection.
+    selected:         Option<ParameterNumber>,
+}
+
+impl Default for ChannelControllerState {
+    fn default() -> Self {
+        Self {
+            controllers:      [None; 128],
+            program:          None,
+            pitch_bend:       U14::CENTER,
+            channel_pressure: None,
+            poly_pressure:    BTreeMap::new(),
+            parameters:       BTreeMap::new(),
+            selected:         None,
+        }
+    }
+}
+
+impl ChannelControllerState {
+    pub fn controller(&self, controller: U7) -> Option<U7> {
+        self.controllers[controller.get() as usize]
+    }
+
+    /// Combined value of a 14-bit controller pair, addressed by its MSB
+    /// controller (0-31; the LSB is 32 higher). A missing LSB counts as 0.
+    pub fn controller_14bit(&self, msb_controller: U7) -> Option<U14> {
+        let msb = msb_controller.get();
+        if msb >= 32 {
+            return None;
+        }
+        let hi = self.controllers[msb as usize]?;
+        let lo = self.controllers[msb as usize + 32].unwrap_or(U7::MIN);
+        Some(U14::from_msb_lsb(hi, lo))
+    }
+
+    pub fn program(&self) -> Option<ProgramSelection> {
+        self.program
+    }
+
+    pub fn pitch_bend(&self) -> U14 {
+        self.pitch_bend
+    }
+
+    pub fn channel_pressure(&self) -> Option<U7> {
+        self.channel_pressure
+    }
+
+    pub fn poly_pressure(&self, note: U7) -> Option<U7> {
+        self.poly_pressure.get(&note).copied()
+    }
+
+    pub fn parameter(&self, parameter: ParameterNumber) -> Option<U14> {
+        self.parameters.get(&parameter).copied()
+    }
+
+    pub fn selected_parameter(&self) -> Option<ParameterNumber> {
+        self.selected
+    }
+
+    fn observe(&mut self, message: &MidiMessage, reset_rule: ControllerResetRule) {
+        match *message {
+            MidiMessage::ControlChange { controller, value, .. } => self.observe_control(controller.get(), value, reset_rule),
+            MidiMessage::ProgramChange { program, .. } => {
+                self.program = Some(ProgramSelection {
+                    bank_msb: self.controllers[CC_BANK_SELECT_MSB as usize],
+                    bank_lsb: self.controllers[CC_BANK_SELECT_LSB as usize],
+                    program,
+                });
+            }
+            MidiMessage::PitchBend { value, .. } => self.pitch_bend = value,
+            MidiMessage::ChannelPressure { pressure, .. } => self.channel_pressure = Some(pressure),
+            MidiMessage::PolyPressure { note, pressure, .. } => {
+                self.poly_pressure.insert(note, pressure);
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_control(&mut self, controller: u8, value: U7, reset_rule: ControllerResetRule) {
+        if controller == CC_RESET_ALL_CONTROLLERS {
+            self.reset_all_controllers(reset_rule);
+            return;
+        }
+        // Channel mode messages other than 121 carry no state.
+        if controller >= CC_ALL_SOUND_OFF {
+            return;
+        }
+
+        self.controllers[controller as usize] = Some(value);
+        // A new MSB invalidates the old LSB of its pair.
+        if controller < 32 {
+            self.controllers[controller as usize + 32] = None;
+        }
+
+        match controller {
+            CC_RPN_MSB | CC_RPN_LSB => self.select_parameter(CC_RPN_MSB, CC_RPN_LSB, ParameterNumber::Registered),
+            CC_NRPN_MSB | CC_NRPN_LSB => self.select_parameter(CC_NRPN_MSB, CC_NRPN_LSB, ParameterNumber::NonRegistered),
+            CC_DATA_ENTRY_MSB => self.update_parameter(|_| U14::from_msb_lsb(value, U7::MIN)),
+            CC_DATA_ENTRY_LSB => self.update_parameter(|current| U14::from_msb_lsb(current.msb(), value)),
+            CC_DATA_INCREMENT => self.update_parameter(|current| U14::new(current.get().saturating_add(1)).unwrap_or(U14::MAX)),
+            CC_DATA_DECREMENT => self.update_parameter(|current| U14::new(current.get().saturating_sub(1)).unwrap_or(U14::MIN)),
+            _ => {}
+        }
+    }
+
+    fn select_parameter(&mut self, msb_cc: u8, lsb_cc: u8, kind: fn(U14) -> ParameterNumber) {
+        let msb = self.controllers[msb_cc as usize].unwrap_or(U7::MAX);
+        let lsb = self.controllers[lsb_cc as usize].unwrap_or(U7::MAX);
+        self.selected = if msb == U7::MAX && lsb == U7::MAX {
+            None
+        } else {
+            Some(kind(U14::from_msb_lsb(msb, lsb)))
+        };
+    }
+
+    fn update_parameter(&mut self, update: impl FnOnce(U14) -> U14) {
+        if let Some(parameter) = self.selected {
+            let current = self.parameters.get(&parameter).copied().unwrap_or(U14::MIN);
+            self.parameters.insert(parameter, update(current));
+        }
+    }
+
+    fn reset_all_controllers(&mut self, rule: ControllerResetRule) {
+        match rule {
+            ControllerResetRule::Ignore => {}
+            ControllerResetRule::ClearAll => {
+                *self = Self { program: self.program, ..Self::default() };
+            }
+            ControllerResetRule::Rp015 => {
+                self.pitch_bend = U14::CENTER;
+                self.channel_pressure = self.channel_pressure.map(|_| U7::MIN);
+                for pressure in self.poly_pressure.values_mut() {
+                    *pressure = U7::MIN;
+                }
+                let mut set = |controller: u8, value: U7| self.controllers[controller as usize] = Some(value);
+                set(CC_MODULATION, U7::MIN);
+                set(CC_EXPRESSION, U7::MAX);
+                for pedal in CC_SUSTAIN..=67 {
+                    set(pedal, U7::MIN);
+                }
+                for number in [CC_NRPN_LSB, CC_NRPN_MSB, CC_RPN_LSB, CC_RPN_MSB] {
+                    set(number, U7::MAX);
+                }
+                self.controllers[(CC_MODULATION + 32) as usize] = None;
+                self.controllers[(CC_EXPRESSION + 32) as usize] = None;
+                self.selected = None;
+            }
+        }
+    }
+}
+
+/// One difference between two [`MidiControllerState`]s, with the value in
+/// the newer state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControllerStateChange {
+    Controller { channel: Channel, controller: U7, value: Option<U7> },
+    Program { channel: Channel, program: Option<ProgramSelection> },
+    PitchBend { channel: Channel, value: U14 },
+    ChannelPressure { channel: Channel, value: Option<U7> },
+    PolyPressure { channel: Channel, note: U7, value: Option<U7> },
+    Parameter { channel: Channel, parameter: ParameterNumber, value: Option<U14> },
+}
+
+/// Controller, program, bend, pressure and RPN/NRPN state of all sixteen
+/// channels of an incoming stream.
+///
+/// Cheap to clone, so a snapshot is just a clone; [`diff`](Self::diff)
+/// lists what changed between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MidiControllerState {
+    channels:   [ChannelControllerState; 16],
+    reset_rule: ControllerResetRule,
+}
+
+impl MidiControllerState {
+    pub fn new(reset_rule: ControllerResetRule) -> Self {
+        Self { reset_rule, ..Self::default() }
+    }
+
+    pub fn reset_rule(&self) -> ControllerResetRule {
+        self.reset_rule
+    }
+
+    pub fn channel(&self, channel: Channel) -> &ChannelControllerState {
+        &self.channels[channel.get() as usize]
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage) {
+        if let MidiMessage::SystemReset = message {
+            debug!("system reset: clearing controller state");
+            *self = Self::new(self.reset_rule);
+            return;
+        }
+        if let Some(channel) = message.channel() {
+            self.channels[channel.get() as usize].observe(message, self.reset_rule);
+        }
+    }
+
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Everything that differs in `newer`, channel by channel.
+    pub fn diff(&self, newer: &Self) -> Vec<ControllerStateChange> {
+        let mut changes = Vec::new();
+        for channel in Channel::all() {
+            let (old, new) = (self.channel(channel), newer.channel(channel));
+            if old == new {
+                continue;
+            }
+            for (index, (a, b)) in old.controllers.iter().zip(&new.controllers).enumerate() {
+                if a != b {
+                    changes.push(ControllerStateChange::Controller { channel, controller: U7::saturating(index as u8), value: *b });
+                }
+            }
+            if old.program != new.program {
+                changes.push(ControllerStateChange::Program { channel, program: new.program });
+            }
+            if old.pitch_bend != new.pitch_bend {
+                changes.push(ControllerStateChange::PitchBend { channel, value: new.pitch_bend });
+            }
+            if old.channel_pressure != new.channel_pressure {
+                changes.push(ControllerStateChange::ChannelPressure { channel, value: new.channel_pressure });
+            }
+            for note in old.poly_pressure.keys().chain(new.poly_pressure.keys()).collect::<std::collections::BTreeSet<_>>() {
+                if old.poly_pressure.get(note) != new.poly_pressure.get(note) {
+                    changes.push(ControllerStateChange::PolyPressure { channel, note: *note, value: new.poly_pressure.get(note).copied() });
+                }
+            }
+            for parameter in old.parameters.keys().chain(new.parameters.keys()).collect::<std::collections::BTreeSet<_>>() {
+                if old.parameters.get(parameter) != new.parameters.get(parameter) {
+                    changes.push(ControllerStateChange::Parameter {
+                        channel,
+                        parameter: *parameter,
+                        value: new.parameters.get(parameter).copied(),
+                    });
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Keeps a [`MidiControllerState`] up to date from a `MidiOutputPort`.
+#[derive(Debug, Default)]
+pub struct MidiControllerMonitor {
+    state:  MidiControllerState,
+    parser: MidiStreamParser,
+}
+
+impl MidiControllerMonitor {
+    pub fn new(reset_rule: ControllerResetRule) -> Self {
+        Self { state: MidiControllerState::new(reset_rule), parser: MidiStreamParser::new() }
+    }
+
+    pub fn state(&self) -> &MidiControllerState {
+        &self.state
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let state = &mut self.state;
+        self.parser.feed(bytes, |message| state.observe(&message));
+    }
+
+    /// Apply everything pending on `port`; see [`MidiOutputPort::drain`].
+    pub fn poll(&mut self, port: &MidiOutputPort) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, .. } => self.feed(bytes),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_controller_state {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    #[test]
+    fn test_monitor_tracks_port_traffic() {
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let output = virtual_device.output_port(0);
+        // bank 1/2, program 5; 14-bit mod wheel; RPN 0 = 12 semitones 50 cents; bend
+        output.push(vec![0xb0, 0, 1, 32, 2, 0xc0, 5], 1);
+        output.push(vec![0xb0, 1, 64, 33, 3], 2);
+        output.push(vec![0xb0, 101, 0, 100, 0, 6, 12, 38, 50, 96, 0], 3);
+        output.push(vec![0xe0, 0, 0x60, 0xa0, 60, 20], 4);
+
+        let device  = virtual_device.open();
+        let port    = device.open_output_port(0).unwrap();
+        let mut monitor = MidiControllerMonitor::default();
+        assert_eq!(monitor.poll(&port).unwrap(), 4);
+
+        let state = monitor.state().channel(ch(0));
+        assert_eq!(state.program(), Some(ProgramSelection { bank_msb: Some(u7(1)), bank_lsb: Some(u7(2)), program: u7(5) }));
+        assert_eq!(state.controller_14bit(u7(CC_MODULATION)), Some(U14::new(64 << 7 | 3).unwrap()));
+        assert_eq!(state.selected_parameter(), Some(ParameterNumber::PITCH_BEND_SENSITIVITY));
+        assert_eq!(state.parameter(ParameterNumber::PITCH_BEND_SENSITIVITY), Some(U14::new(12 << 7 | 51).unwrap()));
+        assert_eq!(state.pitch_bend(), U14::new(0x60 << 7).unwrap());
+        assert_eq!(state.poly_pressure(u7(60)), Some(u7(20)));
+    }
+
+    #[test]
+    fn test_rp015_reset_and_diff() {
+        let mut state = MidiControllerState::default();
+        for message in [
+            MidiMessage::control_change(ch(2), u7(7), u7(90)),
+            MidiMessage::control_change(ch(2), u7(CC_MODULATION), u7(40)),
+            MidiMessage::control_change(ch(2), u7(CC_SUSTAIN), u7(127)),
+            MidiMessage::pitch_bend(ch(2), U14::MAX),
+        ] {
+            state.observe(&message);
+        }
+        let before = state.snapshot();
+        state.observe(&MidiMessage::control_change(ch(2), u7(CC_RESET_ALL_CONTROLLERS), U7::MIN));
+
+        assert_eq!(state.channel(ch(2)).controller(u7(7)), Some(u7(90)));
+        assert_eq!(state.channel(ch(2)).pitch_bend(), U14::CENTER);
+        assert_eq!(state.channel(ch(2)).selected_parameter(), None);
+
+        let changes = before.diff(&state);
+        assert!(changes.contains(&ControllerStateChange::Controller { channel: ch(2), controller: u7(CC_MODULATION), value: Some(U7::MIN) }));
+        assert!(changes.contains(&ControllerStateChange::Controller { channel: ch(2), controller: u7(CC_SUSTAIN), value: Some(U7::MIN) }));
+        assert!(changes.contains(&ControllerStateChange::PitchBend { channel: ch(2), value: U14::CENTER }));
+        assert!(!changes.iter().any(|c| matches!(c, ControllerStateChange::Controller { controller, .. } if controller.get() == 7)));
+        assert!(state.diff(&state.snapshot()).is_empty());
+
+        let mut ignoring = MidiControllerState::new(ControllerResetRule::Ignore);
+        ignoring.observe(&MidiMessage::pitch_bend(ch(0), U14::MAX));
+        ignoring.observe(&MidiMessage::control_change(ch(0), u7(CC_RESET_ALL_CONTROLLERS), U7::MIN));
+        assert_eq!(ignoring.channel(ch(0)).pitch_bend(), U14::MAX);
+    }
+}
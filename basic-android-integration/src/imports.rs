@@ -1,4 +1,5 @@
 // ---------------- [ File: basic-android-integration/src/imports.rs ]
+#![allow(unused_imports)]
 pub(crate) use export_magic::*;
 pub(crate) use libloading::{Library, Symbol};
 pub(crate) use traced_test::*;
@@ -7,6 +8,7 @@ pub(crate) use tracing_setup::*;
 pub(crate) use derive_builder::Builder;
 pub(crate) use getset::{CopyGetters, Getters, Setters};
 pub(crate) use std::sync::Arc;
+pub(crate) use serde::{Deserialize, Serialize};
 pub(crate) use ndk_sys::{
     media_status_t,
     AMidiDevice,
@@ -14,7 +16,9 @@ pub(crate) use ndk_sys::{
     AMidiOutputPort,
     AMidiInputPort,
 };
-pub(crate) use jni::sys::{
+// ndk-sys is generated against jni-sys 0.3; these must be the same types
+// ndk_sys::AMidiDevice_fromJava takes, which `jni::sys` no longer guarantees.
+pub(crate) use jni_sys::{
     JNIEnv,
     jobject
 };
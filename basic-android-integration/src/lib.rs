@@ -4,6 +4,64 @@
 x!{basic_android_integration}
 x!{amidi_library}
 x!{amidi_library_function_pointer_types}
+x!{amidi_signature_checks}
+x!{controller_state}
+x!{machine_control_error}
+x!{machine_control_receiver}
+x!{manufacturer_id}
 x!{midi_device}
+x!{midi_port_error}
+x!{midi_port_registry}
+x!{midi_value_types}
+x!{midi_byte_segments}
+x!{midi_capture}
+x!{midi_clock_follower}
+x!{midi_clock_master}
 x!{midi_input_port}
+x!{midi_input_port_send_all}
+x!{midi_input_port_note_tracking}
+x!{midi_message}
 x!{midi_output_port}
+x!{midi_processor}
+x!{midi_processor_chain}
+x!{midi_processor_stages}
+x!{midi_routing_graph}
+x!{midi_stream_parser}
+x!{midi_tools}
+x!{midi_tuning}
+x!{mmc_command}
+x!{mmc_response}
+x!{monotonic_clock}
+x!{msc_command}
+x!{mtc_generator}
+x!{mtc_reader}
+x!{mts_message}
+x!{mpe_receiver}
+x!{mpe_sender}
+x!{mpe_zone}
+x!{note_tracker}
+x!{paced_midi_input_port}
+x!{parameter_change}
+x!{parameter_change_decoder}
+x!{parameter_change_encoder}
+x!{pitch_bend_retuner}
+x!{running_status_encoder}
+x!{scala_error}
+x!{scala_keyboard_mapping}
+x!{scala_scale}
+x!{smf}
+x!{smf_error}
+x!{smf_meta_event}
+x!{smf_player}
+x!{smf_reader}
+x!{smf_recorder}
+x!{smf_tempo_map}
+x!{smf_writer}
+x!{smpte_time}
+x!{sysex_checksum}
+x!{sysex_send_error}
+x!{sysex_transaction}
+x!{universal_sysex}
+x!{variable_length_quantity}
+x!{virtual_amidi_backend}
+x!{wake_timer}
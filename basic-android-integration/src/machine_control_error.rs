@@ -0,0 +1,51 @@
+// ---------------- [ File: basic-android-integration/src/machine_control_error.rs ]
+crate::ix!();
+
+/// Why an MMC or MSC message could not be built or decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineControlError {
+    /// The SysEx is not the kind of message asked for (another sub-id,
+    /// another manufacturer).
+    NotMachineControl,
+    /// The message ended in the middle of `what`.
+    Truncated { what: &'static str },
+    /// Cue numbers, lists and paths are ASCII digits and `.` only.
+    BadCueCharacter { byte: u8 },
+    /// An empty cue number, list or path would encode as a different cue.
+    EmptyCueField { what: &'static str },
+    /// MSC has no way to send a cue path without a cue list.
+    CuePathWithoutList,
+    Value(MidiValueError),
+    /// The port did not take the whole message.
+    Send(SendAllError),
+}
+
+impl std::fmt::Display for MachineControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MachineControlError::NotMachineControl => write!(f, "not an MMC or MSC message"),
+            MachineControlError::Truncated { what } => write!(f, "message ends inside {}", what),
+            MachineControlError::BadCueCharacter { byte } => {
+                write!(f, "cue field byte {:#04x} is not a digit or '.'", byte)
+            }
+            MachineControlError::EmptyCueField { what } => write!(f, "cue {} is empty", what),
+            MachineControlError::CuePathWithoutList => write!(f, "cue path given without a cue list"),
+            MachineControlError::Value(err) => write!(f, "{}", err),
+            MachineControlError::Send(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MachineControlError {}
+
+impl From<MidiValueError> for MachineControlError {
+    fn from(err: MidiValueError) -> Self {
+        MachineControlError::Value(err)
+    }
+}
+
+impl From<SendAllError> for MachineControlError {
+    fn from(err: SendAllError) -> Self {
+        MachineControlError::Send(err)
+    }
+}
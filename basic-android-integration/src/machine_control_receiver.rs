@@ -0,0 +1,193 @@
+// ---------------- [ File: basic-android-integration/src/machine_control_receiver.rs ]
+crate::ix!();
+
+/// Identifies a read sent with [`MachineControlReceiver::send_read`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MmcRequestId(u64);
+
+/// What a [`MachineControlReceiver`] makes of incoming SysEx.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineControlEvent {
+    Mmc(MmcMessage),
+    /// Response fields no pending read asked for.
+    MmcResponse(MmcResponseMessage),
+    Msc(MscMessage),
+    /// One field answering a read.
+    Reply { request: MmcRequestId, device_id: U7, response: MmcResponse },
+    /// An MMC or MSC message that did not decode.
+    Malformed { data: Vec<u8>, error: MachineControlError },
+}
+
+#[derive(Debug, Clone)]
+struct PendingRead {
+    id:        MmcRequestId,
+    device_id: U7,
+    /// Fields not answered yet.
+    fields:    Vec<u8>,
+}
+
+/// Picks MMC and MSC out of a reassembled SysEx stream and pairs MMC
+/// responses with the reads that asked for them.
+///
+/// With a device id set, commands addressed to other devices are dropped;
+/// all-call (0x7F) and joined MSC groups always get through. Responses are
+/// never filtered, their id is the sender's.
+#[derive(Debug, Clone, Default)]
+pub struct MachineControlReceiver {
+    device_id:    Option<U7>,
+    groups:       Vec<U7>,
+    parser:       MidiStreamParser,
+    pending:      Vec<PendingRead>,
+    next_request: u64,
+}
+
+impl MachineControlReceiver {
+    pub fn new(device_id: Option<U7>) -> Self {
+        Self { device_id, ..Self::default() }
+    }
+
+    pub fn device_id(&self) -> Option<U7> {
+        self.device_id
+    }
+
+    /// Also accept MSC messages for group `group` (0x70-0x7E).
+    pub fn join_msc_group(&mut self, group: U7) {
+        self.groups.push(group);
+    }
+
+    fn accepts(&self, device_id: U7) -> bool {
+        self.device_id.is_none_or(|ours| ours == device_id || device_id == ALL_CALL || self.groups.contains(&device_id))
+    }
+
+    /// Send an MMC Read for `fields` to `device_id` and remember it; the
+    /// answers come back as [`MachineControlEvent::Reply`]. With all-call,
+    /// every device's answer matches.
+    pub fn send_read(&mut self, port: &MidiInputPort, device_id: U7, fields: &[u8]) -> Result<MmcRequestId, MachineControlError> {
+        MmcMessage::new(device_id, vec![MmcCommand::Read(fields.to_vec())]).send(port)?;
+        let id = MmcRequestId(self.next_request);
+        self.next_request += 1;
+        self.pending.push(PendingRead { id, device_id, fields: fields.to_vec() });
+        Ok(id)
+    }
+
+    /// Stop waiting for the rest of a read.
+    pub fn cancel(&mut self, request: MmcRequestId) {
+        self.pending.retain(|read| read.id != request);
+    }
+
+    /// Reads with fields still unanswered.
+    pub fn pending_reads(&self) -> Vec<MmcRequestId> {
+        self.pending.iter().map(|read| read.id).collect()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut sink: impl FnMut(MachineControlEvent)) {
+        let mut messages = Vec::new();
+        self.parser.feed(bytes, |message| messages.push(message));
+        for message in messages {
+            self.observe(&message, &mut sink);
+        }
+    }
+
+    /// Decode everything pending on `port`, answering pending reads.
+    pub fn poll(&mut self, port: &MidiOutputPort, mut sink: impl FnMut(MachineControlEvent)) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, .. } => self.feed(bytes, &mut sink),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage, mut sink: impl FnMut(MachineControlEvent)) {
+        let MidiMessage::SysEx(data) = message else {
+            return;
+        };
+        let [UNIVERSAL_REAL_TIME, device_id, sub_id, ..] = data[..] else {
+            return;
+        };
+        let decoded = match sub_id {
+            MMC_COMMAND_SUB_ID => MmcMessage::from_sysex(data).map(MachineControlEvent::Mmc),
+            MMC_RESPONSE_SUB_ID => MmcResponseMessage::from_sysex(data).map(MachineControlEvent::MmcResponse),
+            MSC_SUB_ID => MscMessage::from_sysex(data).map(MachineControlEvent::Msc),
+            _ => return,
+        };
+        match decoded {
+            Ok(MachineControlEvent::MmcResponse(response)) => self.correlate(response, sink),
+            Ok(event) if self.accepts(U7::saturating(device_id)) => sink(event),
+            Ok(_) => {}
+            Err(error) => sink(MachineControlEvent::Malformed { data: data.to_vec(), error }),
+        }
+    }
+
+    fn correlate(&mut self, mut message: MmcResponseMessage, mut sink: impl FnMut(MachineControlEvent)) {
+        let device_id = message.device_id;
+        message.responses.retain(|response| {
+            let field = response.field();
+            let read  = self.pending.iter_mut().find(|read| {
+                (read.device_id == device_id || read.device_id == ALL_CALL) && read.fields.contains(&field)
+            });
+            let Some(read) = read else {
+                return true;
+            };
+            // All-call reads keep listening: any number of devices may answer.
+            if read.device_id != ALL_CALL {
+                read.fields.retain(|f| *f != field);
+            }
+            sink(MachineControlEvent::Reply { request: read.id, device_id, response: response.clone() });
+            false
+        });
+        self.pending.retain(|read| !read.fields.is_empty());
+        if !message.responses.is_empty() {
+            sink(MachineControlEvent::MmcResponse(message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_machine_control_receiver {
+    use super::*;
+
+    #[test]
+    fn test_read_replies_and_addressing() {
+        let virtual_device = VirtualMidiDevice::new(1, 1);
+        let device = virtual_device.open();
+        let input  = device.open_input_port(0).unwrap();
+        let output = device.open_output_port(0).unwrap();
+        let deck   = U7::new(0x10).unwrap();
+
+        let mut receiver = MachineControlReceiver::new(Some(U7::new(0x01).unwrap()));
+        let request = receiver.send_read(&input, deck, &[MMC_FIELD_SELECTED_TIME_CODE]).unwrap();
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![0xf0, 0x7f, 0x10, 0x06, 0x42, 0x01, 0x01, 0xf7]);
+
+        let position = SmptePosition::new(SmpteTime::new(0, 1, 0, 0, SmpteRate::Fps25).unwrap(), 0);
+        let answer   = MmcResponseMessage::new(deck, vec![
+            MmcResponse::TimeCode { field: MMC_FIELD_SELECTED_TIME_CODE, position },
+            MmcResponse::Other { field: MMC_FIELD_RECORD_STATUS, data: vec![0x00] },
+        ]);
+        let port = virtual_device.output_port(0);
+        // Split across packets, as a USB interface delivers long SysEx.
+        let bytes = answer.to_message().unwrap().to_bytes();
+        port.push(bytes[..6].to_vec(), 1);
+        port.push(bytes[6..].to_vec(), 2);
+        port.push(MmcMessage::new(U7::new(0x02).unwrap(), vec![MmcCommand::Stop]).to_message().unwrap().to_bytes(), 3);
+        port.push(MmcMessage::new(ALL_CALL, vec![MmcCommand::RecordStrobe]).to_message().unwrap().to_bytes(), 4);
+        port.push(vec![0xf0, 0x7f, 0x01, 0x06, 0x44, 0x06, 0xf7], 5);
+
+        let mut events = Vec::new();
+        receiver.poll(&output, |event| events.push(event)).unwrap();
+        assert_eq!(events, vec![
+            MachineControlEvent::Reply {
+                request,
+                device_id: deck,
+                response: MmcResponse::TimeCode { field: MMC_FIELD_SELECTED_TIME_CODE, position },
+            },
+            MachineControlEvent::MmcResponse(MmcResponseMessage::new(deck, vec![
+                MmcResponse::Other { field: MMC_FIELD_RECORD_STATUS, data: vec![0x00] },
+            ])),
+            MachineControlEvent::Mmc(MmcMessage::new(ALL_CALL, vec![MmcCommand::RecordStrobe])),
+            MachineControlEvent::Malformed {
+                data:  vec![0x7f, 0x01, 0x06, 0x44, 0x06],
+                error: MachineControlError::Truncated { what: "command data" },
+            },
+        ]);
+        assert!(receiver.pending_reads().is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+// ---------------- [ File: basic-android-integration/src/manufacturer_id.rs ]
+crate::ix!();
+
+/// A SysEx manufacturer id: one byte, or `00` followed by two bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ManufacturerId {
+    Short(u8),
+    Extended(u8, u8),
+}
+
+/// Known manufacturers. Not exhaustive; the MMA/AMEI list is the reference.
+const MANUFACTURERS: &[(ManufacturerId, &str)] = &[
+    (ManufacturerId::Short(0x01), "Sequential Circuits"),
+    (ManufacturerId::Short(0x04), "Moog"),
+    (ManufacturerId::Short(0x06), "Lexicon"),
+    (ManufacturerId::Short(0x07), "Kurzweil"),
+    (ManufacturerId::Short(0x0f), "Ensoniq"),
+    (ManufacturerId::Short(0x10), "Oberheim"),
+    (ManufacturerId::Short(0x11), "Apple"),
+    (ManufacturerId::Short(0x18), "E-mu"),
+    (ManufacturerId::Short(0x1c), "Eventide"),
+    (ManufacturerId::Short(0x33), "Clavia"),
+    (ManufacturerId::Short(0x3a), "Steinberg"),
+    (ManufacturerId::Short(0x3e), "Waldorf"),
+    (ManufacturerId::Short(0x40), "Kawai"),
+    (ManufacturerId::Short(0x41), "Roland"),
+    (ManufacturerId::Short(0x42), "Korg"),
+    (ManufacturerId::Short(0x43), "Yamaha"),
+    (ManufacturerId::Short(0x44), "Casio"),
+    (ManufacturerId::Short(0x47), "Akai"),
+    (ManufacturerId::Short(0x48), "Victor (JVC)"),
+    (ManufacturerId::Short(0x4c), "Sony"),
+    (ManufacturerId::Short(0x4e), "Teac (Tascam)"),
+    (ManufacturerId::Short(0x51), "Fostex"),
+    (ManufacturerId::Short(0x52), "Zoom"),
+    (ManufacturerId::Short(0x7d), "Non-commercial"),
+    (ManufacturerId::Extended(0x00, 0x0e), "Alesis"),
+    (ManufacturerId::Extended(0x00, 0x41), "Microsoft"),
+    (ManufacturerId::Extended(0x00, 0x66), "Mackie"),
+    (ManufacturerId::Extended(0x01, 0x05), "M-Audio"),
+    (ManufacturerId::Extended(0x20, 0x1f), "TC Electronic"),
+    (ManufacturerId::Extended(0x20, 0x29), "Focusrite/Novation"),
+    (ManufacturerId::Extended(0x20, 0x32), "Behringer"),
+    (ManufacturerId::Extended(0x20, 0x33), "Access Music"),
+    (ManufacturerId::Extended(0x20, 0x3c), "Elektron"),
+    (ManufacturerId::Extended(0x20, 0x6b), "Arturia"),
+    (ManufacturerId::Extended(0x20, 0x76), "Teenage Engineering"),
+    (ManufacturerId::Extended(0x21, 0x09), "Native Instruments"),
+];
+
+impl ManufacturerId {
+    pub const ROLAND: Self = Self::Short(0x41);
+    pub const KORG:   Self = Self::Short(0x42);
+    pub const YAMAHA: Self = Self::Short(0x43);
+    /// For in-house and educational use, never in released products.
+    pub const NON_COMMERCIAL: Self = Self::Short(0x7d);
+
+    /// Split the id off the front of SysEx data (after `F0`).
+    pub fn parse(data: &[u8]) -> Option<(Self, &[u8])> {
+        match data {
+            [0x00, a, b, rest @ ..] => Some((ManufacturerId::Extended(*a, *b), rest)),
+            [0x00, ..] | [] => None,
+            [id, rest @ ..] => Some((ManufacturerId::Short(*id), rest)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            ManufacturerId::Short(id) => vec![id],
+            ManufacturerId::Extended(a, b) => vec![0x00, a, b],
+        }
+    }
+
+    /// `None` for ids missing from the built-in table.
+    pub fn name(&self) -> Option<&'static str> {
+        MANUFACTURERS.iter().find(|(id, _)| id == self).map(|(_, name)| *name)
+    }
+
+    /// Look an id up by name, ignoring case.
+    pub fn by_name(name: &str) -> Option<Self> {
+        MANUFACTURERS.iter().find(|(_, known)| known.eq_ignore_ascii_case(name)).map(|(id, _)| *id)
+    }
+}
+
+impl std::fmt::Display for ManufacturerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hex = self.to_bytes().iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", name, hex),
+            None => write!(f, "{}", hex),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// ---------------- [ File: basic-android-integration/src/midi_byte_segments.rs ]
+crate::ix!();
+
+/// What a [`MidiByteSegment`] of a raw byte stream holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiSegmentKind {
+    /// A channel voice message, with or without its status byte (running status).
+    Channel,
+    /// `F1`, `F2`, `F3` or `F6`.
+    SystemCommon,
+    /// `F0` .. `F7`, including any realtime bytes interleaved inside it.
+    /// Unterminated if the stream ends or another status byte interrupts it.
+    SysEx,
+    /// A single `F8`..=`FF` byte.
+    Realtime,
+    /// Data bytes with no status to attach to, or an undefined status byte.
+    Stray,
+}
+
+/// A run of bytes that forms one message (or what is left of one).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MidiByteSegment {
+    pub range: std::ops::Range<usize>,
+    pub kind:  MidiSegmentKind,
+}
+
+impl MidiByteSegment {
+    /// Whether the segment may be cut anywhere without corrupting the stream.
+    /// Only SysEx qualifies: its data bytes carry no framing of their own.
+    pub fn is_splittable(&self) -> bool {
+        self.kind == MidiSegmentKind::SysEx
+    }
+}
+
+/// Cut a raw MIDI byte stream into message-sized segments without decoding
+/// it, honoring running status. Truncated trailing messages come back as a
+/// shorter segment of the same kind.
+pub fn midi_byte_segments(bytes: &[u8]) -> Vec<MidiByteSegment> {
+    let mut segments = Vec::new();
+    let mut running: Option<u8> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let byte  = bytes[i];
+
+        let kind = if byte >= 0xf8 {
+            i += 1;
+            MidiSegmentKind::Realtime
+        } else if byte == 0xf0 {
+            running = None;
+            i += 1;
+            while i < bytes.len() {
+                let b = bytes[i];
+                if !(0x80..0xf8).contains(&b) {
+                    i += 1;
+                } else {
+                    if b == 0xf7 {
+                        i += 1;
+                    }
+                    break;
+                }
+            }
+            MidiSegmentKind::SysEx
+        } else if byte >= 0x80 {
+            match status_message_len(byte) {
+                Some(len) => {
+                    running = if byte < 0xf0 { Some(byte) } else { None };
+                    i = take_data_bytes(bytes, i + 1, len - 1);
+                    if byte < 0xf0 { MidiSegmentKind::Channel } else { MidiSegmentKind::SystemCommon }
+                }
+                None => {
+                    // Undefined status or a lone F7.
+                    running = None;
+                    i += 1;
+                    MidiSegmentKind::Stray
+                }
+            }
+        } else {
+            match running.and_then(status_message_len) {
+                Some(len) => {
+                    i = take_data_bytes(bytes, i, len - 1);
+                    MidiSegmentKind::Channel
+                }
+                None => {
+                    i += 1;
+                    MidiSegmentKind::Stray
+                }
+            }
+        };
+
+        segments.push(MidiByteSegment { range: start..i, kind });
+    }
+
+    segments
+}
+
+/// Advance over up to `count` data bytes starting at `from`, stopping early at
+/// any status byte.
+fn take_data_bytes(bytes: &[u8], from: usize, count: usize) -> usize {
+    let mut i = from;
+    while i < bytes.len() && i - from < count && bytes[i] < 0x80 {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod test_midi_byte_segments {
+    use super::*;
+
+    fn kinds_and_ranges(bytes: &[u8]) -> Vec<(MidiSegmentKind, std::ops::Range<usize>)> {
+        midi_byte_segments(bytes).into_iter().map(|s| (s.kind, s.range)).collect()
+    }
+
+    #[test]
+    fn test_running_status_and_realtime() {
+        use MidiSegmentKind::*;
+        let bytes = [0x90, 60, 100, 62, 100, 0xf8, 0xc0, 5, 0xf6];
+        assert_eq!(
+            kinds_and_ranges(&bytes),
+            vec![(Channel, 0..3), (Channel, 3..5), (Realtime, 5..6), (Channel, 6..8), (SystemCommon, 8..9)]
+        );
+    }
+
+    #[test]
+    fn test_sysex_keeps_interleaved_realtime_and_stops_at_status() {
+        use MidiSegmentKind::*;
+        let bytes = [0xf0, 0x41, 0xf8, 0x10, 0xf7, 0xf0, 0x01, 0x90, 60, 1, 7];
+        assert_eq!(
+            kinds_and_ranges(&bytes),
+            vec![(SysEx, 0..5), (SysEx, 5..7), (Channel, 7..10), (Channel, 10..11)]
+        );
+    }
+}
@@ -0,0 +1,442 @@
+// ---------------- [ File: basic-android-integration/src/midi_capture.rs ]
+crate::ix!();
+
+/// One `MidiOutputPort::receive` result, as captured.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MidiCaptureRecord {
+    /// Free-form identity of the device the packet came from (name,
+    /// manufacturer, serial...), whatever the capturing app knows.
+    pub device:    String,
+    pub port:      i32,
+    pub opcode:    i32,
+    pub timestamp: i64,
+    pub bytes:     Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum MidiCaptureError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated { offset: usize },
+    UnknownRecordTag { offset: usize, tag: u8 },
+    UnknownDevice { offset: usize, id: u16 },
+    /// Device records must number devices 0, 1, 2, ... in order.
+    UnexpectedDeviceId { offset: usize, id: u16, expected: usize },
+    InvalidDeviceName { offset: usize },
+    /// A field of record `record` is too large (or, for the port,
+    /// negative) for the binary encoding.
+    DoesNotFit { record: usize, what: &'static str },
+    Json { line: usize, error: serde_json::Error },
+}
+
+impl std::fmt::Display for MidiCaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiCaptureError::BadMagic => write!(f, "not a MIDI capture file"),
+            MidiCaptureError::UnsupportedVersion(version) => write!(f, "unsupported capture version {}", version),
+            MidiCaptureError::Truncated { offset } => write!(f, "capture truncated at offset {}", offset),
+            MidiCaptureError::UnknownRecordTag { offset, tag } => {
+                write!(f, "unknown record tag {:#04x} at offset {}", tag, offset)
+            }
+            MidiCaptureError::UnknownDevice { offset, id } => {
+                write!(f, "packet at offset {} refers to undeclared device {}", offset, id)
+            }
+            MidiCaptureError::UnexpectedDeviceId { offset, id, expected } => {
+                write!(f, "device record at offset {} has id {}, expected {}", offset, id, expected)
+            }
+            MidiCaptureError::InvalidDeviceName { offset } => {
+                write!(f, "device name at offset {} is not UTF-8", offset)
+            }
+            MidiCaptureError::DoesNotFit { record, what } => {
+                write!(f, "{} of record {} does not fit the binary capture format", what, record)
+            }
+            MidiCaptureError::Json { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for MidiCaptureError {}
+
+/// A recording of output port traffic, for reproducing field issues.
+///
+/// Two encodings:
+///
+/// - binary: `AMIDICAP`, a little-endian `u16` version, then records. A
+///   device record (`01`, `u16` id, `u16` length, UTF-8 name) declares a
+///   device once; packet records (`02`, `u16` device id, `u16` port, `i32`
+///   opcode, `i64` timestamp, `u32` length, bytes) refer to it.
+/// - JSON lines: one [`MidiCaptureRecord`] object per line.
+///
+/// [`load`](Self::load) and [`parse`](Self::parse) accept either.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MidiCapture {
+    pub records: Vec<MidiCaptureRecord>,
+}
+
+const CAPTURE_MAGIC:    &[u8; 8] = b"AMIDICAP";
+const CAPTURE_VERSION:  u16 = 1;
+const TAG_DEVICE:       u8 = 0x01;
+const TAG_PACKET:       u8 = 0x02;
+
+impl MidiCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Distinct device identities, in order of first appearance.
+    pub fn devices(&self) -> Vec<&str> {
+        let mut devices: Vec<&str> = Vec::new();
+        for record in &self.records {
+            if !devices.contains(&record.device.as_str()) {
+                devices.push(&record.device);
+            }
+        }
+        devices
+    }
+
+    /// Fails if a device count, name length, port or packet length does not
+    /// fit its field.
+    pub fn to_binary(&self) -> Result<Vec<u8>, MidiCaptureError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(CAPTURE_MAGIC);
+        out.extend_from_slice(&CAPTURE_VERSION.to_le_bytes());
+
+        let mut devices: Vec<&str> = Vec::new();
+        for (index, record) in self.records.iter().enumerate() {
+            let fits = |what| move |_| MidiCaptureError::DoesNotFit { record: index, what };
+            let id = match devices.iter().position(|d| *d == record.device) {
+                Some(id) => id,
+                None => {
+                    let name = record.device.as_bytes();
+                    out.push(TAG_DEVICE);
+                    out.extend_from_slice(&u16::try_from(devices.len()).map_err(fits("device count"))?.to_le_bytes());
+                    out.extend_from_slice(&u16::try_from(name.len()).map_err(fits("device name length"))?.to_le_bytes());
+                    out.extend_from_slice(name);
+                    devices.push(&record.device);
+                    devices.len() - 1
+                }
+            };
+            out.push(TAG_PACKET);
+            out.extend_from_slice(&u16::try_from(id).map_err(fits("device id"))?.to_le_bytes());
+            out.extend_from_slice(&u16::try_from(record.port).map_err(fits("port"))?.to_le_bytes());
+            out.extend_from_slice(&record.opcode.to_le_bytes());
+            out.extend_from_slice(&record.timestamp.to_le_bytes());
+            out.extend_from_slice(&u32::try_from(record.bytes.len()).map_err(fits("packet length"))?.to_le_bytes());
+            out.extend_from_slice(&record.bytes);
+        }
+        Ok(out)
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, MidiCaptureError> {
+        if !bytes.starts_with(CAPTURE_MAGIC) {
+            return Err(MidiCaptureError::BadMagic);
+        }
+        let mut reader = CaptureReader { bytes, offset: CAPTURE_MAGIC.len() };
+        let version = u16::from_le_bytes(reader.take()?);
+        if version != CAPTURE_VERSION {
+            return Err(MidiCaptureError::UnsupportedVersion(version));
+        }
+
+        let mut devices = Vec::new();
+        let mut records = Vec::new();
+        while reader.offset < bytes.len() {
+            let offset = reader.offset;
+            match reader.take::<1>()?[0] {
+                TAG_DEVICE => {
+                    let id   = u16::from_le_bytes(reader.take()?);
+                    if id as usize != devices.len() {
+                        return Err(MidiCaptureError::UnexpectedDeviceId { offset, id, expected: devices.len() });
+                    }
+                    let len  = u16::from_le_bytes(reader.take()?) as usize;
+                    let name = std::str::from_utf8(reader.take_slice(len)?)
+                        .map_err(|_| MidiCaptureError::InvalidDeviceName { offset })?;
+                    devices.push(name.to_owned());
+                }
+                TAG_PACKET => {
+                    let id        = u16::from_le_bytes(reader.take()?);
+                    let port      = u16::from_le_bytes(reader.take()?) as i32;
+                    let opcode    = i32::from_le_bytes(reader.take()?);
+                    let timestamp = i64::from_le_bytes(reader.take()?);
+                    let len       = u32::from_le_bytes(reader.take()?) as usize;
+                    let data      = reader.take_slice(len)?.to_vec();
+                    let device    = devices
+                        .get(id as usize)
+                        .ok_or(MidiCaptureError::UnknownDevice { offset, id })?
+                        .clone();
+                    records.push(MidiCaptureRecord { device, port, opcode, timestamp, bytes: data });
+                }
+                tag => return Err(MidiCaptureError::UnknownRecordTag { offset, tag }),
+            }
+        }
+        Ok(Self { records })
+    }
+
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        for record in &self.records {
+            // A struct of strings and integers always serializes.
+            out.push_str(&serde_json::to_string(record).expect("capture record serializes"));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Blank lines are skipped; `line` in errors is 1-based.
+    pub fn from_json_lines(text: &str) -> Result<Self, MidiCaptureError> {
+        let mut records = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(line).map_err(|error| MidiCaptureError::Json { line: index + 1, error })?;
+            records.push(record);
+        }
+        Ok(Self { records })
+    }
+
+    /// Binary if it starts with the magic, JSON lines otherwise.
+    pub fn parse(bytes: &[u8]) -> Result<Self, MidiCaptureError> {
+        if bytes.starts_with(CAPTURE_MAGIC) {
+            Self::from_binary(bytes)
+        } else {
+            Self::from_json_lines(&String::from_utf8_lossy(bytes))
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::parse(&bytes)?)
+    }
+
+    /// JSON lines if the extension is `jsonl`, binary otherwise.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|ext| ext == "jsonl") {
+            std::fs::write(path, self.to_json_lines())?;
+        } else {
+            std::fs::write(path, self.to_binary()?)?;
+        }
+        Ok(())
+    }
+
+    /// One line per packet: time, `device:port`, opcode, raw hex, and the
+    /// messages it completed. Each device/port pair gets its own parser, so
+    /// running status and SysEx spanning packets decode as they would live.
+    /// `devices` filters when non-empty; `absolute` prints raw timestamps
+    /// instead of seconds since the first packet.
+    pub fn annotate(&self, devices: &[String], absolute: bool) -> Vec<String> {
+        let origin      = self.records.first().map(|r| r.timestamp).unwrap_or(0);
+        let mut parsers = std::collections::HashMap::<(&str, i32), MidiStreamParser>::new();
+        let mut lines   = Vec::new();
+
+        for record in &self.records {
+            if !devices.is_empty() && !devices.contains(&record.device) {
+                continue;
+            }
+
+            let time = if absolute {
+                format!("{:>20}", record.timestamp)
+            } else {
+                let nanos = record.timestamp - origin;
+                format!("{:>6}.{:09}", nanos.div_euclid(1_000_000_000), nanos.rem_euclid(1_000_000_000))
+            };
+            let hex: Vec<String> = record.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+            let parser = parsers.entry((record.device.as_str(), record.port)).or_default();
+            let opcode = match record.opcode {
+                AMIDI_OPCODE_DATA => "data",
+                AMIDI_OPCODE_FLUSH => {
+                    parser.reset();
+                    "flush"
+                }
+                _ => "?",
+            };
+
+            let mut decoded = Vec::new();
+            let dropped     = parser.dropped_bytes();
+            parser.feed(&record.bytes, |message| decoded.push(message.to_string()));
+            if parser.dropped_bytes() > dropped {
+                decoded.push(format!("<{} stray byte(s)>", parser.dropped_bytes() - dropped));
+            }
+            if parser.in_sysex() {
+                decoded.push("<sysex continues>".to_owned());
+            }
+
+            lines.push(format!(
+                "{} {}:{} {:<5} {:<24} {}",
+                time,
+                record.device,
+                record.port,
+                opcode,
+                hex.join(" "),
+                decoded.join(" | ")
+            ));
+        }
+
+        lines
+    }
+
+    /// A virtual device whose output ports hand back `device`'s packets, in
+    /// capture order, through the normal `MidiOutputPort::receive` path.
+    pub fn replay(&self, device: &str) -> VirtualMidiDevice {
+        let records: Vec<&MidiCaptureRecord> = self.records.iter().filter(|r| r.device == device).collect();
+        let num_ports = records.iter().map(|r| r.port.max(0) as usize + 1).max().unwrap_or(0);
+
+        info!("replaying {} packet(s) from {:?} on {} port(s)", records.len(), device, num_ports);
+        let virtual_device = VirtualMidiDevice::new(0, num_ports);
+        for record in records {
+            virtual_device.output_port(record.port.max(0) as usize).push_packet(VirtualReceivedPacket {
+                opcode:    record.opcode,
+                bytes:     record.bytes.clone(),
+                timestamp: record.timestamp,
+            });
+        }
+        virtual_device
+    }
+}
+
+struct CaptureReader<'a> {
+    bytes:  &'a [u8],
+    offset: usize,
+}
+
+impl<'a> CaptureReader<'a> {
+    fn take_slice(&mut self, len: usize) -> Result<&'a [u8], MidiCaptureError> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(MidiCaptureError::Truncated { offset: self.offset });
+        };
+        let slice   = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], MidiCaptureError> {
+        Ok(self.take_slice(N)?.try_into().unwrap())
+    }
+}
+
+/// Capture state attached to one `MidiOutputPort`.
+#[derive(Debug)]
+pub(crate) struct OutputPortCapture {
+    device:  String,
+    capture: MidiCapture,
+}
+
+impl<'lib> MidiOutputPort<'lib> {
+    /// Record every packet received from now on, by
+    /// [`receive`](Self::receive) or anything built on it, such as
+    /// [`drain`](Self::drain). `device` identifies the device in the
+    /// records. Starting again discards what was captured so far.
+    pub fn start_capture(&self, device: impl Into<String>) {
+        let device = device.into();
+        info!("capturing output port {} as {:?}", self.port_number(), device);
+        *self.capture.borrow_mut() = Some(OutputPortCapture { device, capture: MidiCapture::new() });
+    }
+
+    /// A copy of everything captured so far, if capturing.
+    pub fn capture(&self) -> Option<MidiCapture> {
+        self.capture.borrow().as_ref().map(|capture| capture.capture.clone())
+    }
+
+    /// Stop capturing and return what was recorded.
+    pub fn stop_capture(&self) -> Option<MidiCapture> {
+        self.capture.borrow_mut().take().map(|capture| capture.capture)
+    }
+
+    /// Called with every successful receive. Empty receives are not
+    /// recorded.
+    pub(crate) fn observe_received(&self, opcode: i32, bytes: &[u8], timestamp: i64) {
+        if bytes.is_empty() && opcode != AMIDI_OPCODE_FLUSH {
+            return;
+        }
+        if let Some(capture) = self.capture.borrow_mut().as_mut() {
+            capture.capture.records.push(MidiCaptureRecord {
+                device: capture.device.clone(),
+                port: self.port_number(),
+                opcode,
+                timestamp,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_midi_capture {
+    use super::*;
+
+    fn receive_all(port: &MidiOutputPort) -> Vec<(i32, Vec<u8>, i64)> {
+        let mut out    = Vec::new();
+        let mut buffer = [0u8; 64];
+        loop {
+            let (mut opcode, mut n, mut ts) = (0, 0, 0);
+            port.receive(&mut opcode, &mut buffer, &mut n, &mut ts).unwrap();
+            if n == 0 && opcode != AMIDI_OPCODE_FLUSH {
+                return out;
+            }
+            out.push((opcode, buffer[..n].to_vec(), ts));
+        }
+    }
+
+    #[test]
+    fn test_capture_round_trips_and_replays() {
+        let virtual_device = VirtualMidiDevice::new(0, 2);
+        virtual_device.output_port(1).push(vec![0x90, 60, 100], 1_000);
+        virtual_device.output_port(1).push(vec![0x80, 60, 0], 2_000);
+        let device = virtual_device.open();
+        let port   = device.open_output_port(1).unwrap();
+
+        port.start_capture("Keystation 49 (USB)");
+        assert_eq!(port.drain(|_| {}).unwrap(), 2);
+        let mut capture = port.stop_capture().unwrap();
+        capture.records.push(MidiCaptureRecord {
+            device: "other".into(), port: 0, opcode: AMIDI_OPCODE_DATA, timestamp: 5, bytes: vec![0xf8],
+        });
+        assert_eq!(capture.records.len(), 3);
+        assert_eq!(capture.records[0].port, 1);
+        assert_eq!(capture.devices(), vec!["Keystation 49 (USB)", "other"]);
+
+        assert_eq!(MidiCapture::parse(&capture.to_binary().unwrap()).unwrap(), capture);
+        assert_eq!(MidiCapture::parse(capture.to_json_lines().as_bytes()).unwrap(), capture);
+
+        let replay = capture.replay("Keystation 49 (USB)").open();
+        assert_eq!(replay.num_output_ports(), 2);
+        let port = replay.open_output_port(1).unwrap();
+        assert_eq!(
+            receive_all(&port),
+            vec![(1, vec![0x90, 60, 100], 1_000), (1, vec![0x80, 60, 0], 2_000)]
+        );
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let capture = MidiCapture {
+            records: vec![MidiCaptureRecord { device: "d".into(), port: 0, opcode: 1, timestamp: 0, bytes: vec![0xf8] }],
+        };
+        let bytes = capture.to_binary().unwrap();
+        assert!(matches!(MidiCapture::from_binary(b"nope"), Err(MidiCaptureError::BadMagic)));
+        assert!(matches!(
+            MidiCapture::from_binary(&bytes[..bytes.len() - 1]),
+            Err(MidiCaptureError::Truncated { .. })
+        ));
+        assert!(matches!(
+            MidiCapture::from_json_lines("\n{\"device\":1}\n"),
+            Err(MidiCaptureError::Json { line: 2, .. })
+        ));
+
+        // The device record's id (right after the version and tag) is 0.
+        let mut renumbered = bytes.clone();
+        renumbered[11] = 1;
+        assert!(matches!(
+            MidiCapture::from_binary(&renumbered),
+            Err(MidiCaptureError::UnexpectedDeviceId { offset: 10, id: 1, expected: 0 })
+        ));
+
+        let mut unencodable = capture.clone();
+        unencodable.records.push(MidiCaptureRecord { device: "d".into(), port: 70_000, opcode: 1, timestamp: 0, bytes: vec![] });
+        assert!(matches!(unencodable.to_binary(), Err(MidiCaptureError::DoesNotFit { record: 1, what: "port" })));
+        unencodable.records[1] = MidiCaptureRecord { device: "x".repeat(70_000), port: 0, opcode: 1, timestamp: 0, bytes: vec![] };
+        assert!(matches!(unencodable.to_binary(), Err(MidiCaptureError::DoesNotFit { record: 1, what: "device name length" })));
+    }
+}
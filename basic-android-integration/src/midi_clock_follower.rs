@@ -0,0 +1,275 @@
+// ---------------- [ File: basic-android-integration/src/midi_clock_follower.rs ]
+crate::ix!();
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// MIDI clock resolution: 24 clocks per quarter note, 6 per sixteenth.
+pub const MIDI_CLOCKS_PER_BEAT:      u64 = 24;
+pub const MIDI_CLOCKS_PER_SIXTEENTH: u64 = 6;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct MidiClockFollowerOptions {
+    /// Beats per bar for [`MidiClockEvent::Bar`].
+    beats_per_bar: u32,
+    /// Number of clock intervals the tempo estimate averages over; larger
+    /// is steadier, smaller follows tempo changes faster.
+    tempo_window: usize,
+    /// A gap between clocks longer than this means the clock stopped; the
+    /// tempo estimate starts over.
+    clock_timeout: Duration,
+}
+
+impl Default for MidiClockFollowerOptions {
+    fn default() -> Self {
+        Self {
+            beats_per_bar: 4,
+            tempo_window:  MIDI_CLOCKS_PER_BEAT as usize,
+            clock_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MidiTransportState {
+    #[default]
+    Stopped,
+    Playing,
+}
+
+/// What a [`MidiClockFollower`] reports. Timestamps are those of the
+/// message that caused the event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiClockEvent {
+    Started { timestamp: i64 },
+    Continued { timestamp: i64, sixteenths: u64 },
+    Stopped { timestamp: i64, sixteenths: u64 },
+    /// Song Position Pointer moved the transport.
+    Located { sixteenths: u64 },
+    /// The clock that starts quarter note `beat` (counting from 0).
+    Beat { timestamp: i64, beat: u64 },
+    /// The clock that starts bar `bar`; always follows the matching `Beat`.
+    Bar { timestamp: i64, bar: u64 },
+}
+
+/// Follows an external MIDI clock: tempo from the clock timestamps and the
+/// transport from Start/Continue/Stop and Song Position Pointer.
+///
+/// As the spec has it, Start and Continue arm the transport and the clock
+/// after them plays the current position; every further clock advances it.
+#[derive(Debug, Clone)]
+pub struct MidiClockFollower {
+    options:   MidiClockFollowerOptions,
+    parser:    MidiStreamParser,
+    state:     MidiTransportState,
+    /// Position in clocks of the clock about to play (armed) or last played.
+    position:  u64,
+    armed:     bool,
+    clocks:    VecDeque<i64>,
+}
+
+impl Default for MidiClockFollower {
+    fn default() -> Self {
+        Self::new(MidiClockFollowerOptions::default())
+    }
+}
+
+impl MidiClockFollower {
+    pub fn new(options: MidiClockFollowerOptions) -> Self {
+        Self {
+            options,
+            parser:   MidiStreamParser::new(),
+            state:    MidiTransportState::Stopped,
+            position: 0,
+            armed:    false,
+            clocks:   VecDeque::new(),
+        }
+    }
+
+    pub fn options(&self) -> &MidiClockFollowerOptions {
+        &self.options
+    }
+
+    pub fn transport(&self) -> MidiTransportState {
+        self.state
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == MidiTransportState::Playing
+    }
+
+    /// Position in MIDI clocks.
+    pub fn position_clocks(&self) -> u64 {
+        self.position
+    }
+
+    /// Position in sixteenths (MIDI beats), as Song Position Pointer counts.
+    pub fn position_sixteenths(&self) -> u64 {
+        self.position / MIDI_CLOCKS_PER_SIXTEENTH
+    }
+
+    /// Smoothed clock interval in nanoseconds, once two clocks have arrived
+    /// within the timeout of each other.
+    pub fn clock_interval_nanos(&self) -> Option<f64> {
+        let (first, last) = (self.clocks.front()?, self.clocks.back()?);
+        (self.clocks.len() > 1).then(|| (last - first) as f64 / (self.clocks.len() - 1) as f64)
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        self.clock_interval_nanos().filter(|interval| *interval > 0.0).map(|interval| 60e9 / (interval * MIDI_CLOCKS_PER_BEAT as f64))
+    }
+
+    /// Forget tempo and transport, as if nothing had been received.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.options.clone());
+    }
+
+    /// Feed one packet received at `timestamp`.
+    pub fn feed(&mut self, bytes: &[u8], timestamp: i64, mut sink: impl FnMut(MidiClockEvent)) {
+        let mut messages = Vec::new();
+        self.parser.feed(bytes, |message| messages.push(message));
+        for message in messages {
+            self.observe(&message, timestamp, &mut sink);
+        }
+    }
+
+    /// Feed every packet pending on `port`, stamped with its receive time.
+    pub fn poll(&mut self, port: &MidiOutputPort, mut sink: impl FnMut(MidiClockEvent)) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, timestamp } => self.feed(bytes, timestamp, &mut sink),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage, timestamp: i64, mut sink: impl FnMut(MidiClockEvent)) {
+        match *message {
+            MidiMessage::TimingClock => {
+                self.measure(timestamp);
+                if self.is_playing() {
+                    self.advance(timestamp, &mut sink);
+                }
+            }
+            MidiMessage::Start => {
+                self.state    = MidiTransportState::Playing;
+                self.position = 0;
+                self.armed    = true;
+                sink(MidiClockEvent::Started { timestamp });
+            }
+            MidiMessage::Continue => {
+                self.state = MidiTransportState::Playing;
+                self.armed = true;
+                sink(MidiClockEvent::Continued { timestamp, sixteenths: self.position_sixteenths() });
+            }
+            MidiMessage::Stop if self.is_playing() => {
+                self.state = MidiTransportState::Stopped;
+                // A stop between clocks leaves the next clock's position.
+                if !self.armed {
+                    self.position += 1;
+                }
+                self.armed = false;
+                sink(MidiClockEvent::Stopped { timestamp, sixteenths: self.position_sixteenths() });
+            }
+            MidiMessage::SongPosition(sixteenths) => {
+                self.position = sixteenths.get() as u64 * MIDI_CLOCKS_PER_SIXTEENTH;
+                self.armed    = self.is_playing();
+                sink(MidiClockEvent::Located { sixteenths: sixteenths.get() as u64 });
+            }
+            _ => {}
+        }
+    }
+
+    fn measure(&mut self, timestamp: i64) {
+        let timeout = self.options.clock_timeout.as_nanos() as i64;
+        if self.clocks.back().is_some_and(|last| timestamp - last > timeout || timestamp < *last) {
+            self.clocks.clear();
+        }
+        self.clocks.push_back(timestamp);
+        while self.clocks.len() > self.options.tempo_window.max(1) + 1 {
+            self.clocks.pop_front();
+        }
+    }
+
+    fn advance(&mut self, timestamp: i64, sink: &mut impl FnMut(MidiClockEvent)) {
+        if self.armed {
+            self.armed = false;
+        } else {
+            self.position += 1;
+        }
+        if self.position.is_multiple_of(MIDI_CLOCKS_PER_BEAT) {
+            let beat = self.position / MIDI_CLOCKS_PER_BEAT;
+            sink(MidiClockEvent::Beat { timestamp, beat });
+            let beats_per_bar = self.options.beats_per_bar.max(1) as u64;
+            if beat.is_multiple_of(beats_per_bar) {
+                sink(MidiClockEvent::Bar { timestamp, bar: beat / beats_per_bar });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_midi_clock_follower {
+    use super::*;
+
+    /// 120 BPM: 24 clocks per 500ms.
+    const INTERVAL: i64 = 500_000_000 / 24;
+
+    #[test]
+    fn test_tempo_estimate_smooths_jitter_and_restarts_after_gap() {
+        let mut follower = MidiClockFollower::default();
+        assert_eq!(follower.bpm(), None);
+        for n in 0..48 {
+            let jitter = if n % 2 == 0 { 1_500_000 } else { -1_500_000 };
+            follower.observe(&MidiMessage::TimingClock, n * INTERVAL + jitter, |_| {});
+        }
+        assert!((follower.bpm().unwrap() - 120.0).abs() < 0.5, "{:?}", follower.bpm());
+
+        follower.observe(&MidiMessage::TimingClock, 60_000_000_000, |_| {});
+        assert_eq!(follower.bpm(), None);
+        follower.observe(&MidiMessage::TimingClock, 60_000_000_000 + 2 * INTERVAL, |_| {});
+        assert!((follower.bpm().unwrap() - 60.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_transport_beats_and_bars_from_port() {
+        let options = MidiClockFollowerOptionsBuilder::default().beats_per_bar(2u32).build().unwrap();
+        let mut follower = MidiClockFollower::new(options);
+
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let output = virtual_device.output_port(0);
+        output.push(vec![0xfa], 0);
+        for n in 0..=48 {
+            output.push(vec![0xf8], n * INTERVAL);
+        }
+        output.push(vec![0xfc], 49 * INTERVAL);
+        let device = virtual_device.open();
+        let port   = device.open_output_port(0).unwrap();
+
+        let mut events = Vec::new();
+        follower.poll(&port, |event| events.push(event)).unwrap();
+        assert_eq!(events, vec![
+            MidiClockEvent::Started { timestamp: 0 },
+            MidiClockEvent::Beat { timestamp: 0, beat: 0 },
+            MidiClockEvent::Bar { timestamp: 0, bar: 0 },
+            MidiClockEvent::Beat { timestamp: 24 * INTERVAL, beat: 1 },
+            MidiClockEvent::Beat { timestamp: 48 * INTERVAL, beat: 2 },
+            MidiClockEvent::Bar { timestamp: 48 * INTERVAL, bar: 1 },
+            MidiClockEvent::Stopped { timestamp: 49 * INTERVAL, sixteenths: 8 },
+        ]);
+        assert_eq!(follower.transport(), MidiTransportState::Stopped);
+
+        // Relocate to bar 2 (sixteenth 16) and continue from there.
+        events.clear();
+        for message in [MidiMessage::song_position(U14::new(16).unwrap()), MidiMessage::Continue, MidiMessage::TimingClock] {
+            follower.observe(&message, 100 * INTERVAL, |event| events.push(event));
+        }
+        assert_eq!(events, vec![
+            MidiClockEvent::Located { sixteenths: 16 },
+            MidiClockEvent::Continued { timestamp: 100 * INTERVAL, sixteenths: 16 },
+            MidiClockEvent::Beat { timestamp: 100 * INTERVAL, beat: 4 },
+            MidiClockEvent::Bar { timestamp: 100 * INTERVAL, bar: 2 },
+        ]);
+        assert_eq!(follower.position_clocks(), 96);
+    }
+}
@@ -0,0 +1,346 @@
+// ---------------- [ File: basic-android-integration/src/midi_clock_master.rs ]
+crate::ix!();
+
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct MidiClockMasterOptions {
+    /// Clamped to [`MIDI_CLOCK_MIN_BPM`]..=[`MIDI_CLOCK_MAX_BPM`]; a NaN or
+    /// infinite value falls back to 120.
+    bpm: f64,
+    /// See [`MidiClockMaster::set_swing`].
+    swing: f64,
+    /// How far ahead of the monotonic clock ticks are handed to the ports.
+    /// `pump` has to run at least this often.
+    lookahead: Duration,
+    /// A tick already this far overdue when `pump` gets to it (the pump ran
+    /// late) is sent now and the grid restarts from it, so one interval is
+    /// stretched instead of a burst of catch-up ticks following.
+    max_jitter: Duration,
+    /// Keep sending F8 while the transport is stopped, so followers keep
+    /// their tempo lock.
+    clock_while_stopped: bool,
+}
+
+impl Default for MidiClockMasterOptions {
+    fn default() -> Self {
+        Self {
+            bpm:                 120.0,
+            swing:               0.0,
+            lookahead:           Duration::from_millis(20),
+            max_jitter:          Duration::from_millis(1),
+            clock_while_stopped: true,
+        }
+    }
+}
+
+/// Tempo range of [`MidiClockMaster`]. Below the minimum a clock interval
+/// is over two seconds, which followers take for a stopped clock anyway.
+pub const MIDI_CLOCK_MIN_BPM: f64 = 1.0;
+pub const MIDI_CLOCK_MAX_BPM: f64 = 1000.0;
+
+fn clamp_bpm(bpm: f64) -> Result<f64, MidiValueError> {
+    if !bpm.is_finite() {
+        return Err(MidiValueError::NotFinite { what: "bpm" });
+    }
+    Ok(bpm.clamp(MIDI_CLOCK_MIN_BPM, MIDI_CLOCK_MAX_BPM))
+}
+
+/// What one [`MidiClockMaster::pump`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MidiClockMasterStats {
+    /// Clock slots scheduled (with or without an F8 in them).
+    pub slots:       usize,
+    /// Slots that were overdue by more than `max_jitter`.
+    pub late:        usize,
+    pub send_errors: usize,
+}
+
+/// Leads external gear: 24 PPQN clock plus Start, Stop, Continue and Song
+/// Position Pointer, sent to any number of input ports.
+///
+/// Like [`SmfPlayer`] it owns no thread: [`pump`](Self::pump) stamps every
+/// tick due within the lookahead with its exact time and hands it to
+/// `send_with_timestamp`, so tick timing does not depend on how punctual the
+/// pump is. Transport messages and tempo or swing changes take effect at
+/// the first tick not yet handed out, i.e. up to one lookahead later, and
+/// go out in the same packet as that tick, ahead of its F8.
+pub struct MidiClockMaster<'p, 'lib, C: MonotonicClock = SystemMonotonicClock> {
+    clock:               C,
+    destinations:        Vec<&'p MidiInputPort<'lib>>,
+    bpm:                 f64,
+    swing:               f64,
+    lookahead:           i64,
+    max_jitter:          i64,
+    clock_while_stopped: bool,
+    /// Wall time and phase of a tick the grid is measured from; set on first use.
+    anchor:              Option<(i64, u64)>,
+    /// Phase of the next tick: its song position while playing, a
+    /// continuation of the last one while stopped. Swing follows it.
+    phase:               u64,
+    playing:             bool,
+    /// Song position while stopped.
+    position:            u64,
+    /// Transport bytes to go out with the next tick.
+    pending:             Vec<u8>,
+}
+
+impl<'p, 'lib, C: MonotonicClock> MidiClockMaster<'p, 'lib, C> {
+    pub fn new(options: MidiClockMasterOptions, clock: C) -> Self {
+        let bpm = clamp_bpm(options.bpm).unwrap_or_else(|e| {
+            warn!("MidiClockMaster: {}, using 120", e);
+            MidiClockMasterOptions::default().bpm
+        });
+        Self {
+            clock,
+            destinations:        Vec::new(),
+            bpm,
+            swing:               options.swing.clamp(0.0, 0.9),
+            lookahead:           options.lookahead.as_nanos() as i64,
+            max_jitter:          options.max_jitter.as_nanos() as i64,
+            clock_while_stopped: options.clock_while_stopped,
+            anchor:              None,
+            phase:               0,
+            playing:             false,
+            position:            0,
+            pending:             Vec::new(),
+        }
+    }
+
+    pub fn add_destination(&mut self, port: &'p MidiInputPort<'lib>) {
+        self.destinations.push(port);
+    }
+
+    pub fn remove_destination(&mut self, port: &MidiInputPort<'lib>) {
+        self.destinations.retain(|destination| !std::ptr::eq(*destination, port));
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Takes effect from the next tick not yet sent, wherever in the bar.
+    /// Clamped to [`MIDI_CLOCK_MIN_BPM`]..=[`MIDI_CLOCK_MAX_BPM`]; NaN and
+    /// infinities are rejected and leave the tempo as it was.
+    pub fn set_bpm(&mut self, bpm: f64) -> Result<(), MidiValueError> {
+        let bpm = clamp_bpm(bpm)?;
+        self.rephase(self.phase);
+        self.bpm = bpm;
+        Ok(())
+    }
+
+    pub fn swing(&self) -> f64 {
+        self.swing
+    }
+
+    /// Delay every second sixteenth by this fraction of a sixteenth: 0 is
+    /// straight, 1/3 a triplet shuffle. Ticks inside each pair of sixteenths
+    /// are stretched and squeezed to match; clamped to 0..=0.9.
+    pub fn set_swing(&mut self, swing: f64) {
+        self.rephase(self.phase);
+        self.swing = swing.clamp(0.0, 0.9);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Song position, in MIDI clocks, of the next tick.
+    pub fn position_clocks(&self) -> u64 {
+        if self.playing { self.phase } else { self.position }
+    }
+
+    pub fn start(&mut self) {
+        self.pending.push(0xfa);
+        self.rephase(0);
+        self.playing  = true;
+        self.position = 0;
+    }
+
+    pub fn stop(&mut self) {
+        if self.playing {
+            self.pending.push(0xfc);
+            self.position = self.phase;
+            self.playing  = false;
+        }
+    }
+
+    pub fn continue_playback(&mut self) {
+        if !self.playing {
+            self.pending.push(0xfb);
+            self.rephase(self.position);
+            self.playing = true;
+        }
+    }
+
+    /// Send a Song Position Pointer; `sixteenths` from the start of the song.
+    pub fn locate(&mut self, sixteenths: U14) {
+        MidiMessage::song_position(sixteenths).write_to(&mut self.pending);
+        self.position = sixteenths.get() as u64 * MIDI_CLOCKS_PER_SIXTEENTH;
+        if self.playing {
+            self.rephase(self.position);
+        }
+    }
+
+    pub fn pump(&mut self) -> MidiClockMasterStats {
+        let now      = self.clock.now_nanos();
+        let deadline = now + self.lookahead;
+        let mut stats = MidiClockMasterStats::default();
+        loop {
+            let mut timestamp = self.next_tick_nanos();
+            if timestamp > deadline {
+                return stats;
+            }
+            if timestamp < now - self.max_jitter {
+                self.anchor = Some((now, self.phase));
+                timestamp   = now;
+                stats.late += 1;
+            }
+
+            let mut bytes = std::mem::take(&mut self.pending);
+            if self.playing || self.clock_while_stopped {
+                bytes.push(0xf8);
+            }
+            if !bytes.is_empty() {
+                for port in &self.destinations {
+                    if let Err(status) = port.send_with_timestamp(&bytes, timestamp) {
+                        warn!("midi clock: send to port {} failed: {}", port.port_number(), status);
+                        stats.send_errors += 1;
+                    }
+                }
+            }
+            stats.slots += 1;
+            self.phase  += 1;
+        }
+    }
+
+    /// Wall time of the next tick.
+    pub fn next_tick_nanos(&mut self) -> i64 {
+        let (wall, phase) = *self.anchor.get_or_insert((self.clock.now_nanos() + self.lookahead, self.phase));
+        wall + (self.grid_offset(self.phase) - self.grid_offset(phase)).round() as i64
+    }
+
+    /// Restart the grid at the next tick with a new phase.
+    fn rephase(&mut self, phase: u64) {
+        let wall    = self.next_tick_nanos();
+        self.anchor = Some((wall, phase));
+        self.phase  = phase;
+    }
+
+    /// Nanoseconds from phase 0 to `phase` at the current tempo and swing.
+    fn grid_offset(&self, phase: u64) -> f64 {
+        let clock = 60e9 / (self.bpm * MIDI_CLOCKS_PER_BEAT as f64);
+        let half  = MIDI_CLOCKS_PER_SIXTEENTH;
+        let (pairs, within) = (phase / (2 * half), phase % (2 * half));
+        let within = if within <= half {
+            within as f64 * clock * (1.0 + self.swing)
+        } else {
+            half as f64 * clock * (1.0 + self.swing) + (within - half) as f64 * clock * (1.0 - self.swing)
+        };
+        pairs as f64 * (2 * half) as f64 * clock + within
+    }
+}
+
+#[cfg(test)]
+mod test_midi_clock_master {
+    use super::*;
+
+    const MS: i64 = 1_000_000;
+
+    /// Pump every 5ms, well inside the default 20ms lookahead.
+    fn run_until(master: &mut MidiClockMaster<'_, '_, VirtualMonotonicClock>, clock: &VirtualMonotonicClock, until: i64) {
+        while clock.now_nanos() < until {
+            clock.advance(5 * MS);
+            let stats = master.pump();
+            assert_eq!((stats.late, stats.send_errors), (0, 0));
+        }
+    }
+
+    fn ticks(port: &VirtualInputPort) -> Vec<i64> {
+        port.sent().iter().filter(|packet| packet.bytes.ends_with(&[0xf8])).map(|packet| packet.timestamp.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_clock_transport_and_tempo_change_to_two_ports() {
+        let virtual_device = VirtualMidiDevice::new(2, 0);
+        let device  = virtual_device.open();
+        let (a, b)  = (device.open_input_port(0).unwrap(), device.open_input_port(1).unwrap());
+        let clock   = VirtualMonotonicClock::starting_at(0);
+        let options = MidiClockMasterOptionsBuilder::default().clock_while_stopped(false).build().unwrap();
+        let mut master = MidiClockMaster::new(options, clock.clone());
+        master.add_destination(&a);
+        master.add_destination(&b);
+
+        master.start();
+        assert_eq!(master.pump().slots, 1);
+        run_until(&mut master, &clock, 500 * MS);
+        master.set_bpm(60.0).unwrap();
+        run_until(&mut master, &clock, 1000 * MS);
+        master.stop();
+        run_until(&mut master, &clock, 1200 * MS);
+
+        // 120 BPM: a tick every 20.83ms up to ~520ms; 60 BPM after that.
+        let sent = ticks(&virtual_device.input_port(0));
+        assert_eq!(sent[..3], [20 * MS, 20 * MS + 20_833_333, 20 * MS + 41_666_667]);
+        let slow = sent.windows(2).map(|w| w[1] - w[0]).skip_while(|d| (d - 20_833_333).abs() <= 1).collect::<Vec<_>>();
+        assert!(slow.iter().all(|d| (d - 41_666_667).abs() <= 1), "{:?}", slow);
+        assert_eq!(virtual_device.input_port(0).sent(), virtual_device.input_port(1).sent());
+
+        let all = virtual_device.input_port(0).sent();
+        assert_eq!(all[0].bytes, vec![0xfa, 0xf8]);
+        assert_eq!(all.last().unwrap().bytes, vec![0xfc]);
+        assert_eq!(master.position_clocks(), sent.len() as u64);
+    }
+
+    #[test]
+    fn test_swing_and_late_pump() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(0);
+        let options = MidiClockMasterOptionsBuilder::default().swing(1.0 / 3.0).lookahead(Duration::from_millis(5)).build().unwrap();
+        let mut master = MidiClockMaster::new(options, clock.clone());
+        master.add_destination(&port);
+
+        master.start();
+        // 125 BPM: 20ms clocks, 120ms sixteenths, swung to 160ms + 80ms;
+        // the grid starts one lookahead after start().
+        master.set_bpm(125.0).unwrap();
+        for t in (0..=240).step_by(2) {
+            clock.set(t * MS);
+            assert_eq!(master.pump().late, 0);
+        }
+        let sent = ticks(&virtual_device.input_port(0));
+        assert_eq!(sent.len(), 13);
+        assert_eq!((sent[0], sent[6], sent[12]), (5 * MS, 165 * MS, 245 * MS));
+
+        clock.set(1000 * MS);
+        assert_eq!(master.pump(), MidiClockMasterStats { slots: 1, late: 1, send_errors: 0 });
+        assert_eq!(*ticks(&virtual_device.input_port(0)).last().unwrap(), 1000 * MS);
+    }
+
+    #[test]
+    fn test_bpm_is_validated() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device  = virtual_device.open();
+        let port    = device.open_input_port(0).unwrap();
+        let clock   = VirtualMonotonicClock::starting_at(0);
+        let options = MidiClockMasterOptionsBuilder::default().bpm(f64::NAN).build().unwrap();
+        let mut master = MidiClockMaster::new(options, clock.clone());
+        master.add_destination(&port);
+        assert_eq!(master.bpm(), 120.0);
+
+        assert_eq!(master.set_bpm(f64::INFINITY), Err(MidiValueError::NotFinite { what: "bpm" }));
+        assert_eq!(master.bpm(), 120.0);
+        master.set_bpm(-5.0).unwrap();
+        assert_eq!(master.bpm(), MIDI_CLOCK_MIN_BPM);
+        master.set_bpm(0.0).unwrap();
+
+        // One clock at 1 BPM is 2.5 s: a pump 100 ms in sends just the first.
+        master.start();
+        run_until(&mut master, &clock, 100 * MS);
+        assert_eq!(ticks(&virtual_device.input_port(0)).len(), 1);
+    }
+}
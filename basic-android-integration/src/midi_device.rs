@@ -5,6 +5,7 @@ crate::ix!();
 pub struct MidiDevice<'lib> {
     library: Arc<AmidiLibrary>,
     raw_device: *mut AMidiDevice,
+    registry: Arc<MidiPortRegistry>,
     _marker: std::marker::PhantomData<&'lib AmidiLibrary>,
 }
 
@@ -22,7 +23,7 @@ impl<'lib> MidiDevice<'lib> {
         trace!("Creating MidiDevice fromJava...");
 
         let mut raw_dev: *mut AMidiDevice = std::ptr::null_mut();
-        let status = (library.amidi_device_from_java)(env, midi_device_obj, &mut raw_dev);
+        let status = unsafe { (library.amidi_device_from_java)(env, midi_device_obj, &mut raw_dev) };
         if status != media_status_t(0) {
             error!("AMidiDevice_fromJava failed with status: {:?}", status);
             return Err(status);
@@ -32,6 +33,7 @@ impl<'lib> MidiDevice<'lib> {
         Ok(Self {
             library,
             raw_device: raw_dev,
+            registry: Arc::new(MidiPortRegistry::default()),
             _marker: std::marker::PhantomData,
         })
     }
@@ -56,23 +58,62 @@ impl<'lib> MidiDevice<'lib> {
         unsafe { (self.library.amidi_device_get_default_protocol)(self.raw_device) }
     }
 
+    /// Describe every port on the device, inputs first, with whether it is
+    /// currently open through this `MidiDevice`.
+    pub fn ports(&self) -> impl Iterator<Item = MidiPortInfo> + '_ {
+        let inputs  = (0..self.num_input_ports().max(0) as i32).map(|n| (MidiPortDirection::Input, n));
+        let outputs = (0..self.num_output_ports().max(0) as i32).map(|n| (MidiPortDirection::Output, n));
+
+        inputs.chain(outputs).map(move |(direction, port_number)| MidiPortInfo {
+            direction,
+            port_number,
+            open: self.registry.is_open(direction, port_number),
+        })
+    }
+
+    /// Check `port_number` against the device's port count and claim it in
+    /// the open-port registry. The claim is released if the caller drops it,
+    /// e.g. because the native open then fails.
+    fn claim_port(
+        &self,
+        direction:   MidiPortDirection,
+        port_number: i32,
+    ) -> Result<MidiPortClaim, MidiPortError> {
+        let num_ports = match direction {
+            MidiPortDirection::Input  => self.num_input_ports(),
+            MidiPortDirection::Output => self.num_output_ports(),
+        };
+        if port_number < 0 || port_number as isize >= num_ports {
+            error!("{} port #{} out of range (device has {})", direction, port_number, num_ports);
+            return Err(MidiPortError::PortOutOfRange { direction, port_number, num_ports });
+        }
+        self.registry.claim(direction, port_number)
+    }
+
     pub fn open_output_port(
         &self,
         port_number: i32,
-    ) -> Result<MidiOutputPort<'lib>, media_status_t> {
+    ) -> Result<MidiOutputPort<'lib>, MidiPortError> {
         trace!("Opening MIDI output port #{}...", port_number);
+        let claim = self.claim_port(MidiPortDirection::Output, port_number)?;
         let mut raw_out: *mut AMidiOutputPort = std::ptr::null_mut();
         let status = unsafe {
             (self.library.amidi_output_port_open)(self.raw_device, port_number, &mut raw_out)
         };
         if status != media_status_t(0) {
             error!("AMidiOutputPort_open failed with status: {:?}", status);
-            return Err(status);
+            return Err(MidiPortError::Native {
+                direction: MidiPortDirection::Output,
+                port_number,
+                status,
+            });
         }
         debug!("MidiOutputPort pointer = {:p}", raw_out);
         Ok(MidiOutputPort {
             library: self.library.clone(),
             raw_out,
+            claim,
+            capture: Default::default(),
             _marker: std::marker::PhantomData,
         })
     }
@@ -80,20 +121,28 @@ impl<'lib> MidiDevice<'lib> {
     pub fn open_input_port(
         &self,
         port_number: i32,
-    ) -> Result<MidiInputPort<'lib>, media_status_t> {
+    ) -> Result<MidiInputPort<'lib>, MidiPortError> {
         trace!("Opening MIDI input port #{}...", port_number);
+        let claim = self.claim_port(MidiPortDirection::Input, port_number)?;
         let mut raw_in: *mut AMidiInputPort = std::ptr::null_mut();
         let status = unsafe {
             (self.library.amidi_input_port_open)(self.raw_device, port_number, &mut raw_in)
         };
         if status != media_status_t(0) {
             error!("AMidiInputPort_open failed with status: {:?}", status);
-            return Err(status);
+            return Err(MidiPortError::Native {
+                direction: MidiPortDirection::Input,
+                port_number,
+                status,
+            });
         }
         debug!("MidiInputPort pointer = {:p}", raw_in);
         Ok(MidiInputPort {
             library: self.library.clone(),
             raw_in,
+            claim,
+            scratch: Default::default(),
+            note_tracking: Default::default(),
             _marker: std::marker::PhantomData,
         })
     }
@@ -5,10 +5,22 @@ crate::ix!();
 pub struct MidiInputPort<'lib> {
     pub(crate) library: Arc<AmidiLibrary>,
     pub(crate) raw_in: *mut AMidiInputPort,
+    /// Holds this port's slot in the device's open-port registry; released
+    /// after the native port is closed.
+    pub(crate) claim: MidiPortClaim,
+    /// Serialization buffer reused by `send_message*`.
+    pub(crate) scratch: std::cell::RefCell<Vec<u8>>,
+    /// Set by `enable_note_tracking`.
+    pub(crate) note_tracking: std::cell::OnceCell<Arc<NoteTracking>>,
     pub(crate) _marker: std::marker::PhantomData<&'lib AmidiLibrary>,
 }
 
 impl<'lib> MidiInputPort<'lib> {
+    /// The port number this port was opened with.
+    pub fn port_number(&self) -> i32 {
+        self.claim.port_number()
+    }
+
     /// Send MIDI data immediately. Returns number of bytes sent if >= 0,
     /// or `Err(isize)` on failure (< 0).
     pub fn send(&self, buffer: &[u8]) -> Result<usize, isize> {
@@ -25,6 +37,7 @@ impl<'lib> MidiInputPort<'lib> {
             Err(ret)
         } else {
             debug!("Sent {} bytes (no timestamp).", ret);
+            self.observe_sent(&buffer[..ret as usize]);
             Ok(ret as usize)
         }
     }
@@ -46,10 +59,35 @@ impl<'lib> MidiInputPort<'lib> {
             Err(ret)
         } else {
             debug!("Sent {} bytes with timestamp={}.", ret, timestamp);
+            self.observe_sent(&buffer[..ret as usize]);
             Ok(ret as usize)
         }
     }
 
+    /// Serialize one message and send it immediately.
+    pub fn send_message(&self, message: &MidiMessage) -> Result<usize, isize> {
+        self.send_messages(std::slice::from_ref(message))
+    }
+
+    /// Serialize several messages back to back and send them in one call.
+    pub fn send_messages(&self, messages: &[MidiMessage]) -> Result<usize, isize> {
+        let mut scratch = self.scratch.borrow_mut();
+        scratch.clear();
+        for message in messages {
+            message.write_to(&mut scratch);
+        }
+        self.send(&scratch)
+    }
+
+    /// Serialize one message and schedule it for `timestamp` (nanoseconds,
+    /// `CLOCK_MONOTONIC`).
+    pub fn send_message_at(&self, message: &MidiMessage, timestamp: i64) -> Result<usize, isize> {
+        let mut scratch = self.scratch.borrow_mut();
+        scratch.clear();
+        message.write_to(&mut scratch);
+        self.send_with_timestamp(&scratch, timestamp)
+    }
+
     /// Flushes any queued data. Returns `Ok(())` if status==0, otherwise `Err(status)`.
     pub fn send_flush(&self) -> Result<(), media_status_t> {
         trace!("Sending FLUSH to MIDI input port...");
@@ -69,6 +107,7 @@ impl<'lib> MidiInputPort<'lib> {
 impl<'lib> Drop for MidiInputPort<'lib> {
     fn drop(&mut self) {
         if !self.raw_in.is_null() {
+            self.shut_down_note_tracking();
             trace!("Dropping MidiInputPort => closing...");
             unsafe {
                 (self.library.amidi_input_port_close)(self.raw_in);
@@ -0,0 +1,224 @@
+// ---------------- [ File: basic-android-integration/src/midi_input_port_note_tracking.rs ]
+crate::ix!();
+
+use std::sync::{Mutex, Once, TryLockError, Weak};
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct NoteTrackingOptions {
+    /// Send the panic sequence when the port is dropped.
+    panic_on_drop:  bool,
+    /// Send the panic sequence from a process-wide panic hook, so a crash
+    /// anywhere in the app doesn't leave notes hanging.
+    panic_on_panic: bool,
+}
+
+impl Default for NoteTrackingOptions {
+    fn default() -> Self {
+        Self {
+            panic_on_drop:  true,
+            panic_on_panic: false,
+        }
+    }
+}
+
+/// Tracking state attached to one `MidiInputPort`.
+///
+/// Shared with the panic hook, which may run on any thread, hence the
+/// mutex and the copied native handle.
+#[derive(Debug)]
+pub(crate) struct NoteTracking {
+    options: NoteTrackingOptions,
+    library: Arc<AmidiLibrary>,
+    raw_in:  NativeInputPort,
+    state:   Mutex<NoteTrackingState>,
+}
+
+#[derive(Debug, Default)]
+struct NoteTrackingState {
+    tracker: NoteTracker,
+    parser:  MidiStreamParser,
+    /// Set under the lock before the native port is closed; the panic hook
+    /// never touches a closed port.
+    closed:  bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NativeInputPort(*mut AMidiInputPort);
+
+// SAFETY: the pointer is only dereferenced by libamidi, whose send calls may
+// be made from any thread, and only while `NoteTrackingState::closed` is
+// false under the state lock.
+unsafe impl Send for NativeInputPort {}
+unsafe impl Sync for NativeInputPort {}
+
+fn lock_state(tracking: &NoteTracking) -> std::sync::MutexGuard<'_, NoteTrackingState> {
+    tracking.state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Ports registered with the panic hook.
+static PANIC_HOOK_PORTS: Mutex<Vec<Weak<NoteTracking>>> = Mutex::new(Vec::new());
+static PANIC_HOOK_INSTALLED: Once = Once::new();
+
+fn install_note_panic_hook() {
+    PANIC_HOOK_INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            silence_registered_ports();
+            previous(info);
+        }));
+    });
+}
+
+fn silence_registered_ports() {
+    // try_lock throughout: a panic while one of these locks is held on this
+    // thread must not deadlock the hook.
+    let ports: Vec<Arc<NoteTracking>> = match PANIC_HOOK_PORTS.try_lock() {
+        Ok(ports) => ports.iter().filter_map(Weak::upgrade).collect(),
+        Err(TryLockError::Poisoned(ports)) => ports.into_inner().iter().filter_map(Weak::upgrade).collect(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+
+    for tracking in ports {
+        let mut state = match tracking.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::Poisoned(state)) => state.into_inner(),
+            Err(TryLockError::WouldBlock) => continue,
+        };
+        if state.closed {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        for message in state.tracker.panic_messages() {
+            message.write_to(&mut bytes);
+        }
+        state.tracker.clear();
+        for range in midi_packet_ranges(&bytes, AMIDI_MAX_PACKET_PAYLOAD) {
+            let chunk = &bytes[range];
+            // SAFETY: the port is open (checked under the lock above).
+            unsafe { (tracking.library.amidi_input_port_send)(tracking.raw_in.0, chunk.as_ptr(), chunk.len()) };
+        }
+    }
+}
+
+impl<'lib> MidiInputPort<'lib> {
+    /// Start tracking sounding notes and pedals in everything sent through
+    /// this port from now on. Calling it again has no effect.
+    pub fn enable_note_tracking(&self, options: NoteTrackingOptions) {
+        let tracking = Arc::new(NoteTracking {
+            options,
+            library: self.library.clone(),
+            raw_in:  NativeInputPort(self.raw_in),
+            state:   Default::default(),
+        });
+        if self.note_tracking.set(tracking.clone()).is_err() {
+            debug!("note tracking already enabled on port {}", self.port_number());
+            return;
+        }
+        info!("note tracking enabled on port {} ({:?})", self.port_number(), tracking.options);
+        if tracking.options.panic_on_panic() {
+            install_note_panic_hook();
+            let mut ports = PANIC_HOOK_PORTS.lock().unwrap_or_else(|e| e.into_inner());
+            ports.retain(|port| port.strong_count() > 0);
+            ports.push(Arc::downgrade(&tracking));
+        }
+    }
+
+    /// A snapshot of what the tracker thinks is sounding, if tracking is on.
+    pub fn note_tracker(&self) -> Option<NoteTracker> {
+        self.note_tracking.get().map(|tracking| lock_state(tracking).tracker.clone())
+    }
+
+    /// Silence the synth: Note Off for every note we sent that is still
+    /// sounding and pedal-up for held pedals, then All Notes Off (CC 123)
+    /// and All Sound Off (CC 120) on every channel. Without tracking only
+    /// the controller sweep is sent.
+    pub fn panic(&self) -> Result<usize, SendAllError> {
+        let messages = match self.note_tracking.get() {
+            Some(tracking) => lock_state(tracking).tracker.panic_messages(),
+            None => NoteTracker::blanket_panic_messages().collect(),
+        };
+        info!("sending panic ({} messages) on port {}", messages.len(), self.port_number());
+        let mut bytes = Vec::new();
+        for message in &messages {
+            message.write_to(&mut bytes);
+        }
+        self.send_all(&bytes)
+    }
+
+    /// Called with the bytes the port accepted.
+    pub(crate) fn observe_sent(&self, bytes: &[u8]) {
+        if let Some(tracking) = self.note_tracking.get() {
+            let state = &mut *lock_state(tracking);
+            let tracker = &mut state.tracker;
+            state.parser.feed(bytes, |message| tracker.observe(&message));
+        }
+    }
+
+    /// Called from `Drop` before the native port is closed.
+    pub(crate) fn shut_down_note_tracking(&self) {
+        let Some(tracking) = self.note_tracking.get() else {
+            return;
+        };
+        if tracking.options.panic_on_drop()
+            && !lock_state(tracking).tracker.is_silent()
+            && let Err(e) = self.panic()
+        {
+            warn!("panic on drop of port {} failed: {}", self.port_number(), e);
+        }
+        lock_state(tracking).closed = true;
+    }
+}
+
+#[cfg(test)]
+mod test_midi_input_port_note_tracking {
+    use super::*;
+
+    fn note_on(note: u8) -> MidiMessage {
+        MidiMessage::note_on(Channel::new(2).unwrap(), U7::new(note).unwrap(), U7::new(90).unwrap())
+    }
+
+    #[test]
+    fn test_tracks_sent_bytes_and_panics_on_drop() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        port.enable_note_tracking(NoteTrackingOptions::default());
+
+        // running status, so the tracker has to parse rather than match packets
+        port.send(&[0x92, 60, 90, 62, 90, 60, 0]).unwrap();
+        port.send_message(&note_on(64)).unwrap();
+        let tracker = port.note_tracker().unwrap();
+        let sounding: Vec<u8> = tracker.sounding_notes(Channel::new(2).unwrap()).map(U7::get).collect();
+        assert_eq!(sounding, vec![62, 64]);
+
+        virtual_device.input_port(0).clear_sent();
+        drop(port);
+        let sent = virtual_device.input_port(0).sent_bytes();
+        assert_eq!(&sent[..6], &[0x82, 62, 0, 0x82, 64, 0]);
+        assert_eq!(sent.len(), 6 + 16 * 6);
+    }
+
+    #[test]
+    fn test_panic_hook_silences_registered_ports() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let options = NoteTrackingOptionsBuilder::default().panic_on_drop(false).panic_on_panic(true).build().unwrap();
+        port.enable_note_tracking(options);
+        port.send_message(&note_on(70)).unwrap();
+        virtual_device.input_port(0).clear_sent();
+
+        let result = std::panic::catch_unwind(|| panic!("simulated crash"));
+        assert!(result.is_err());
+
+        let sent = virtual_device.input_port(0).sent_bytes();
+        assert_eq!(&sent[..3], &[0x82, 70, 0]);
+        assert!(port.note_tracker().unwrap().is_silent());
+
+        virtual_device.input_port(0).clear_sent();
+        drop(port);
+        assert!(virtual_device.input_port(0).sent_bytes().is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+// ---------------- [ File: basic-android-integration/src/midi_input_port_send_all.rs ]
+crate::ix!();
+
+use std::time::Duration;
+
+/// Largest payload libamidi carries in one packet: `AMIDI_PACKET_SIZE` (1024)
+/// minus the opcode and timestamp header (`AMIDI_PACKET_OVERHEAD`, 9).
+pub const AMIDI_MAX_PACKET_PAYLOAD: usize = 1015;
+
+/// How `MidiInputPort::send_all` packetizes and retries.
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct SendAllPolicy {
+    /// Upper bound on the bytes handed to one native send. Whole messages are
+    /// never split across packets; only SysEx is cut to fit. Values below 3
+    /// are treated as 3 so every channel message still fits.
+    max_packet_size: usize,
+
+    /// Consecutive sends accepting zero bytes before the port counts as stalled.
+    max_stalled_attempts: u32,
+
+    /// Sleep after the first zero-byte send; doubles up to `max_backoff`.
+    initial_backoff: Duration,
+    max_backoff:     Duration,
+}
+
+impl Default for SendAllPolicy {
+    fn default() -> Self {
+        Self {
+            max_packet_size:      AMIDI_MAX_PACKET_PAYLOAD,
+            max_stalled_attempts: 8,
+            initial_backoff:      Duration::from_millis(1),
+            max_backoff:          Duration::from_millis(50),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendAllError {
+    /// The port kept accepting nothing for `attempts` tries in a row.
+    Stalled {
+        /// Bytes of the buffer the port did accept.
+        sent:     usize,
+        total:    usize,
+        attempts: u32,
+        waited:   Duration,
+    },
+
+    /// The native send returned a negative status.
+    Native {
+        sent:  usize,
+        total: usize,
+        code:  isize,
+    },
+}
+
+impl std::fmt::Display for SendAllError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendAllError::Stalled { sent, total, attempts, waited } => write!(
+                f,
+                "MIDI input port stalled after {} of {} bytes ({} empty sends over {:?})",
+                sent, total, attempts, waited
+            ),
+            SendAllError::Native { sent, total, code } => write!(
+                f,
+                "MIDI input port send failed with {} after {} of {} bytes",
+                code, sent, total
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SendAllError {}
+
+/// Group a byte stream into packets of at most `max_packet_size` bytes that
+/// only end on message boundaries, except inside SysEx.
+pub fn midi_packet_ranges(bytes: &[u8], max_packet_size: usize) -> Vec<std::ops::Range<usize>> {
+    let max = max_packet_size.max(3);
+    let mut packets = Vec::new();
+    let mut current: Option<std::ops::Range<usize>> = None;
+
+    for segment in midi_byte_segments(bytes) {
+        let range = segment.range.clone();
+
+        if let Some(cur) = &mut current
+            && cur.len() + range.len() <= max
+        {
+            cur.end = range.end;
+            continue;
+        }
+
+        if segment.is_splittable() {
+            let mut start = range.start;
+            if let Some(mut cur) = current.take() {
+                let room = max - cur.len();
+                cur.end += room;
+                start += room;
+                packets.push(cur);
+            }
+            while range.end - start > max {
+                packets.push(start..start + max);
+                start += max;
+            }
+            current = Some(start..range.end);
+        } else {
+            packets.extend(current.take());
+            current = Some(range);
+        }
+    }
+
+    packets.extend(current);
+    packets
+}
+
+impl MidiInputPort<'_> {
+    /// Send every byte of `buffer` with the default [`SendAllPolicy`].
+    pub fn send_all(&self, buffer: &[u8]) -> Result<usize, SendAllError> {
+        self.send_all_with_policy(buffer, &SendAllPolicy::default())
+    }
+
+    /// Send every byte of `buffer`, looping while the native side accepts
+    /// only part of a packet and backing off while it accepts nothing.
+    ///
+    /// The buffer is cut into packets on message boundaries (SysEx is chunked
+    /// to `max_packet_size`), and a partially accepted packet is always
+    /// completed before the next one starts, so the receiver sees an intact
+    /// stream.
+    pub fn send_all_with_policy(&self, buffer: &[u8], policy: &SendAllPolicy) -> Result<usize, SendAllError> {
+        self.send_all_at(buffer, None, policy)
+    }
+
+    /// [`send_all_with_policy`](Self::send_all_with_policy), but every packet
+    /// goes through `send_with_timestamp` when `timestamp` is given.
+    pub fn send_all_at(
+        &self,
+        buffer:    &[u8],
+        timestamp: Option<i64>,
+        policy:    &SendAllPolicy,
+    ) -> Result<usize, SendAllError> {
+        trace!("send_all: {} bytes, timestamp={:?}, policy={:?}", buffer.len(), timestamp, policy);
+        let total = buffer.len();
+
+        for packet in midi_packet_ranges(buffer, policy.max_packet_size()) {
+            let mut offset   = packet.start;
+            let mut stalled  = 0;
+            let mut backoff  = policy.initial_backoff();
+            let mut waited   = Duration::ZERO;
+
+            while offset < packet.end {
+                let chunk  = &buffer[offset..packet.end];
+                let result = match timestamp {
+                    Some(ts) => self.send_with_timestamp(chunk, ts),
+                    None     => self.send(chunk),
+                };
+                match result {
+                    Ok(0) => {
+                        stalled += 1;
+                        if stalled > policy.max_stalled_attempts() {
+                            error!("send_all stalled at byte {} of {}", offset, total);
+                            return Err(SendAllError::Stalled { sent: offset, total, attempts: stalled, waited });
+                        }
+                        debug!("send_all: port accepted nothing, backing off {:?}", backoff);
+                        std::thread::sleep(backoff);
+                        waited += backoff;
+                        backoff = (backoff * 2).min(policy.max_backoff());
+                    }
+                    Ok(accepted) => {
+                        if offset + accepted < packet.end {
+                            debug!("send_all: partial send, {} of {} bytes", accepted, packet.end - offset);
+                        }
+                        offset += accepted;
+                        stalled = 0;
+                        backoff = policy.initial_backoff();
+                    }
+                    Err(code) => {
+                        return Err(SendAllError::Native { sent: offset, total, code });
+                    }
+                }
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test_send_all {
+    use super::*;
+
+    fn quick_policy(max_packet_size: usize) -> SendAllPolicy {
+        SendAllPolicyBuilder::default()
+            .max_packet_size(max_packet_size)
+            .max_stalled_attempts(3u32)
+            .initial_backoff(Duration::from_micros(10))
+            .max_backoff(Duration::from_micros(40))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_packets_end_on_message_boundaries() {
+        let bytes = [0x90, 60, 100, 0x90, 61, 100, 0xb0, 7, 100];
+        assert_eq!(midi_packet_ranges(&bytes, 7), vec![0..6, 6..9]);
+
+        let mut sysex = vec![0xf0];
+        sysex.extend(std::iter::repeat_n(0x11, 10));
+        sysex.push(0xf7);
+        let mut bytes = vec![0xc0, 1];
+        bytes.extend(&sysex);
+        assert_eq!(midi_packet_ranges(&bytes, 5), vec![0..5, 5..10, 10..14]);
+    }
+
+    #[test]
+    fn test_partial_acceptance_is_completed() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+
+        let virtual_port = virtual_device.input_port(0);
+        virtual_port.script_sends([
+            VirtualSendResult::AcceptUpTo(2),
+            VirtualSendResult::AcceptUpTo(0),
+            VirtualSendResult::AcceptUpTo(1),
+        ]);
+
+        let bytes = [0x90, 60, 100, 0x80, 60, 0];
+        assert_eq!(port.send_all_with_policy(&bytes, &quick_policy(3)), Ok(6));
+        assert_eq!(virtual_port.sent_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_stall_reports_progress() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+
+        virtual_device.input_port(0).script_sends(
+            std::iter::once(VirtualSendResult::AcceptUpTo(3))
+                .chain(std::iter::repeat_n(VirtualSendResult::AcceptUpTo(0), 10)),
+        );
+
+        match port.send_all_with_policy(&[0x90, 60, 100, 0x80, 60, 0], &quick_policy(3)) {
+            Err(SendAllError::Stalled { sent, total, attempts, .. }) => {
+                assert_eq!((sent, total, attempts), (3, 6, 4));
+            }
+            other => panic!("expected a stall, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+// ---------------- [ File: basic-android-integration/src/midi_message.rs ]
+crate::ix!();
+
+/// One complete MIDI 1.0 message.
+///
+/// Every field is a range-checked newtype, so any `MidiMessage` serializes to
+/// well-formed bytes. `SysEx` holds only the data between `F0` and `F7`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiMessage {
+    NoteOff         { channel: Channel, note: U7, velocity: U7 },
+    NoteOn          { channel: Channel, note: U7, velocity: U7 },
+    PolyPressure    { channel: Channel, note: U7, pressure: U7 },
+    ControlChange   { channel: Channel, controller: U7, value: U7 },
+    ProgramChange   { channel: Channel, program: U7 },
+    ChannelPressure { channel: Channel, pressure: U7 },
+    PitchBend       { channel: Channel, value: U14 },
+
+    SysEx(SysExData),
+    MtcQuarterFrame(U7),
+    SongPosition(U14),
+    SongSelect(U7),
+    TuneRequest,
+
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    SystemReset,
+}
+
+impl MidiMessage {
+    pub fn note_on(channel: Channel, note: U7, velocity: U7) -> Self {
+        MidiMessage::NoteOn { channel, note, velocity }
+    }
+
+    pub fn note_off(channel: Channel, note: U7, velocity: U7) -> Self {
+        MidiMessage::NoteOff { channel, note, velocity }
+    }
+
+    pub fn poly_pressure(channel: Channel, note: U7, pressure: U7) -> Self {
+        MidiMessage::PolyPressure { channel, note, pressure }
+    }
+
+    pub fn control_change(channel: Channel, controller: U7, value: U7) -> Self {
+        MidiMessage::ControlChange { channel, controller, value }
+    }
+
+    pub fn program_change(channel: Channel, program: U7) -> Self {
+        MidiMessage::ProgramChange { channel, program }
+    }
+
+    pub fn channel_pressure(channel: Channel, pressure: U7) -> Self {
+        MidiMessage::ChannelPressure { channel, pressure }
+    }
+
+    pub fn pitch_bend(channel: Channel, value: U14) -> Self {
+        MidiMessage::PitchBend { channel, value }
+    }
+
+    /// Build a SysEx message from the bytes between `F0` and `F7`.
+    /// Fails if any byte has its high bit set.
+    pub fn sysex(data: impl Into<Vec<u8>>) -> Result<Self, MidiValueError> {
+        SysExData::new(data).map(MidiMessage::SysEx)
+    }
+
+    pub fn song_position(sixteenths: U14) -> Self {
+        MidiMessage::SongPosition(sixteenths)
+    }
+
+    pub fn song_select(song: U7) -> Self {
+        MidiMessage::SongSelect(song)
+    }
+
+    pub fn mtc_quarter_frame(data: U7) -> Self {
+        MidiMessage::MtcQuarterFrame(data)
+    }
+
+    /// The status byte this message starts with (`F0` for SysEx).
+    pub fn status(&self) -> u8 {
+        match self {
+            MidiMessage::NoteOff { channel, .. }         => 0x80 | channel.get(),
+            MidiMessage::NoteOn { channel, .. }          => 0x90 | channel.get(),
+            MidiMessage::PolyPressure { channel, .. }    => 0xa0 | channel.get(),
+            MidiMessage::ControlChange { channel, .. }   => 0xb0 | channel.get(),
+            MidiMessage::ProgramChange { channel, .. }   => 0xc0 | channel.get(),
+            MidiMessage::ChannelPressure { channel, .. } => 0xd0 | channel.get(),
+            MidiMessage::PitchBend { channel, .. }       => 0xe0 | channel.get(),
+            MidiMessage::SysEx(_)                        => 0xf0,
+            MidiMessage::MtcQuarterFrame(_)              => 0xf1,
+            MidiMessage::SongPosition(_)                 => 0xf2,
+            MidiMessage::SongSelect(_)                   => 0xf3,
+            MidiMessage::TuneRequest                     => 0xf6,
+            MidiMessage::TimingClock                     => 0xf8,
+            MidiMessage::Start                           => 0xfa,
+            MidiMessage::Continue                        => 0xfb,
+            MidiMessage::Stop                            => 0xfc,
+            MidiMessage::ActiveSensing                   => 0xfe,
+            MidiMessage::SystemReset                     => 0xff,
+        }
+    }
+
+    /// The channel of a channel voice message, `None` for system messages.
+    pub fn channel(&self) -> Option<Channel> {
+        match self {
+            MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::PolyPressure { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBend { channel, .. } => Some(*channel),
+            _ => None,
+        }
+    }
+
+    /// System realtime messages (`F8`..=`FF`) may be interleaved anywhere,
+    /// even inside a SysEx.
+    pub fn is_realtime(&self) -> bool {
+        self.status() >= 0xf8
+    }
+
+    /// `F1`..=`F7`: these cancel running status.
+    pub fn is_system_common(&self) -> bool {
+        (0xf1..=0xf7).contains(&self.status())
+    }
+
+    /// Number of bytes [`MidiMessage::write_to`] will append.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            MidiMessage::SysEx(data) => data.len() + 2,
+            other => status_message_len(other.status()).unwrap_or(1),
+        }
+    }
+
+    /// Append the wire encoding (always with a status byte) to `out`.
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        out.push(self.status());
+        match self {
+            MidiMessage::NoteOff { note, velocity, .. }
+            | MidiMessage::NoteOn { note, velocity, .. } => out.extend_from_slice(&[note.get(), velocity.get()]),
+            MidiMessage::PolyPressure { note, pressure, .. } => out.extend_from_slice(&[note.get(), pressure.get()]),
+            MidiMessage::ControlChange { controller, value, .. } => out.extend_from_slice(&[controller.get(), value.get()]),
+            MidiMessage::ProgramChange { program, .. } => out.push(program.get()),
+            MidiMessage::ChannelPressure { pressure, .. } => out.push(pressure.get()),
+            MidiMessage::PitchBend { value, .. }
+            | MidiMessage::SongPosition(value) => out.extend_from_slice(&[value.lsb().get(), value.msb().get()]),
+            MidiMessage::SysEx(data) => {
+                out.extend_from_slice(data);
+                out.push(0xf7);
+            }
+            MidiMessage::MtcQuarterFrame(data)
+            | MidiMessage::SongSelect(data) => out.push(data.get()),
+            _ => {}
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.encoded_len());
+        self.write_to(&mut out);
+        out
+    }
+}
+
+/// Human-readable form for logs and tools. Channels print 1-based, as on
+/// instrument panels.
+impl std::fmt::Display for MidiMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ch = |channel: &Channel| channel.get() + 1;
+        match self {
+            MidiMessage::NoteOff { channel, note, velocity } => {
+                write!(f, "Note Off     ch {:>2} note {:>3} vel {:>3}", ch(channel), note.get(), velocity.get())
+            }
+            MidiMessage::NoteOn { channel, note, velocity } => {
+                write!(f, "Note On      ch {:>2} note {:>3} vel {:>3}", ch(channel), note.get(), velocity.get())
+            }
+            MidiMessage::PolyPressure { channel, note, pressure } => {
+                write!(f, "Poly Press   ch {:>2} note {:>3} val {:>3}", ch(channel), note.get(), pressure.get())
+            }
+            MidiMessage::ControlChange { channel, controller, value } => {
+                write!(f, "Control      ch {:>2} cc {:>3} val {:>3}", ch(channel), controller.get(), value.get())
+            }
+            MidiMessage::ProgramChange { channel, program } => {
+                write!(f, "Program      ch {:>2} prog {:>3}", ch(channel), program.get())
+            }
+            MidiMessage::ChannelPressure { channel, pressure } => {
+                write!(f, "Chan Press   ch {:>2} val {:>3}", ch(channel), pressure.get())
+            }
+            MidiMessage::PitchBend { channel, value } => {
+                write!(f, "Pitch Bend   ch {:>2} val {:>+5}", ch(channel), value.signed())
+            }
+            MidiMessage::SysEx(data) => {
+                write!(f, "SysEx        {} byte(s):", data.len())?;
+                for byte in data.iter() {
+                    write!(f, " {:02x}", byte)?;
+                }
+                Ok(())
+            }
+            MidiMessage::MtcQuarterFrame(data) => {
+                write!(f, "MTC QF       piece {} value {}", data.get() >> 4, data.get() & 0x0f)
+            }
+            MidiMessage::SongPosition(position) => write!(f, "Song Pos     {} sixteenths", position.get()),
+            MidiMessage::SongSelect(song) => write!(f, "Song Select  {}", song.get()),
+            MidiMessage::TuneRequest => write!(f, "Tune Request"),
+            MidiMessage::TimingClock => write!(f, "Clock"),
+            MidiMessage::Start => write!(f, "Start"),
+            MidiMessage::Continue => write!(f, "Continue"),
+            MidiMessage::Stop => write!(f, "Stop"),
+            MidiMessage::ActiveSensing => write!(f, "Active Sensing"),
+            MidiMessage::SystemReset => write!(f, "System Reset"),
+        }
+    }
+}
+
+/// Total length in bytes (status included) of the message a status byte
+/// starts, or `None` for SysEx (`F0`), the `F7` terminator and undefined
+/// system statuses.
+pub fn status_message_len(status: u8) -> Option<usize> {
+    match status {
+        0x80..=0xbf | 0xe0..=0xef => Some(3),
+        0xc0..=0xdf               => Some(2),
+        0xf1 | 0xf3               => Some(2),
+        0xf2                      => Some(3),
+        0xf6                      => Some(1),
+        0xf8 | 0xfa..=0xfc | 0xfe | 0xff => Some(1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_midi_message {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    #[test]
+    fn test_channel_voice_encoding() {
+        assert_eq!(MidiMessage::note_on(ch(0), u7(60), u7(100)).to_bytes(), vec![0x90, 60, 100]);
+        assert_eq!(MidiMessage::note_off(ch(15), u7(60), u7(0)).to_bytes(), vec![0x8f, 60, 0]);
+        assert_eq!(MidiMessage::control_change(ch(2), u7(7), u7(127)).to_bytes(), vec![0xb2, 7, 127]);
+        assert_eq!(MidiMessage::program_change(ch(9), u7(5)).to_bytes(), vec![0xc9, 5]);
+        assert_eq!(MidiMessage::pitch_bend(ch(0), U14::CENTER).to_bytes(), vec![0xe0, 0x00, 0x40]);
+        assert_eq!(MidiMessage::pitch_bend(ch(0), U14::MAX).to_bytes(), vec![0xe0, 0x7f, 0x7f]);
+    }
+
+    #[test]
+    fn test_system_encoding() {
+        let sysex = MidiMessage::sysex(vec![0x7e, 0x7f, 0x06, 0x01]).unwrap();
+        assert_eq!(sysex.to_bytes(), vec![0xf0, 0x7e, 0x7f, 0x06, 0x01, 0xf7]);
+        assert_eq!(sysex.encoded_len(), 6);
+        assert_eq!(MidiMessage::TimingClock.to_bytes(), vec![0xf8]);
+        assert_eq!(MidiMessage::song_position(U14::new(0x81).unwrap()).to_bytes(), vec![0xf2, 0x01, 0x01]);
+        assert!(MidiMessage::Stop.is_realtime());
+        assert!(!MidiMessage::TuneRequest.is_realtime());
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_rejected() {
+        assert!(Channel::new(16).is_err());
+        assert!(U7::new(128).is_err());
+        assert!(U14::new(0x4000).is_err());
+        assert!(U14::from_signed(8192).is_err());
+        assert_eq!(U14::from_signed(-8192).unwrap(), U14::MIN);
+        assert_eq!(
+            MidiMessage::sysex(vec![0x41, 0xf7]).unwrap_err(),
+            MidiValueError::SysExDataByte { index: 1, byte: 0xf7 },
+        );
+    }
+}
@@ -1,14 +1,34 @@
 // ---------------- [ File: basic-android-integration/src/midi_output_port.rs ]
 crate::ix!();
 
+/// One packet taken from a [`MidiOutputPort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceivedPacket<'a> {
+    Data { bytes: &'a [u8], timestamp: i64 },
+    /// The sender flushed its port. Anything half received, such as a
+    /// SysEx split across packets, will never be completed, so parsers
+    /// should reset.
+    Flush,
+}
+
 #[derive(Debug)]
 pub struct MidiOutputPort<'lib> {
     pub(crate) library: Arc<AmidiLibrary>,
     pub(crate) raw_out: *mut AMidiOutputPort,
+    /// Holds this port's slot in the device's open-port registry; released
+    /// after the native port is closed.
+    pub(crate) claim: MidiPortClaim,
+    /// Set by `start_capture`.
+    pub(crate) capture: std::cell::RefCell<Option<OutputPortCapture>>,
     pub(crate) _marker: std::marker::PhantomData<&'lib AmidiLibrary>,
 }
 
 impl<'lib> MidiOutputPort<'lib> {
+    /// The port number this port was opened with.
+    pub fn port_number(&self) -> i32 {
+        self.claim.port_number()
+    }
+
     /// Receive MIDI data (up to `buffer.len()` bytes).
     /// Returns `Ok(())` if successful, or `Err(isize)` if < 0 from the C API.
     pub fn receive(
@@ -37,9 +57,40 @@ impl<'lib> MidiOutputPort<'lib> {
                 "Received {} bytes, opcode={}, timestamp={}",
                 *num_bytes_received, *opcode_ptr, *timestamp
             );
+            self.observe_received(*opcode_ptr, &buffer[..*num_bytes_received], *timestamp);
             Ok(())
         }
     }
+
+    /// The next data or flush packet, `None` once nothing is pending.
+    /// Packets with other opcodes are skipped.
+    pub fn receive_packet<'b>(&self, buffer: &'b mut [u8; AMIDI_MAX_PACKET_PAYLOAD]) -> Result<Option<ReceivedPacket<'b>>, isize> {
+        let (received, timestamp) = loop {
+            let (mut opcode, mut received, mut timestamp) = (0, 0, 0);
+            self.receive(&mut opcode, buffer, &mut received, &mut timestamp)?;
+            match opcode {
+                _ if received == 0 && opcode != AMIDI_OPCODE_FLUSH => return Ok(None),
+                AMIDI_OPCODE_DATA => break (received, timestamp),
+                AMIDI_OPCODE_FLUSH => return Ok(Some(ReceivedPacket::Flush)),
+                _ => {}
+            }
+        };
+        Ok(Some(ReceivedPacket::Data { bytes: &buffer[..received], timestamp }))
+    }
+
+    /// Hand every pending packet to `on_packet`. Returns the number of
+    /// data packets read.
+    pub fn drain(&self, mut on_packet: impl FnMut(ReceivedPacket<'_>)) -> Result<usize, isize> {
+        let mut buffer  = [0u8; AMIDI_MAX_PACKET_PAYLOAD];
+        let mut packets = 0;
+        while let Some(packet) = self.receive_packet(&mut buffer)? {
+            if let ReceivedPacket::Data { .. } = packet {
+                packets += 1;
+            }
+            on_packet(packet);
+        }
+        Ok(packets)
+    }
 }
 
 impl<'lib> Drop for MidiOutputPort<'lib> {
@@ -53,3 +104,30 @@ impl<'lib> Drop for MidiOutputPort<'lib> {
         }
     }
 }
+
+#[cfg(test)]
+mod test_midi_output_port {
+    use super::*;
+
+    #[test]
+    fn test_drain_reports_flushes_and_skips_other_opcodes() {
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let device = virtual_device.open();
+        let output = device.open_output_port(0).unwrap();
+
+        let port = virtual_device.output_port(0);
+        port.push(vec![0xf0, 0x7e], 1);
+        port.push_packet(VirtualReceivedPacket { opcode: AMIDI_OPCODE_FLUSH, bytes: Vec::new(), timestamp: 2 });
+        port.push_packet(VirtualReceivedPacket { opcode: 7, bytes: vec![0x90], timestamp: 3 });
+        port.push(vec![0x90, 0x3c, 0x40], 4);
+
+        let mut seen = Vec::new();
+        let packets = output.drain(|packet| seen.push(match packet {
+            ReceivedPacket::Data { bytes, timestamp } => Some((bytes.to_vec(), timestamp)),
+            ReceivedPacket::Flush => None,
+        })).unwrap();
+        assert_eq!(packets, 2);
+        assert_eq!(seen, vec![Some((vec![0xf0, 0x7e], 1)), None, Some((vec![0x90, 0x3c, 0x40], 4))]);
+        assert_eq!(port.pending(), 0);
+    }
+}
@@ -0,0 +1,66 @@
+// ---------------- [ File: basic-android-integration/src/midi_port_error.rs ]
+crate::ix!();
+
+/// Which side of a `MidiDevice` a port number refers to.
+///
+/// Note the AMidi naming: an *input* port is one we send to, an *output* port
+/// is one we receive from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiPortDirection {
+    Input,
+    Output,
+}
+
+impl std::fmt::Display for MidiPortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiPortDirection::Input  => f.write_str("input"),
+            MidiPortDirection::Output => f.write_str("output"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiPortError {
+    /// `port_number` is negative or not below the device's port count.
+    PortOutOfRange {
+        direction:   MidiPortDirection,
+        port_number: i32,
+        num_ports:   isize,
+    },
+
+    /// The port is already open on this device and has not been dropped yet.
+    AlreadyOpen {
+        direction:   MidiPortDirection,
+        port_number: i32,
+    },
+
+    /// libamidi refused to open the port.
+    Native {
+        direction:   MidiPortDirection,
+        port_number: i32,
+        status:      media_status_t,
+    },
+}
+
+impl std::fmt::Display for MidiPortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiPortError::PortOutOfRange { direction, port_number, num_ports } => write!(
+                f,
+                "{} port #{} is out of range (device has {} {} ports)",
+                direction, port_number, num_ports, direction
+            ),
+            MidiPortError::AlreadyOpen { direction, port_number } => {
+                write!(f, "{} port #{} is already open", direction, port_number)
+            }
+            MidiPortError::Native { direction, port_number, status } => write!(
+                f,
+                "opening {} port #{} failed with status {:?}",
+                direction, port_number, status
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MidiPortError {}
@@ -0,0 +1,98 @@
+// ---------------- [ File: basic-android-integration/src/midi_port_registry.rs ]
+crate::ix!();
+
+/// Tracks which ports of one `MidiDevice` are currently open.
+///
+/// Every open port holds a [`MidiPortClaim`]; dropping the port drops the
+/// claim, which frees the slot again.
+#[derive(Debug, Default)]
+pub(crate) struct MidiPortRegistry {
+    open: std::sync::Mutex<std::collections::HashSet<(MidiPortDirection, i32)>>,
+}
+
+impl MidiPortRegistry {
+    /// Mark the port as open, or fail with `AlreadyOpen` if it already is.
+    pub(crate) fn claim(
+        self:        &Arc<Self>,
+        direction:   MidiPortDirection,
+        port_number: i32,
+    ) -> Result<MidiPortClaim, MidiPortError> {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        if !open.insert((direction, port_number)) {
+            warn!("{} port #{} is already open", direction, port_number);
+            return Err(MidiPortError::AlreadyOpen { direction, port_number });
+        }
+        trace!("Claimed {} port #{}", direction, port_number);
+        Ok(MidiPortClaim {
+            registry: self.clone(),
+            direction,
+            port_number,
+        })
+    }
+
+    pub(crate) fn is_open(&self, direction: MidiPortDirection, port_number: i32) -> bool {
+        let open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        open.contains(&(direction, port_number))
+    }
+
+    fn release(&self, direction: MidiPortDirection, port_number: i32) {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        open.remove(&(direction, port_number));
+        trace!("Released {} port #{}", direction, port_number);
+    }
+}
+
+/// Proof that a port slot is held. Releases the slot when dropped.
+#[derive(Debug)]
+pub(crate) struct MidiPortClaim {
+    registry:    Arc<MidiPortRegistry>,
+    direction:   MidiPortDirection,
+    port_number: i32,
+}
+
+impl MidiPortClaim {
+    pub(crate) fn port_number(&self) -> i32 {
+        self.port_number
+    }
+}
+
+impl Drop for MidiPortClaim {
+    fn drop(&mut self) {
+        self.registry.release(self.direction, self.port_number);
+    }
+}
+
+/// Open/closed state of one port, as yielded by `MidiDevice::ports`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct MidiPortInfo {
+    pub(crate) direction:   MidiPortDirection,
+    pub(crate) port_number: i32,
+    pub(crate) open:        bool,
+}
+
+#[cfg(test)]
+mod test_midi_port_registry {
+    use super::*;
+
+    #[test]
+    fn test_double_claim_is_rejected_until_release() {
+        let registry = Arc::new(MidiPortRegistry::default());
+
+        let claim = registry.claim(MidiPortDirection::Input, 0).expect("first claim succeeds");
+        assert!(registry.is_open(MidiPortDirection::Input, 0));
+        assert!(!registry.is_open(MidiPortDirection::Output, 0));
+
+        assert_eq!(
+            registry.claim(MidiPortDirection::Input, 0).unwrap_err(),
+            MidiPortError::AlreadyOpen { direction: MidiPortDirection::Input, port_number: 0 },
+        );
+
+        // The same number on the other side is a different port.
+        let _output = registry.claim(MidiPortDirection::Output, 0).expect("output 0 is free");
+
+        drop(claim);
+        assert!(!registry.is_open(MidiPortDirection::Input, 0));
+        registry.claim(MidiPortDirection::Input, 0).expect("claim succeeds after release");
+    }
+}
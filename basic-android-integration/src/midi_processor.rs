@@ -0,0 +1,36 @@
+// ---------------- [ File: basic-android-integration/src/midi_processor.rs ]
+crate::ix!();
+
+/// A message with the AMidi timestamp (nanoseconds, `CLOCK_MONOTONIC`) it
+/// was received at or should be delivered at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimedMidiEvent {
+    pub timestamp: i64,
+    pub message:   MidiMessage,
+}
+
+impl TimedMidiEvent {
+    pub fn new(timestamp: i64, message: MidiMessage) -> Self {
+        Self { timestamp, message }
+    }
+}
+
+/// A processing stage: takes one event, emits zero or more.
+///
+/// Processors may change timestamps (a delay pushes them later); sinks
+/// schedule output for whatever timestamp they are given.
+pub trait MidiProcessor {
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>);
+
+    /// Drop any internal state (held notes, pending delays).
+    fn reset(&mut self) {}
+}
+
+impl<F> MidiProcessor for F
+where
+    F: FnMut(TimedMidiEvent, &mut Vec<TimedMidiEvent>),
+{
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        self(event, out)
+    }
+}
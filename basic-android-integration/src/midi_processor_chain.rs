@@ -0,0 +1,135 @@
+// ---------------- [ File: basic-android-integration/src/midi_processor_chain.rs ]
+crate::ix!();
+
+/// One built-in stage, tagged by `"stage"` in a preset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "stage")]
+pub enum MidiProcessorStage {
+    MessageFilter(MessageFilter),
+    ChannelFilter(ChannelFilter),
+    Transpose(Transpose),
+    ChannelRemap(ChannelRemap),
+    VelocityMap(VelocityMap),
+    ControllerMap(ControllerMap),
+    KeyboardSplit(KeyboardSplit),
+}
+
+impl MidiProcessorStage {
+    fn processor(&mut self) -> &mut dyn MidiProcessor {
+        match self {
+            MidiProcessorStage::MessageFilter(stage) => stage,
+            MidiProcessorStage::ChannelFilter(stage) => stage,
+            MidiProcessorStage::Transpose(stage)     => stage,
+            MidiProcessorStage::ChannelRemap(stage)  => stage,
+            MidiProcessorStage::VelocityMap(stage)   => stage,
+            MidiProcessorStage::ControllerMap(stage) => stage,
+            MidiProcessorStage::KeyboardSplit(stage) => stage,
+        }
+    }
+}
+
+impl MidiProcessor for MidiProcessorStage {
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        self.processor().process(event, out)
+    }
+
+    fn reset(&mut self) {
+        self.processor().reset()
+    }
+}
+
+/// Built-in stages run in order, each fed everything the previous one
+/// emitted. Serializes to a JSON preset (held-note state is not saved).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MidiProcessorChain {
+    pub stages: Vec<MidiProcessorStage>,
+    #[serde(skip)]
+    scratch: Vec<TimedMidiEvent>,
+}
+
+impl MidiProcessorChain {
+    pub fn new(stages: Vec<MidiProcessorStage>) -> Self {
+        Self { stages, scratch: Vec::new() }
+    }
+
+    pub fn push(&mut self, stage: MidiProcessorStage) -> &mut Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("processor stages always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(Self::from_json(&json)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+}
+
+impl MidiProcessor for MidiProcessorChain {
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        let start = out.len();
+        out.push(event);
+        for stage in &mut self.stages {
+            self.scratch.clear();
+            for event in out.drain(start..) {
+                stage.process(event, &mut self.scratch);
+            }
+            out.append(&mut self.scratch);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stages.iter_mut().for_each(MidiProcessor::reset);
+    }
+}
+
+#[cfg(test)]
+mod test_midi_processor_chain {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    #[test]
+    fn test_preset_round_trip_and_chaining() {
+        let preset = r#"{
+            "stages": [
+                { "stage": "ChannelFilter", "channels": [0] },
+                { "stage": "Transpose", "semitones": -12 },
+                { "stage": "KeyboardSplit", "split": 60, "lower_channel": 1, "upper_channel": 2 },
+                { "stage": "VelocityMap", "curve": { "Linear": { "min": 100, "max": 100 } } }
+            ]
+        }"#;
+        let chain     = MidiProcessorChain::from_json(preset).unwrap();
+        let mut chain = MidiProcessorChain::from_json(&chain.to_json()).unwrap();
+        assert_eq!(chain.stages.len(), 4);
+
+        let mut out = Vec::new();
+        for message in [
+            MidiMessage::note_on(ch(0), u7(70), u7(20)),
+            MidiMessage::note_on(ch(5), u7(70), u7(20)),
+            MidiMessage::note_on(ch(0), u7(80), u7(20)),
+            MidiMessage::note_off(ch(0), u7(70), U7::MIN),
+        ] {
+            chain.process(TimedMidiEvent::new(7, message), &mut out);
+        }
+        let messages: Vec<_> = out.iter().map(|event| event.message.clone()).collect();
+        assert_eq!(messages, vec![
+            MidiMessage::note_on(ch(1), u7(58), u7(100)),
+            MidiMessage::note_on(ch(2), u7(68), u7(100)),
+            MidiMessage::note_off(ch(1), u7(58), U7::MIN),
+        ]);
+        assert!(out.iter().all(|event| event.timestamp == 7));
+    }
+}
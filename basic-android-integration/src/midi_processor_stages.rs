@@ -0,0 +1,372 @@
+// ---------------- [ File: basic-android-integration/src/midi_processor_stages.rs ]
+crate::ix!();
+
+use std::collections::HashMap;
+
+/// Coarse message classes for [`MessageFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiMessageType {
+    Note,
+    PolyPressure,
+    ControlChange,
+    ProgramChange,
+    ChannelPressure,
+    PitchBend,
+    SysEx,
+    SystemCommon,
+    Realtime,
+}
+
+impl MidiMessageType {
+    pub fn of(message: &MidiMessage) -> Self {
+        match message {
+            MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. } => MidiMessageType::Note,
+            MidiMessage::PolyPressure { .. } => MidiMessageType::PolyPressure,
+            MidiMessage::ControlChange { .. } => MidiMessageType::ControlChange,
+            MidiMessage::ProgramChange { .. } => MidiMessageType::ProgramChange,
+            MidiMessage::ChannelPressure { .. } => MidiMessageType::ChannelPressure,
+            MidiMessage::PitchBend { .. } => MidiMessageType::PitchBend,
+            MidiMessage::SysEx(_) => MidiMessageType::SysEx,
+            _ if message.is_realtime() => MidiMessageType::Realtime,
+            _ => MidiMessageType::SystemCommon,
+        }
+    }
+}
+
+/// Note-on with velocity 0 is a note-off everywhere below.
+fn note_event(message: &MidiMessage) -> Option<(Channel, U7, bool)> {
+    match *message {
+        MidiMessage::NoteOn { channel, note, velocity } => Some((channel, note, velocity.get() > 0)),
+        MidiMessage::NoteOff { channel, note, .. } => Some((channel, note, false)),
+        _ => None,
+    }
+}
+
+fn set_channel(message: &mut MidiMessage, channel: Channel) {
+    match message {
+        MidiMessage::NoteOff { channel: c, .. }
+        | MidiMessage::NoteOn { channel: c, .. }
+        | MidiMessage::PolyPressure { channel: c, .. }
+        | MidiMessage::ControlChange { channel: c, .. }
+        | MidiMessage::ProgramChange { channel: c, .. }
+        | MidiMessage::ChannelPressure { channel: c, .. }
+        | MidiMessage::PitchBend { channel: c, .. } => *c = channel,
+        _ => {}
+    }
+}
+
+fn set_note(message: &mut MidiMessage, note: U7) {
+    if let MidiMessage::NoteOn { note: n, .. } | MidiMessage::NoteOff { note: n, .. } | MidiMessage::PolyPressure { note: n, .. } =
+        message
+    {
+        *n = note;
+    }
+}
+
+/// Pass (or, with `block`, drop) only the listed message types.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MessageFilter {
+    pub types: Vec<MidiMessageType>,
+    #[serde(default)]
+    pub block: bool,
+}
+
+impl MidiProcessor for MessageFilter {
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        if self.types.contains(&MidiMessageType::of(&event.message)) != self.block {
+            out.push(event);
+        }
+    }
+}
+
+/// Pass channel messages on the listed channels only; system messages
+/// always pass.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChannelFilter {
+    pub channels: Vec<Channel>,
+}
+
+impl MidiProcessor for ChannelFilter {
+    fn process(&mut self, event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        if event.message.channel().is_none_or(|c| self.channels.contains(&c)) {
+            out.push(event);
+        }
+    }
+}
+
+/// Shift notes (and poly pressure) by `semitones`.
+///
+/// Each note-off uses the shift its note-on got, so changing `semitones`
+/// while notes are held can't leave them hanging; notes shifted out of
+/// range are dropped, on and off alike.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transpose {
+    pub semitones: i8,
+    /// (channel, incoming note) -> outgoing note, `None` if dropped.
+    #[serde(skip)]
+    held: HashMap<(Channel, U7), Option<U7>>,
+}
+
+impl Transpose {
+    pub fn new(semitones: i8) -> Self {
+        Self { semitones, held: HashMap::new() }
+    }
+
+    fn shifted(&self, note: U7) -> Option<U7> {
+        U7::new(u8::try_from(note.get() as i16 + self.semitones as i16).ok()?).ok()
+    }
+}
+
+impl MidiProcessor for Transpose {
+    fn process(&mut self, mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        let target = match (note_event(&event.message), &event.message) {
+            (Some((channel, note, true)), _) => {
+                let target = self.shifted(note);
+                self.held.insert((channel, note), target);
+                target
+            }
+            (Some((channel, note, false)), _) => match self.held.remove(&(channel, note)) {
+                Some(target) => target,
+                None => self.shifted(note),
+            },
+            (None, MidiMessage::PolyPressure { channel, note, .. }) => {
+                self.held.get(&(*channel, *note)).copied().unwrap_or_else(|| self.shifted(*note))
+            }
+            _ => return out.push(event),
+        };
+        if let Some(note) = target {
+            set_note(&mut event.message, note);
+            out.push(event);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.held.clear();
+    }
+}
+
+/// Move channel messages from one channel to another; unlisted channels
+/// pass unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChannelRemap {
+    /// (from, to) pairs.
+    pub map: Vec<(Channel, Channel)>,
+}
+
+impl MidiProcessor for ChannelRemap {
+    fn process(&mut self, mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        if let Some(channel) = event.message.channel()
+            && let Some((_, to)) = self.map.iter().find(|(from, _)| *from == channel)
+        {
+            set_channel(&mut event.message, *to);
+        }
+        out.push(event);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VelocityCurve {
+    /// Velocities 1-127 spread evenly over `min..=max`.
+    Linear { min: U7, max: U7 },
+    /// `127 * (v / 127) ^ exponent`: above 1 softens, below 1 hardens.
+    Exponential { exponent: f32 },
+    /// Output for each input velocity, indexed by velocity; inputs past
+    /// the end use the last entry.
+    Table { values: Vec<U7> },
+}
+
+impl VelocityCurve {
+    /// Never returns 0, so a note-on stays a note-on.
+    pub fn apply(&self, velocity: U7) -> U7 {
+        let v = velocity.get() as f32;
+        let out = match self {
+            VelocityCurve::Linear { min, max } => {
+                min.get() as f32 + (v - 1.0) * (max.get() as f32 - min.get() as f32) / 126.0
+            }
+            VelocityCurve::Exponential { exponent } => 127.0 * (v / 127.0).powf(*exponent),
+            VelocityCurve::Table { values } => match values.get(velocity.get() as usize).or(values.last()) {
+                Some(value) => value.get() as f32,
+                None => v,
+            },
+        };
+        U7::saturating(out.round().clamp(1.0, 127.0) as u8)
+    }
+}
+
+/// Reshape note-on velocities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VelocityMap {
+    pub curve: VelocityCurve,
+}
+
+impl MidiProcessor for VelocityMap {
+    fn process(&mut self, mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        if let MidiMessage::NoteOn { velocity, .. } = &mut event.message
+            && velocity.get() > 0
+        {
+            *velocity = self.curve.apply(*velocity);
+        }
+        out.push(event);
+    }
+}
+
+/// Renumber a controller and rescale its value from 0-127 to `min..=max`
+/// (`min > max` inverts it).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControllerMap {
+    /// `None` applies to every channel.
+    #[serde(default)]
+    pub channel: Option<Channel>,
+    pub from:    U7,
+    pub to:      U7,
+    pub min:     U7,
+    pub max:     U7,
+}
+
+impl MidiProcessor for ControllerMap {
+    fn process(&mut self, mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        if let MidiMessage::ControlChange { channel, controller, value } = &mut event.message
+            && *controller == self.from
+            && self.channel.is_none_or(|c| c == *channel)
+        {
+            let (min, max) = (self.min.get() as f32, self.max.get() as f32);
+            let scaled     = min + (value.get() as f32 * (max - min) / 127.0).round();
+            *controller = self.to;
+            *value      = U7::saturating(scaled.clamp(0.0, 127.0) as u8);
+        }
+        out.push(event);
+    }
+}
+
+/// Send notes below `split` to one channel and the rest to another. Other
+/// channel messages (pedals, bend) go to both; note-offs follow their note-on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyboardSplit {
+    pub split:         U7,
+    pub lower_channel: Channel,
+    pub upper_channel: Channel,
+    #[serde(skip)]
+    held: HashMap<(Channel, U7), Channel>,
+}
+
+impl KeyboardSplit {
+    pub fn new(split: U7, lower_channel: Channel, upper_channel: Channel) -> Self {
+        Self { split, lower_channel, upper_channel, held: HashMap::new() }
+    }
+
+    fn side(&self, note: U7) -> Channel {
+        if note < self.split { self.lower_channel } else { self.upper_channel }
+    }
+}
+
+impl MidiProcessor for KeyboardSplit {
+    fn process(&mut self, mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>) {
+        let target = match note_event(&event.message) {
+            Some((channel, note, true)) => {
+                let target = self.side(note);
+                self.held.insert((channel, note), target);
+                target
+            }
+            Some((channel, note, false)) => self.held.remove(&(channel, note)).unwrap_or_else(|| self.side(note)),
+            None => match &event.message {
+                MidiMessage::PolyPressure { channel, note, .. } => {
+                    self.held.get(&(*channel, *note)).copied().unwrap_or_else(|| self.side(*note))
+                }
+                message if message.channel().is_some() => {
+                    let mut lower = event.clone();
+                    set_channel(&mut lower.message, self.lower_channel);
+                    out.push(lower);
+                    if self.upper_channel != self.lower_channel {
+                        set_channel(&mut event.message, self.upper_channel);
+                        out.push(event);
+                    }
+                    return;
+                }
+                _ => return out.push(event),
+            },
+        };
+        set_channel(&mut event.message, target);
+        out.push(event);
+    }
+
+    fn reset(&mut self) {
+        self.held.clear();
+    }
+}
+
+#[cfg(test)]
+mod test_midi_processor_stages {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    fn run(processor: &mut impl MidiProcessor, messages: Vec<MidiMessage>) -> Vec<MidiMessage> {
+        let mut out = Vec::new();
+        for message in messages {
+            processor.process(TimedMidiEvent::new(0, message), &mut out);
+        }
+        out.into_iter().map(|event| event.message).collect()
+    }
+
+    #[test]
+    fn test_transpose_releases_held_notes_after_change() {
+        let mut transpose = Transpose::new(12);
+        let first = run(&mut transpose, vec![MidiMessage::note_on(ch(0), u7(60), u7(90)), MidiMessage::note_on(ch(0), u7(120), u7(90))]);
+        transpose.semitones = -12;
+        let second = run(&mut transpose, vec![MidiMessage::note_off(ch(0), u7(60), U7::MIN), MidiMessage::note_off(ch(0), u7(120), U7::MIN)]);
+        assert_eq!(first, vec![MidiMessage::note_on(ch(0), u7(72), u7(90))]);
+        assert_eq!(second, vec![MidiMessage::note_off(ch(0), u7(72), U7::MIN)]);
+    }
+
+    #[test]
+    fn test_velocity_curves_and_controller_map() {
+        assert_eq!(VelocityCurve::Linear { min: u7(64), max: u7(127) }.apply(u7(1)), u7(64));
+        assert_eq!(VelocityCurve::Exponential { exponent: 2.0 }.apply(u7(64)), u7(32));
+        assert_eq!(VelocityCurve::Exponential { exponent: 2.0 }.apply(u7(1)), u7(1));
+        assert_eq!(VelocityCurve::Table { values: vec![u7(1), u7(10)] }.apply(u7(100)), u7(10));
+
+        let mut invert = ControllerMap { channel: None, from: u7(1), to: u7(11), min: U7::MAX, max: U7::MIN };
+        let out = run(&mut invert, vec![
+            MidiMessage::control_change(ch(3), u7(1), u7(0)),
+            MidiMessage::control_change(ch(3), u7(2), u7(0)),
+            MidiMessage::control_change(ch(3), u7(1), U7::MAX),
+            MidiMessage::control_change(ch(3), u7(1), u7(64)),
+        ]);
+        assert_eq!(out, vec![
+            MidiMessage::control_change(ch(3), u7(11), U7::MAX),
+            MidiMessage::control_change(ch(3), u7(2), u7(0)),
+            MidiMessage::control_change(ch(3), u7(11), U7::MIN),
+            MidiMessage::control_change(ch(3), u7(11), u7(63)),
+        ]);
+
+        let mut narrow = ControllerMap { channel: None, from: u7(1), to: u7(1), min: u7(20), max: u7(40) };
+        let out = run(&mut narrow, vec![MidiMessage::control_change(ch(0), u7(1), U7::MAX), MidiMessage::control_change(ch(0), u7(1), u7(64))]);
+        assert_eq!(out, vec![MidiMessage::control_change(ch(0), u7(1), u7(40)), MidiMessage::control_change(ch(0), u7(1), u7(30))]);
+    }
+
+    #[test]
+    fn test_split_and_filters() {
+        let mut split = KeyboardSplit::new(u7(60), ch(1), ch(2));
+        let out = run(&mut split, vec![
+            MidiMessage::note_on(ch(0), u7(48), u7(90)),
+            MidiMessage::note_on(ch(0), u7(72), u7(90)),
+            MidiMessage::control_change(ch(0), u7(64), U7::MAX),
+            MidiMessage::note_on(ch(0), u7(48), U7::MIN),
+            MidiMessage::TimingClock,
+        ]);
+        assert_eq!(out, vec![
+            MidiMessage::note_on(ch(1), u7(48), u7(90)),
+            MidiMessage::note_on(ch(2), u7(72), u7(90)),
+            MidiMessage::control_change(ch(1), u7(64), U7::MAX),
+            MidiMessage::control_change(ch(2), u7(64), U7::MAX),
+            MidiMessage::note_on(ch(1), u7(48), U7::MIN),
+            MidiMessage::TimingClock,
+        ]);
+
+        let mut block_clock = MessageFilter { types: vec![MidiMessageType::Realtime], block: true };
+        let mut only_ch2    = ChannelFilter { channels: vec![ch(2)] };
+        let out = run(&mut block_clock, out);
+        assert_eq!(run(&mut only_ch2, out).len(), 2);
+    }
+}
@@ -0,0 +1,329 @@
+// ---------------- [ File: basic-android-integration/src/midi_routing_graph.rs ]
+crate::ix!();
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MidiRouteNodeId(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiRoutingError {
+    UnknownNode(MidiRouteNodeId),
+    /// Sources only have outputs and sinks only have inputs.
+    WrongDirection { from: MidiRouteNodeId, to: MidiRouteNodeId },
+    /// The connection would close this loop (first node repeated at the end).
+    FeedbackCycle { path: Vec<MidiRouteNodeId> },
+}
+
+impl std::fmt::Display for MidiRoutingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiRoutingError::UnknownNode(id) => write!(f, "no routing node {:?}", id),
+            MidiRoutingError::WrongDirection { from, to } => {
+                write!(f, "cannot route {:?} -> {:?}: sources have no inputs and sinks no outputs", from, to)
+            }
+            MidiRoutingError::FeedbackCycle { path } => write!(f, "connection would create a feedback cycle: {:?}", path),
+        }
+    }
+}
+
+impl std::error::Error for MidiRoutingError {}
+
+enum MidiRouteNode<'p, 'lib> {
+    Source { port: &'p MidiOutputPort<'lib>, parser: MidiStreamParser },
+    Sink { port: &'p MidiInputPort<'lib> },
+    Processor(Box<dyn MidiProcessor + 'p>),
+}
+
+impl MidiRouteNode<'_, '_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            MidiRouteNode::Source { .. } => "source",
+            MidiRouteNode::Sink { .. } => "sink",
+            MidiRouteNode::Processor(_) => "processor",
+        }
+    }
+}
+
+/// What one [`MidiRoutingGraph::pump`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MidiRoutingStats {
+    pub received:       usize,
+    pub delivered:      usize,
+    pub receive_errors: usize,
+    pub send_errors:    usize,
+}
+
+/// A MIDI hub: output ports (sources) feed processing stages and input
+/// ports (sinks) along directed edges.
+///
+/// Everything happens in [`pump`](Self::pump): each source is drained and
+/// every event is pushed through the graph to the sinks before `pump`
+/// returns, so between pumps nothing is in flight and the graph can be
+/// rewired without losing messages. Parser state (running status, a SysEx
+/// split across packets) lives with the source node, not the edges, so it
+/// survives rewiring too.
+///
+/// Connections that would close a loop are refused. Loops through the
+/// outside world (a synth with MIDI thru echoing into one of our sources)
+/// are caught as well once declared with [`declare_echo`](Self::declare_echo).
+pub struct MidiRoutingGraph<'p, 'lib> {
+    nodes:       Vec<Option<MidiRouteNode<'p, 'lib>>>,
+    edges:       BTreeSet<(MidiRouteNodeId, MidiRouteNodeId)>,
+    /// sink -> source pairs joined outside the graph.
+    echoes:      BTreeSet<(MidiRouteNodeId, MidiRouteNodeId)>,
+    send_policy: SendAllPolicy,
+}
+
+impl<'p, 'lib> Default for MidiRoutingGraph<'p, 'lib> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'p, 'lib> MidiRoutingGraph<'p, 'lib> {
+    pub fn new() -> Self {
+        Self {
+            nodes:       Vec::new(),
+            edges:       BTreeSet::new(),
+            echoes:      BTreeSet::new(),
+            send_policy: SendAllPolicy::default(),
+        }
+    }
+
+    pub fn set_send_policy(&mut self, policy: SendAllPolicy) {
+        self.send_policy = policy;
+    }
+
+    pub fn add_source(&mut self, port: &'p MidiOutputPort<'lib>) -> MidiRouteNodeId {
+        self.add(MidiRouteNode::Source { port, parser: MidiStreamParser::new() })
+    }
+
+    pub fn add_sink(&mut self, port: &'p MidiInputPort<'lib>) -> MidiRouteNodeId {
+        self.add(MidiRouteNode::Sink { port })
+    }
+
+    pub fn add_processor(&mut self, processor: impl MidiProcessor + 'p) -> MidiRouteNodeId {
+        self.add(MidiRouteNode::Processor(Box::new(processor)))
+    }
+
+    fn add(&mut self, node: MidiRouteNode<'p, 'lib>) -> MidiRouteNodeId {
+        let id = MidiRouteNodeId(self.nodes.len());
+        debug!("routing: added {} {:?}", node.kind(), id);
+        self.nodes.push(Some(node));
+        id
+    }
+
+    /// Remove a node and every edge touching it.
+    pub fn remove(&mut self, id: MidiRouteNodeId) -> Result<(), MidiRoutingError> {
+        self.node(id)?;
+        self.nodes[id.0] = None;
+        self.edges.retain(|(from, to)| *from != id && *to != id);
+        self.echoes.retain(|(sink, source)| *sink != id && *source != id);
+        debug!("routing: removed {:?}", id);
+        Ok(())
+    }
+
+    pub fn connect(&mut self, from: MidiRouteNodeId, to: MidiRouteNodeId) -> Result<(), MidiRoutingError> {
+        let (source, sink) = (self.node(from)?, self.node(to)?);
+        if matches!(source, MidiRouteNode::Sink { .. }) || matches!(sink, MidiRouteNode::Source { .. }) {
+            return Err(MidiRoutingError::WrongDirection { from, to });
+        }
+        self.check_acyclic(from, to)?;
+        self.edges.insert((from, to));
+        debug!("routing: {:?} -> {:?}", from, to);
+        Ok(())
+    }
+
+    pub fn disconnect(&mut self, from: MidiRouteNodeId, to: MidiRouteNodeId) -> bool {
+        self.edges.remove(&(from, to))
+    }
+
+    /// Record that whatever `sink` sends comes back in on `source`, so
+    /// routes that would loop through that device are refused.
+    pub fn declare_echo(&mut self, sink: MidiRouteNodeId, source: MidiRouteNodeId) -> Result<(), MidiRoutingError> {
+        let (s, o) = (self.node(sink)?, self.node(source)?);
+        if !matches!(s, MidiRouteNode::Sink { .. }) || !matches!(o, MidiRouteNode::Source { .. }) {
+            return Err(MidiRoutingError::WrongDirection { from: sink, to: source });
+        }
+        self.check_acyclic(sink, source)?;
+        self.echoes.insert((sink, source));
+        Ok(())
+    }
+
+    pub fn is_connected(&self, from: MidiRouteNodeId, to: MidiRouteNodeId) -> bool {
+        self.edges.contains(&(from, to))
+    }
+
+    fn node(&self, id: MidiRouteNodeId) -> Result<&MidiRouteNode<'p, 'lib>, MidiRoutingError> {
+        self.nodes.get(id.0).and_then(Option::as_ref).ok_or(MidiRoutingError::UnknownNode(id))
+    }
+
+    fn successors(&self, id: MidiRouteNodeId) -> impl Iterator<Item = MidiRouteNodeId> + '_ {
+        let edges  = self.edges.range((id, MidiRouteNodeId(0))..=(id, MidiRouteNodeId(usize::MAX))).map(|(_, to)| *to);
+        let echoes = self.echoes.iter().filter(move |(sink, _)| *sink == id).map(|(_, source)| *source);
+        edges.chain(echoes)
+    }
+
+    /// Adding `from -> to` closes a loop iff `from` is reachable from `to`.
+    fn check_acyclic(&self, from: MidiRouteNodeId, to: MidiRouteNodeId) -> Result<(), MidiRoutingError> {
+        let mut stack   = vec![vec![to]];
+        let mut visited = BTreeSet::new();
+        while let Some(path) = stack.pop() {
+            let last = *path.last().unwrap();
+            if last == from {
+                let mut cycle = vec![from];
+                cycle.extend(path);
+                warn!("routing: refusing feedback cycle {:?}", cycle);
+                return Err(MidiRoutingError::FeedbackCycle { path: cycle });
+            }
+            if !visited.insert(last) {
+                continue;
+            }
+            for next in self.successors(last) {
+                let mut longer = path.clone();
+                longer.push(next);
+                stack.push(longer);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drain every source and deliver what arrived.
+    pub fn pump(&mut self) -> MidiRoutingStats {
+        let mut stats = MidiRoutingStats::default();
+
+        for index in 0..self.nodes.len() {
+            let mut events = Vec::new();
+            if let Some(MidiRouteNode::Source { port, parser }) = &mut self.nodes[index] {
+                let drained = port.drain(|packet| match packet {
+                    ReceivedPacket::Data { bytes, timestamp } => {
+                        parser.feed(bytes, |message| events.push(TimedMidiEvent::new(timestamp, message)))
+                    }
+                    ReceivedPacket::Flush => parser.reset(),
+                });
+                if let Err(e) = drained {
+                    warn!("routing: receive on source {} failed: {}", index, e);
+                    stats.receive_errors += 1;
+                }
+            }
+            stats.received += events.len();
+            let source = MidiRouteNodeId(index);
+            let targets: Vec<_> = self.edges_from(source);
+            for event in events {
+                for &target in &targets {
+                    self.deliver(target, event.clone(), &mut stats);
+                }
+            }
+        }
+        stats
+    }
+
+    fn edges_from(&self, id: MidiRouteNodeId) -> Vec<MidiRouteNodeId> {
+        self.edges.range((id, MidiRouteNodeId(0))..=(id, MidiRouteNodeId(usize::MAX))).map(|(_, to)| *to).collect()
+    }
+
+    /// Push one event into a node. The graph is acyclic, so this terminates.
+    fn deliver(&mut self, id: MidiRouteNodeId, event: TimedMidiEvent, stats: &mut MidiRoutingStats) {
+        match &mut self.nodes[id.0] {
+            Some(MidiRouteNode::Sink { port }) => {
+                let bytes = event.message.to_bytes();
+                match port.send_all_at(&bytes, Some(event.timestamp), &self.send_policy) {
+                    Ok(_) => stats.delivered += 1,
+                    Err(e) => {
+                        warn!("routing: send to sink {:?} failed: {}", id, e);
+                        stats.send_errors += 1;
+                    }
+                }
+            }
+            Some(MidiRouteNode::Processor(processor)) => {
+                let mut out = Vec::new();
+                processor.process(event, &mut out);
+                let targets = self.edges_from(id);
+                for produced in out {
+                    for &target in &targets {
+                        self.deliver(target, produced.clone(), stats);
+                    }
+                }
+            }
+            Some(MidiRouteNode::Source { .. }) | None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_midi_routing_graph {
+    use super::*;
+
+    fn transpose(semitones: i8) -> impl FnMut(TimedMidiEvent, &mut Vec<TimedMidiEvent>) {
+        move |mut event: TimedMidiEvent, out: &mut Vec<TimedMidiEvent>| {
+            if let MidiMessage::NoteOn { note, .. } | MidiMessage::NoteOff { note, .. } = &mut event.message {
+                *note = U7::saturating((note.get() as i16 + semitones as i16).clamp(0, 127) as u8);
+            }
+            out.push(event);
+        }
+    }
+
+    #[test]
+    fn test_fans_out_through_processor() {
+        let keyboard = VirtualMidiDevice::new(0, 1);
+        let synths   = VirtualMidiDevice::new(2, 0);
+        keyboard.output_port(0).push(vec![0x90, 60, 100], 1_000);
+        keyboard.output_port(0).push(vec![0xf0, 0x7d], 2_000);
+
+        let (keyboard_device, synth_device) = (keyboard.open(), synths.open());
+        let source = keyboard_device.open_output_port(0).unwrap();
+        let (a, b) = (synth_device.open_input_port(0).unwrap(), synth_device.open_input_port(1).unwrap());
+
+        let mut graph = MidiRoutingGraph::new();
+        let kbd   = graph.add_source(&source);
+        let up    = graph.add_processor(transpose(12));
+        let synth_a = graph.add_sink(&a);
+        let synth_b = graph.add_sink(&b);
+        graph.connect(kbd, up).unwrap();
+        graph.connect(up, synth_a).unwrap();
+        graph.connect(kbd, synth_b).unwrap();
+
+        let stats = graph.pump();
+        assert_eq!((stats.received, stats.delivered), (1, 2));
+        assert_eq!(synths.input_port(0).sent()[0].bytes, vec![0x90, 72, 100]);
+        assert_eq!(synths.input_port(0).sent()[0].timestamp, Some(1_000));
+        assert_eq!(synths.input_port(1).sent_bytes(), vec![0x90, 60, 100]);
+
+        // Rewire while a SysEx is half received: it still arrives whole, on the new route.
+        graph.disconnect(kbd, synth_b);
+        graph.connect(up, synth_b).unwrap();
+        keyboard.output_port(0).push(vec![0x01, 0xf7, 0x80, 60, 0], 3_000);
+        let stats = graph.pump();
+        assert_eq!((stats.received, stats.delivered), (2, 4));
+        assert_eq!(synths.input_port(1).sent_bytes(), vec![0x90, 60, 100, 0xf0, 0x7d, 0x01, 0xf7, 0x80, 72, 0]);
+    }
+
+    #[test]
+    fn test_refuses_feedback_cycles() {
+        let device = VirtualMidiDevice::new(1, 1);
+        let opened = device.open();
+        let (output, input) = (opened.open_output_port(0).unwrap(), opened.open_input_port(0).unwrap());
+
+        let mut graph = MidiRoutingGraph::new();
+        let source = graph.add_source(&output);
+        let first  = graph.add_processor(transpose(1));
+        let second = graph.add_processor(transpose(1));
+        let sink   = graph.add_sink(&input);
+        graph.connect(source, first).unwrap();
+        graph.connect(first, second).unwrap();
+
+        assert_eq!(
+            graph.connect(second, first),
+            Err(MidiRoutingError::FeedbackCycle { path: vec![second, first, second] })
+        );
+        assert!(matches!(graph.connect(sink, first), Err(MidiRoutingError::WrongDirection { .. })));
+
+        // The synth echoes into our source, so routing to it would loop.
+        graph.declare_echo(sink, source).unwrap();
+        assert!(matches!(graph.connect(second, sink), Err(MidiRoutingError::FeedbackCycle { .. })));
+
+        graph.remove(first).unwrap();
+        assert_eq!(graph.connect(first, sink), Err(MidiRoutingError::UnknownNode(first)));
+    }
+}
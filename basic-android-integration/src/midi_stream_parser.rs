@@ -0,0 +1,236 @@
+// ---------------- [ File: basic-android-integration/src/midi_stream_parser.rs ]
+crate::ix!();
+
+/// Incremental MIDI 1.0 byte-stream parser.
+///
+/// Feed it bytes as they arrive from `MidiOutputPort::receive` (packet
+/// boundaries don't matter) and it yields complete [`MidiMessage`]s. It
+/// handles running status, realtime bytes interleaved anywhere (including
+/// inside SysEx), and SysEx reassembly across packets. System common
+/// messages and SysEx cancel running status. Stray data bytes, undefined
+/// status bytes and SysEx interrupted by another status byte are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MidiStreamParser {
+    running_status: Option<u8>,
+    status:         Option<u8>,
+    data:           [u8; 2],
+    data_len:       usize,
+    sysex:          Option<Vec<u8>>,
+    dropped_bytes:  usize,
+}
+
+impl MidiStreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a complete buffer in one go.
+    pub fn parse_all(bytes: &[u8]) -> Vec<MidiMessage> {
+        let mut parser = Self::new();
+        let mut out    = Vec::new();
+        parser.feed(bytes, |message| out.push(message));
+        out
+    }
+
+    /// Forget any partial message and running status, e.g. after a receive error.
+    pub fn reset(&mut self) {
+        *self = Self { dropped_bytes: self.dropped_bytes, ..Self::default() };
+    }
+
+    /// Bytes discarded so far because they could not belong to any message.
+    pub fn dropped_bytes(&self) -> usize {
+        self.dropped_bytes
+    }
+
+    /// Whether a SysEx is currently being reassembled.
+    pub fn in_sysex(&self) -> bool {
+        self.sysex.is_some()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut sink: impl FnMut(MidiMessage)) {
+        for &byte in bytes {
+            if let Some(message) = self.push(byte) {
+                sink(message);
+            }
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        if byte >= 0xf8 {
+            return realtime_message(byte).or_else(|| {
+                self.dropped_bytes += 1;
+                None
+            });
+        }
+
+        if byte < 0x80 {
+            return self.push_data(byte);
+        }
+
+        // Any non-realtime status byte ends a SysEx in progress.
+        if let Some(data) = self.sysex.take() {
+            if byte == 0xf7 {
+                return Some(MidiMessage::SysEx(SysExData::from_data_bytes(data)));
+            }
+            warn!("SysEx of {} bytes interrupted by status {:#04x}", data.len(), byte);
+            self.dropped_bytes += data.len() + 1;
+        }
+
+        self.data_len = 0;
+
+        match byte {
+            0xf0 => {
+                self.running_status = None;
+                self.status         = None;
+                self.sysex          = Some(Vec::new());
+                None
+            }
+            0x80..=0xef => {
+                self.running_status = Some(byte);
+                self.status         = Some(byte);
+                None
+            }
+            _ => {
+                self.running_status = None;
+                self.status         = None;
+                match status_message_len(byte) {
+                    Some(1) => (byte == 0xf6).then_some(MidiMessage::TuneRequest),
+                    Some(_) => {
+                        self.status = Some(byte);
+                        None
+                    }
+                    None => {
+                        // F4, F5, or a stray F7.
+                        self.dropped_bytes += 1;
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_data(&mut self, byte: u8) -> Option<MidiMessage> {
+        if let Some(sysex) = &mut self.sysex {
+            sysex.push(byte);
+            return None;
+        }
+
+        let Some(status) = self.status.or(self.running_status) else {
+            self.dropped_bytes += 1;
+            return None;
+        };
+        self.status = Some(status);
+
+        self.data[self.data_len] = byte;
+        self.data_len += 1;
+
+        let needed = status_message_len(status).unwrap_or(1) - 1;
+        if self.data_len < needed {
+            return None;
+        }
+
+        self.data_len = 0;
+        // Channel messages stay armed for running status; system common don't.
+        self.status = None;
+        Some(message_from_parts(status, self.data[0], self.data[1]))
+    }
+}
+
+fn realtime_message(byte: u8) -> Option<MidiMessage> {
+    match byte {
+        0xf8 => Some(MidiMessage::TimingClock),
+        0xfa => Some(MidiMessage::Start),
+        0xfb => Some(MidiMessage::Continue),
+        0xfc => Some(MidiMessage::Stop),
+        0xfe => Some(MidiMessage::ActiveSensing),
+        0xff => Some(MidiMessage::SystemReset),
+        _    => None,
+    }
+}
+
+fn message_from_parts(status: u8, d1: u8, d2: u8) -> MidiMessage {
+    let channel = Channel::from_status(status);
+    let a       = U7::from_masked(d1);
+    let b       = U7::from_masked(d2);
+    match status & 0xf0 {
+        0x80 => MidiMessage::NoteOff { channel, note: a, velocity: b },
+        0x90 => MidiMessage::NoteOn { channel, note: a, velocity: b },
+        0xa0 => MidiMessage::PolyPressure { channel, note: a, pressure: b },
+        0xb0 => MidiMessage::ControlChange { channel, controller: a, value: b },
+        0xc0 => MidiMessage::ProgramChange { channel, program: a },
+        0xd0 => MidiMessage::ChannelPressure { channel, pressure: a },
+        0xe0 => MidiMessage::PitchBend { channel, value: U14::from_msb_lsb(b, a) },
+        _ => match status {
+            0xf1 => MidiMessage::MtcQuarterFrame(a),
+            0xf2 => MidiMessage::SongPosition(U14::from_msb_lsb(b, a)),
+            _    => MidiMessage::SongSelect(a),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test_midi_stream_parser {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    #[test]
+    fn test_running_status_and_interleaved_realtime() {
+        let messages = MidiStreamParser::parse_all(&[0x90, 60, 0xf8, 100, 62, 101, 0xe1, 0x00, 0x40]);
+        assert_eq!(
+            messages,
+            vec![
+                MidiMessage::TimingClock,
+                MidiMessage::note_on(ch(0), u7(60), u7(100)),
+                MidiMessage::note_on(ch(0), u7(62), u7(101)),
+                MidiMessage::pitch_bend(ch(1), U14::CENTER),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sysex_across_packets_and_system_common_cancels_running_status() {
+        let mut parser = MidiStreamParser::new();
+        let mut out    = Vec::new();
+        parser.feed(&[0xb0, 7, 100, 0xf0, 0x41, 0x10], |m| out.push(m));
+        assert!(parser.in_sysex());
+        parser.feed(&[0xfe, 0x42, 0xf7, 0xf3, 2, 8, 9], |m| out.push(m));
+
+        assert_eq!(
+            out,
+            vec![
+                MidiMessage::control_change(ch(0), u7(7), u7(100)),
+                MidiMessage::ActiveSensing,
+                MidiMessage::sysex(vec![0x41, 0x10, 0x42]).unwrap(),
+                MidiMessage::song_select(u7(2)),
+            ]
+        );
+        // 8 and 9 had no status to attach to.
+        assert_eq!(parser.dropped_bytes(), 2);
+    }
+
+    #[test]
+    fn test_round_trips_every_message_kind() {
+        let messages = vec![
+            MidiMessage::note_off(ch(3), u7(1), u7(2)),
+            MidiMessage::poly_pressure(ch(4), u7(3), u7(4)),
+            MidiMessage::program_change(ch(5), u7(6)),
+            MidiMessage::channel_pressure(ch(6), u7(7)),
+            MidiMessage::pitch_bend(ch(15), U14::new(0x1234).unwrap()),
+            MidiMessage::sysex(vec![1, 2, 3]).unwrap(),
+            MidiMessage::mtc_quarter_frame(u7(0x35)),
+            MidiMessage::song_position(U14::new(300).unwrap()),
+            MidiMessage::TuneRequest,
+            MidiMessage::Start,
+            MidiMessage::Continue,
+            MidiMessage::Stop,
+            MidiMessage::SystemReset,
+        ];
+        let mut bytes = Vec::new();
+        for message in &messages {
+            message.write_to(&mut bytes);
+        }
+        assert_eq!(MidiStreamParser::parse_all(&bytes), messages);
+    }
+}
@@ -0,0 +1,378 @@
+// ---------------- [ File: basic-android-integration/src/midi_tools.rs ]
+crate::ix!();
+
+pub const MIDI_TOOLS_USAGE: &str = "\
+usage: midi-tools <command> [args]
+
+commands:
+    decode <hex>...                 decode hex bytes (\"90 3c 64\", \"0x90,0x3c\", \"903c64\")
+    decode --file <path>            decode a capture (binary or JSON lines) or a hex dump file
+    smf-to-json <in.mid> [out.json] write a Standard MIDI File as JSON (stdout by default)
+    json-to-smf <in.json> <out.mid> build a Standard MIDI File from JSON
+    sysex-check <hex>...            verify the checksum of a Roland-style SysEx message
+    generate <kind> [options]       print a test stream, one message per line
+
+generate kinds:
+    notes          chromatic note on/off pairs from middle C (at most 68)
+    cc-sweep       controller 1 from 0 to 127
+    pitch-sweep    pitch bend from lowest to highest
+    sysex          one SysEx with <count> incrementing data bytes (non-commercial id 7d)
+    clock          start, <count> timing clocks, stop
+
+generate options:
+    --channel <1-16>      channel for channel messages (default 1)
+    --count <n>           number of steps (default depends on kind)
+    --running-status      omit repeated status bytes
+    --binary              write raw bytes to stdout instead of hex
+";
+
+/// One `midi-tools` invocation, parsed from its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiToolsCommand {
+    Decode(Vec<u8>),
+    /// A capture (binary or JSON lines) or a hex dump.
+    DecodeFile(String),
+    SmfToJson { input: String, output: Option<String> },
+    JsonToSmf { input: String, output: String },
+    SysExCheck(Vec<u8>),
+    Generate(TestStreamOptions),
+}
+
+impl MidiToolsCommand {
+    /// Arguments after the program name.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let Some((command, args)) = args.split_first() else {
+            return Err("no command given".into());
+        };
+        Ok(match (command.as_str(), args) {
+            ("decode", [flag, path]) if flag == "--file" => MidiToolsCommand::DecodeFile(path.clone()),
+            ("decode", []) => return Err("decode needs hex bytes or --file <path>".into()),
+            ("decode", hex) => MidiToolsCommand::Decode(parse_hex_bytes(&hex.join(" "))?),
+            ("smf-to-json", [input]) => MidiToolsCommand::SmfToJson { input: input.clone(), output: None },
+            ("smf-to-json", [input, output]) => MidiToolsCommand::SmfToJson { input: input.clone(), output: Some(output.clone()) },
+            ("smf-to-json", _) => return Err("usage: smf-to-json <in.mid> [out.json]".into()),
+            ("json-to-smf", [input, output]) => MidiToolsCommand::JsonToSmf { input: input.clone(), output: output.clone() },
+            ("json-to-smf", _) => return Err("usage: json-to-smf <in.json> <out.mid>".into()),
+            ("sysex-check", hex) => MidiToolsCommand::SysExCheck(parse_hex_bytes(&hex.join(" "))?),
+            ("generate", args) => MidiToolsCommand::Generate(TestStreamOptions::from_args(args)?),
+            (other, _) => return Err(format!("unknown command {}", other)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStreamKind {
+    Notes,
+    CcSweep,
+    PitchSweep,
+    SysEx,
+    Clock,
+}
+
+impl TestStreamKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "notes" => TestStreamKind::Notes,
+            "cc-sweep" => TestStreamKind::CcSweep,
+            "pitch-sweep" => TestStreamKind::PitchSweep,
+            "sysex" => TestStreamKind::SysEx,
+            "clock" => TestStreamKind::Clock,
+            _ => return None,
+        })
+    }
+}
+
+/// What `generate` prints and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestStreamOptions {
+    pub kind:           TestStreamKind,
+    pub channel:        Channel,
+    /// Steps; `None` for the kind's default.
+    pub count:          Option<usize>,
+    pub running_status: bool,
+    /// Raw bytes instead of one hex line per message.
+    pub binary:         bool,
+}
+
+impl TestStreamOptions {
+    /// `generate`'s arguments: the kind, then options.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let Some((kind, args)) = args.split_first() else {
+            return Err("generate needs a kind".into());
+        };
+        let kind = TestStreamKind::from_name(kind).ok_or_else(|| format!("unknown stream kind {}", kind))?;
+
+        let mut options = Self { kind, channel: Channel::MIN, count: None, running_status: false, binary: false };
+        let mut args    = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--channel" => {
+                    let value = args.next().ok_or("--channel needs a value")?;
+                    let n: u8 = value.parse().map_err(|_| format!("bad channel {}", value))?;
+                    let channel = n.checked_sub(1).and_then(|n| Channel::new(n).ok());
+                    options.channel = channel.ok_or("channels are 1-16")?;
+                }
+                "--count" => {
+                    let value = args.next().ok_or("--count needs a value")?;
+                    options.count = Some(value.parse().map_err(|_| format!("bad count {}", value))?);
+                }
+                "--running-status" => options.running_status = true,
+                "--binary" => options.binary = true,
+                other => return Err(format!("unknown option {}", other)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn messages(&self) -> Result<Vec<MidiMessage>, String> {
+        generate_test_stream(self.kind, self.channel, self.count)
+    }
+
+    /// Each message's bytes, with running status if asked for.
+    pub fn encode(&self, messages: &[MidiMessage]) -> Vec<Vec<u8>> {
+        let mut encoder = RunningStatusEncoder::new();
+        messages
+            .iter()
+            .map(|message| {
+                let mut bytes = Vec::new();
+                if self.running_status {
+                    encoder.encode(message, &mut bytes);
+                } else {
+                    message.write_to(&mut bytes);
+                }
+                bytes
+            })
+            .collect()
+    }
+}
+
+/// Every hex digit pair in `text`; `0x` prefixes, separators and `#`
+/// comments are ignored.
+pub fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let mut digits = String::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        for token in line.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+            let token = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+            if let Some(bad) = token.chars().find(|c| !c.is_ascii_hexdigit()) {
+                return Err(format!("not a hex digit: {:?}", bad));
+            }
+            if token.len() % 2 == 1 {
+                digits.push('0');
+            }
+            digits.push_str(token);
+        }
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Lower-case hex, space separated.
+pub fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// One line per decoded message, then notes on an unterminated SysEx or
+/// dropped bytes.
+pub fn decode_to_lines(bytes: &[u8]) -> Vec<String> {
+    let mut lines  = Vec::new();
+    let mut parser = MidiStreamParser::new();
+    parser.feed(bytes, |message| lines.push(format!("{:<12} {}", hex_string(&message.to_bytes()), message)));
+    if parser.in_sysex() {
+        lines.push("<unterminated sysex>".into());
+    }
+    if parser.dropped_bytes() > 0 {
+        lines.push(format!("<{} stray byte(s) dropped>", parser.dropped_bytes()));
+    }
+    lines
+}
+
+/// A capture is annotated packet by packet; anything else is read as a
+/// hex dump.
+pub fn decode_file_to_lines(contents: &[u8]) -> Result<Vec<String>, String> {
+    if let Ok(capture) = MidiCapture::parse(contents)
+        && !capture.records.is_empty()
+    {
+        return Ok(capture.annotate(&[], false));
+    }
+    Ok(decode_to_lines(&parse_hex_bytes(&String::from_utf8_lossy(contents))?))
+}
+
+pub fn smf_to_json(bytes: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(serde_json::to_string_pretty(&Smf::parse(bytes)?)?)
+}
+
+/// Every field, SysEx data included, is range-checked on the way in, and
+/// the result is parsed back before it is returned.
+pub fn json_to_smf(json: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let smf: Smf = serde_json::from_str(json)?;
+    let bytes = smf.to_bytes()?;
+    Smf::parse(&bytes)?;
+    Ok(bytes)
+}
+
+/// Lines describing a Roland-style exclusive message and its checksum,
+/// and whether the checksum is right.
+pub fn check_roland_sysex(bytes: &[u8]) -> Result<(Vec<String>, bool), String> {
+    let Some(message) = RolandSysEx::parse(bytes) else {
+        return Err("not a Roland-style exclusive message (F0 41 dev model cmd ... sum F7)".into());
+    };
+    let parts = format!(
+        "device {:02x} model {} command {:02x} body {}",
+        message.device_id,
+        hex_string(message.model_id),
+        message.command,
+        hex_string(message.body)
+    );
+    Ok(if message.checksum_ok() {
+        (vec![parts, format!("checksum {:02x} ok", message.checksum)], true)
+    } else {
+        (vec![parts, format!("checksum {:02x}, expected {:02x}", message.checksum, message.expected_checksum())], false)
+    })
+}
+
+/// Notes go up from middle C, so at most 68 fit below 128.
+pub fn generate_test_stream(kind: TestStreamKind, channel: Channel, count: Option<usize>) -> Result<Vec<MidiMessage>, String> {
+    Ok(match kind {
+        TestStreamKind::Notes => {
+            let count = count.unwrap_or(12);
+            if count > 68 {
+                return Err(format!("notes from middle C stop at 127; {} is more than 68", count));
+            }
+            (60..60 + count as u8)
+                .flat_map(|note| {
+                    let note = U7::saturating(note);
+                    [MidiMessage::note_on(channel, note, U7::new(100).unwrap()), MidiMessage::note_off(channel, note, U7::MIN)]
+                })
+                .collect()
+        }
+        TestStreamKind::CcSweep => {
+            let steps = count.unwrap_or(128).max(2);
+            (0..steps)
+                .map(|i| {
+                    let value = U7::saturating((i * 127 / (steps - 1)) as u8);
+                    MidiMessage::control_change(channel, U7::new(1).unwrap(), value)
+                })
+                .collect()
+        }
+        TestStreamKind::PitchSweep => {
+            let steps = count.unwrap_or(129).max(2);
+            (0..steps)
+                .map(|i| {
+                    let value = (i * U14::MAX.get() as usize / (steps - 1)) as u16;
+                    MidiMessage::pitch_bend(channel, U14::new(value).unwrap())
+                })
+                .collect()
+        }
+        TestStreamKind::SysEx => {
+            let data: Vec<u8> = std::iter::once(0x7d).chain((0..count.unwrap_or(16)).map(|i| (i % 128) as u8)).collect();
+            vec![MidiMessage::sysex(data).map_err(|e| e.to_string())?]
+        }
+        TestStreamKind::Clock => std::iter::once(MidiMessage::Start)
+            .chain(std::iter::repeat_n(MidiMessage::TimingClock, count.unwrap_or(24)))
+            .chain(std::iter::once(MidiMessage::Stop))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test_midi_tools {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_hex_parsing() {
+        assert_eq!(parse_hex_bytes("90 3c 64"), Ok(vec![0x90, 0x3c, 0x64]));
+        assert_eq!(parse_hex_bytes("0x90,0x3C\n903c64 # comment f0"), Ok(vec![0x90, 0x3c, 0x90, 0x3c, 0x64]));
+        assert_eq!(parse_hex_bytes("f 1:2"), Ok(vec![0x0f, 0x01, 0x02]));
+        assert_eq!(parse_hex_bytes("9g"), Err("not a hex digit: 'g'".into()));
+        assert_eq!(hex_string(&[0xf0, 0x7e]), "f0 7e");
+    }
+
+    #[test]
+    fn test_argument_parsing() {
+        assert_eq!(MidiToolsCommand::from_args(&args("decode 90 3c 64")), Ok(MidiToolsCommand::Decode(vec![0x90, 0x3c, 0x64])));
+        assert_eq!(MidiToolsCommand::from_args(&args("decode --file dump.txt")), Ok(MidiToolsCommand::DecodeFile("dump.txt".into())));
+        assert_eq!(
+            MidiToolsCommand::from_args(&args("smf-to-json a.mid")),
+            Ok(MidiToolsCommand::SmfToJson { input: "a.mid".into(), output: None })
+        );
+        assert!(MidiToolsCommand::from_args(&args("json-to-smf a.json")).is_err());
+        assert!(MidiToolsCommand::from_args(&args("decode")).is_err());
+        assert!(MidiToolsCommand::from_args(&args("frobnicate")).is_err());
+        assert!(MidiToolsCommand::from_args(&[]).is_err());
+
+        let Ok(MidiToolsCommand::Generate(options)) = MidiToolsCommand::from_args(&args("generate notes --channel 16 --count 3 --running-status")) else {
+            panic!("generate did not parse");
+        };
+        assert_eq!(options, TestStreamOptions {
+            kind:           TestStreamKind::Notes,
+            channel:        Channel::MAX,
+            count:          Some(3),
+            running_status: true,
+            binary:         false,
+        });
+        for bad in ["generate", "generate waves", "generate notes --channel 0", "generate notes --channel 17", "generate clock --count x", "generate clock --loud"] {
+            assert!(TestStreamOptions::from_args(&args(bad)[1..]).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_decoding() {
+        assert_eq!(decode_to_lines(&[0x90, 0x3c, 0x64, 0x3e, 0x64]).len(), 2);
+        assert_eq!(decode_to_lines(&[0x3c, 0xf0, 0x01]), vec!["<unterminated sysex>", "<1 stray byte(s) dropped>"]);
+        assert_eq!(decode_file_to_lines(b"# dump\n80 3c 00\n"), Ok(decode_to_lines(&[0x80, 0x3c, 0x00])));
+    }
+
+    #[test]
+    fn test_smf_json_conversion() {
+        let mut smf = Smf::new(SmfFormat::SingleTrack, SmfTiming::Metrical(96));
+        smf.tracks.push(SmfTrack::new(vec![
+            SmfEvent::midi(0, MidiMessage::note_on(Channel::MIN, U7::new(60).unwrap(), U7::new(100).unwrap())),
+            SmfEvent::meta(96, MetaEvent::EndOfTrack),
+        ]));
+        let bytes = smf.to_bytes().unwrap();
+        let json  = smf_to_json(&bytes).unwrap();
+        assert_eq!(json_to_smf(&json).unwrap(), bytes);
+        assert!(json_to_smf(&json.replacen("60", "128", 1)).is_err());
+        assert!(smf_to_json(b"MThd").is_err());
+    }
+
+    #[test]
+    fn test_roland_checksum_report() {
+        let good = parse_hex_bytes("f0 41 10 42 12 40 00 7f 00 41 f7").unwrap();
+        let (lines, ok) = check_roland_sysex(&good).unwrap();
+        assert!(ok);
+        assert_eq!(lines, vec!["device 10 model 42 command 12 body 40 00 7f 00", "checksum 41 ok"]);
+
+        let (lines, ok) = check_roland_sysex(&parse_hex_bytes("f0 41 10 42 12 40 00 7f 00 40 f7").unwrap()).unwrap();
+        assert!(!ok);
+        assert_eq!(lines[1], "checksum 40, expected 41");
+        assert!(check_roland_sysex(&[0xf0, 0x43, 0xf7]).is_err());
+    }
+
+    #[test]
+    fn test_stream_generation() {
+        let ch = Channel::MIN;
+        let notes = generate_test_stream(TestStreamKind::Notes, ch, Some(68)).unwrap();
+        assert_eq!(notes.len(), 136);
+        assert_eq!(notes[134], MidiMessage::note_on(ch, U7::MAX, U7::new(100).unwrap()));
+        assert!(generate_test_stream(TestStreamKind::Notes, ch, Some(69)).is_err());
+
+        let sweep = generate_test_stream(TestStreamKind::CcSweep, ch, Some(3)).unwrap();
+        let values: Vec<_> = sweep.iter().map(|m| m.to_bytes()[2]).collect();
+        assert_eq!(values, vec![0, 63, 127]);
+        let bend = generate_test_stream(TestStreamKind::PitchSweep, ch, None).unwrap();
+        assert_eq!(bend.last(), Some(&MidiMessage::pitch_bend(ch, U14::MAX)));
+        assert_eq!(generate_test_stream(TestStreamKind::Clock, ch, Some(2)).unwrap().len(), 4);
+        assert_eq!(generate_test_stream(TestStreamKind::SysEx, ch, Some(200)).unwrap()[0].to_bytes().len(), 203);
+
+        let options = TestStreamOptions { kind: TestStreamKind::CcSweep, channel: ch, count: Some(3), running_status: true, binary: false };
+        assert_eq!(options.encode(&options.messages().unwrap()), vec![vec![0xb0, 1, 0], vec![1, 63], vec![1, 127]]);
+    }
+}
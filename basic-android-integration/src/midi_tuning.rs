@@ -0,0 +1,96 @@
+// ---------------- [ File: basic-android-integration/src/midi_tuning.rs ]
+crate::ix!();
+
+/// Frequency in Hz of a (fractional) MIDI note number, A4 = 69 = 440 Hz.
+pub fn pitch_to_hz(pitch: f64) -> f64 {
+    440.0 * ((pitch - 69.0) / 12.0).exp2()
+}
+
+/// Fractional MIDI note number of a frequency in Hz.
+pub fn hz_to_pitch(hz: f64) -> f64 {
+    69.0 + 12.0 * (hz / 440.0).log2()
+}
+
+/// What each of the 128 keys should sound, as a fractional MIDI note number
+/// (60.5 is a quarter tone above middle C), `None` for silent keys.
+///
+/// This is the common ground between the MTS encoders and the pitch-bend
+/// fallback: build it from a Scala scale or by hand, then send it either way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiTuning {
+    pitches: [Option<f64>; 128],
+}
+
+impl Default for MidiTuning {
+    fn default() -> Self {
+        Self::equal_temperament()
+    }
+}
+
+impl MidiTuning {
+    /// Standard 12-tone equal temperament: key `k` plays pitch `k`.
+    pub fn equal_temperament() -> Self {
+        Self::from_fn(|key| Some(key as f64))
+    }
+
+    pub fn from_fn(mut pitch: impl FnMut(u8) -> Option<f64>) -> Self {
+        Self { pitches: std::array::from_fn(|key| pitch(key as u8)) }
+    }
+
+    /// Keys outside the mapping's range, or mapped to `x`, are silent.
+    pub fn from_scala(scale: &ScalaScale, mapping: &ScalaKeyboardMapping) -> Result<Self, ScalaError> {
+        let mut pitches = [None; 128];
+        for (key, pitch) in (0..=127u8).zip(pitches.iter_mut()) {
+            *pitch = mapping.frequency(scale, key)?.map(hz_to_pitch);
+        }
+        Ok(Self { pitches })
+    }
+
+    pub fn pitch(&self, key: U7) -> Option<f64> {
+        self.pitches[key.get() as usize]
+    }
+
+    pub fn set_pitch(&mut self, key: U7, pitch: Option<f64>) {
+        self.pitches[key.get() as usize] = pitch;
+    }
+
+    pub fn frequency(&self, key: U7) -> Option<f64> {
+        self.pitch(key).map(pitch_to_hz)
+    }
+
+    /// Every key with its pitch, in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (U7, Option<f64>)> + '_ {
+        (0..=127u8).map(U7::saturating).zip(self.pitches.iter().copied())
+    }
+
+    /// Deviation in cents from equal temperament of each pitch class, taken
+    /// from the octave at middle C (keys 60..=71); silent keys count as 0.
+    /// This is what an MTS scale/octave message carries.
+    pub fn octave_cents(&self) -> [f64; 12] {
+        std::array::from_fn(|class| {
+            let key = 60 + class;
+            self.pitches[key].map(|pitch| (pitch - key as f64) * 100.0).unwrap_or(0.0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_midi_tuning {
+    use super::*;
+
+    #[test]
+    fn test_nineteen_edo() {
+        let tuning = MidiTuning::from_scala(&ScalaScale::equal_temperament(19), &ScalaKeyboardMapping::default()).unwrap();
+        let a4 = tuning.frequency(U7::saturating(69)).unwrap();
+        assert!((a4 - 440.0).abs() < 1e-9);
+
+        // Degree 0 on key 60 sits 9 steps of 1200/19 cents below A 440.
+        let c = tuning.pitch(U7::saturating(60)).unwrap();
+        assert!((c - (69.0 - 9.0 * 12.0 / 19.0)).abs() < 1e-9);
+        let step = tuning.pitch(U7::saturating(61)).unwrap() - c;
+        assert!((step - 12.0 / 19.0).abs() < 1e-9);
+
+        assert_eq!(MidiTuning::equal_temperament().octave_cents(), [0.0; 12]);
+        assert!((pitch_to_hz(hz_to_pitch(261.5)) - 261.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,252 @@
+// ---------------- [ File: basic-android-integration/src/midi_value_types.rs ]
+crate::ix!();
+
+/// A value did not fit the field it was meant for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiValueError {
+    OutOfRange {
+        what:  &'static str,
+        value: i64,
+        min:   i64,
+        max:   i64,
+    },
+
+    /// A NaN or infinite value where a real number is needed.
+    NotFinite {
+        what: &'static str,
+    },
+
+    /// SysEx payloads may only contain 7-bit data bytes.
+    SysExDataByte {
+        index: usize,
+        byte:  u8,
+    },
+}
+
+impl std::fmt::Display for MidiValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiValueError::OutOfRange { what, value, min, max } => {
+                write!(f, "{} {} is outside {}..={}", what, value, min, max)
+            }
+            MidiValueError::NotFinite { what } => write!(f, "{} is not a finite number", what),
+            MidiValueError::SysExDataByte { index, byte } => {
+                write!(f, "sysex data byte {:#04x} at index {} has the high bit set", byte, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MidiValueError {}
+
+/// A MIDI channel, 0..=15 on the wire (shown to users as 1..=16).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Channel(u8);
+
+impl Channel {
+    pub const MIN: Channel = Channel(0);
+    pub const MAX: Channel = Channel(15);
+
+    pub fn new(channel: u8) -> Result<Self, MidiValueError> {
+        if channel > 15 {
+            return Err(MidiValueError::OutOfRange { what: "channel", value: channel as i64, min: 0, max: 15 });
+        }
+        Ok(Channel(channel))
+    }
+
+    /// Take the low nibble of a status byte.
+    pub const fn from_status(status: u8) -> Self {
+        Channel(status & 0x0f)
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    /// All sixteen channels in order.
+    pub fn all() -> impl Iterator<Item = Channel> {
+        (0..16).map(Channel)
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = MidiValueError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Channel::new(value)
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(value: Channel) -> u8 {
+        value.0
+    }
+}
+
+/// A 7-bit data value (note number, velocity, controller value, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct U7(u8);
+
+impl U7 {
+    pub const MIN: U7 = U7(0);
+    pub const MAX: U7 = U7(127);
+
+    pub fn new(value: u8) -> Result<Self, MidiValueError> {
+        if value > 127 {
+            return Err(MidiValueError::OutOfRange { what: "7-bit value", value: value as i64, min: 0, max: 127 });
+        }
+        Ok(U7(value))
+    }
+
+    /// Clamp to 0..=127 instead of failing.
+    pub const fn saturating(value: u8) -> Self {
+        if value > 127 { U7(127) } else { U7(value) }
+    }
+
+    /// Drop the high bit. Used on bytes the parser already knows are data bytes.
+    pub(crate) const fn from_masked(value: u8) -> Self {
+        U7(value & 0x7f)
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for U7 {
+    type Error = MidiValueError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        U7::new(value)
+    }
+}
+
+impl From<U7> for u8 {
+    fn from(value: U7) -> u8 {
+        value.0
+    }
+}
+
+/// A 14-bit value sent as two 7-bit halves (pitch bend, song position, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct U14(u16);
+
+impl U14 {
+    pub const MIN:    U14 = U14(0);
+    pub const MAX:    U14 = U14(0x3fff);
+    /// No pitch bend.
+    pub const CENTER: U14 = U14(0x2000);
+
+    pub fn new(value: u16) -> Result<Self, MidiValueError> {
+        if value > 0x3fff {
+            return Err(MidiValueError::OutOfRange { what: "14-bit value", value: value as i64, min: 0, max: 0x3fff });
+        }
+        Ok(U14(value))
+    }
+
+    /// Pitch-bend style signed value, -8192..=8191 around [`U14::CENTER`].
+    pub fn from_signed(value: i16) -> Result<Self, MidiValueError> {
+        if !(-8192..=8191).contains(&value) {
+            return Err(MidiValueError::OutOfRange { what: "signed 14-bit value", value: value as i64, min: -8192, max: 8191 });
+        }
+        Ok(U14((value + 8192) as u16))
+    }
+
+    pub const fn from_msb_lsb(msb: U7, lsb: U7) -> Self {
+        U14(((msb.0 as u16) << 7) | lsb.0 as u16)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    pub const fn signed(self) -> i16 {
+        self.0 as i16 - 8192
+    }
+
+    pub const fn msb(self) -> U7 {
+        U7((self.0 >> 7) as u8)
+    }
+
+    pub const fn lsb(self) -> U7 {
+        U7((self.0 & 0x7f) as u8)
+    }
+}
+
+impl TryFrom<u16> for U14 {
+    type Error = MidiValueError;
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        U14::new(value)
+    }
+}
+
+impl From<U14> for u16 {
+    fn from(value: U14) -> u16 {
+        value.0
+    }
+}
+
+/// SysEx data, the bytes between `F0` and `F7`, all 7-bit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub struct SysExData(Vec<u8>);
+
+impl SysExData {
+    /// Fails if any byte has its high bit set.
+    pub fn new(data: impl Into<Vec<u8>>) -> Result<Self, MidiValueError> {
+        let data = data.into();
+        if let Some(index) = data.iter().position(|b| *b & 0x80 != 0) {
+            return Err(MidiValueError::SysExDataByte { index, byte: data[index] });
+        }
+        Ok(SysExData(data))
+    }
+
+    /// For bytes the parser already knows are data bytes.
+    pub(crate) fn from_data_bytes(data: Vec<u8>) -> Self {
+        debug_assert!(data.iter().all(|b| *b < 0x80));
+        SysExData(data)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl std::ops::Deref for SysExData {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SysExData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<u8>> for SysExData {
+    type Error = MidiValueError;
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        SysExData::new(value)
+    }
+}
+
+impl From<SysExData> for Vec<u8> {
+    fn from(value: SysExData) -> Vec<u8> {
+        value.0
+    }
+}
+
+/// The count byte for `len` data bytes.
+pub(crate) fn count_byte(len: usize, what: &'static str) -> Result<u8, MidiValueError> {
+    match u8::try_from(len) {
+        Ok(count) if count <= 127 => Ok(count),
+        _ => Err(MidiValueError::OutOfRange { what, value: len as i64, min: 0, max: 127 }),
+    }
+}
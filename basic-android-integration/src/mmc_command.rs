@@ -0,0 +1,205 @@
+// ---------------- [ File: basic-android-integration/src/mmc_command.rs ]
+crate::ix!();
+
+/// Sub-id #1 of MMC commands (controller to device) and responses.
+pub const MMC_COMMAND_SUB_ID:  u8 = 0x06;
+pub const MMC_RESPONSE_SUB_ID: u8 = 0x07;
+
+/// Commands 0x40-0x77 carry a count byte and that many data bytes.
+fn mmc_has_data(command: u8) -> bool {
+    (0x40..=0x77).contains(&command)
+}
+
+/// Where a Locate command goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmcLocateTarget {
+    Time(SmptePosition),
+    /// A time code field held by the device (e.g. a GP register).
+    Field(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MmcCommand {
+    Stop,
+    Play,
+    DeferredPlay,
+    FastForward,
+    Rewind,
+    /// Punch in (or out, if already recording).
+    RecordStrobe,
+    RecordExit,
+    RecordPause,
+    Pause,
+    Eject,
+    Chase,
+    CommandErrorReset,
+    MmcReset,
+    Locate(MmcLocateTarget),
+    /// Ask for these response fields; see [`MmcResponse`].
+    Read(Vec<u8>),
+    Other { command: u8, data: Vec<u8> },
+}
+
+impl MmcCommand {
+    fn simple(command: u8) -> Option<Self> {
+        Some(match command {
+            0x01 => MmcCommand::Stop,
+            0x02 => MmcCommand::Play,
+            0x03 => MmcCommand::DeferredPlay,
+            0x04 => MmcCommand::FastForward,
+            0x05 => MmcCommand::Rewind,
+            0x06 => MmcCommand::RecordStrobe,
+            0x07 => MmcCommand::RecordExit,
+            0x08 => MmcCommand::RecordPause,
+            0x09 => MmcCommand::Pause,
+            0x0a => MmcCommand::Eject,
+            0x0b => MmcCommand::Chase,
+            0x0c => MmcCommand::CommandErrorReset,
+            0x0d => MmcCommand::MmcReset,
+            _ => return None,
+        })
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            MmcCommand::Stop => 0x01,
+            MmcCommand::Play => 0x02,
+            MmcCommand::DeferredPlay => 0x03,
+            MmcCommand::FastForward => 0x04,
+            MmcCommand::Rewind => 0x05,
+            MmcCommand::RecordStrobe => 0x06,
+            MmcCommand::RecordExit => 0x07,
+            MmcCommand::RecordPause => 0x08,
+            MmcCommand::Pause => 0x09,
+            MmcCommand::Eject => 0x0a,
+            MmcCommand::Chase => 0x0b,
+            MmcCommand::CommandErrorReset => 0x0c,
+            MmcCommand::MmcReset => 0x0d,
+            MmcCommand::Read(_) => 0x42,
+            MmcCommand::Locate(_) => 0x44,
+            MmcCommand::Other { command, .. } => *command,
+        }
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) -> Result<(), MachineControlError> {
+        out.push(self.code());
+        let data = match self {
+            MmcCommand::Locate(MmcLocateTarget::Time(position)) => {
+                std::iter::once(0x01).chain(position.to_bytes()).collect()
+            }
+            MmcCommand::Locate(MmcLocateTarget::Field(field)) => vec![0x00, *field],
+            MmcCommand::Read(fields) => fields.clone(),
+            MmcCommand::Other { data, .. } => data.clone(),
+            _ => return Ok(()),
+        };
+        if mmc_has_data(self.code()) {
+            out.push(count_byte(data.len(), "MMC command data length")?);
+        }
+        out.extend_from_slice(&data);
+        Ok(())
+    }
+
+    fn decode(command: u8, data: &[u8]) -> Result<Self, MachineControlError> {
+        if let Some(simple) = Self::simple(command) {
+            return Ok(simple);
+        }
+        Ok(match (command, data) {
+            (0x42, fields) => MmcCommand::Read(fields.to_vec()),
+            (0x44, [0x01, hr, mn, sc, fr, sf]) => {
+                MmcCommand::Locate(MmcLocateTarget::Time(SmptePosition::from_bytes([*hr, *mn, *sc, *fr, *sf])?))
+            }
+            (0x44, [0x00, field]) => MmcCommand::Locate(MmcLocateTarget::Field(*field)),
+            (0x44, _) => return Err(MachineControlError::Truncated { what: "locate target" }),
+            _ => MmcCommand::Other { command, data: data.to_vec() },
+        })
+    }
+}
+
+/// One MMC command message: any number of commands for one device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MmcMessage {
+    pub device_id: U7,
+    pub commands:  Vec<MmcCommand>,
+}
+
+impl MmcMessage {
+    pub fn new(device_id: U7, commands: Vec<MmcCommand>) -> Self {
+        Self { device_id, commands }
+    }
+
+    /// SysEx data, without `F0`/`F7`. Fails if a command's data is longer
+    /// than 127 bytes or a command code or data byte isn't 7-bit.
+    pub fn to_sysex(&self) -> Result<Vec<u8>, MachineControlError> {
+        let mut out = vec![UNIVERSAL_REAL_TIME, self.device_id.get(), MMC_COMMAND_SUB_ID];
+        for command in &self.commands {
+            command.write_to(&mut out)?;
+        }
+        Ok(SysExData::new(out)?.into_vec())
+    }
+
+    pub fn to_message(&self) -> Result<MidiMessage, MachineControlError> {
+        Ok(MidiMessage::sysex(self.to_sysex()?)?)
+    }
+
+    pub fn from_sysex(data: &[u8]) -> Result<Self, MachineControlError> {
+        let [UNIVERSAL_REAL_TIME, device_id, MMC_COMMAND_SUB_ID, rest @ ..] = data else {
+            return Err(MachineControlError::NotMachineControl);
+        };
+        let mut rest = rest;
+        let mut commands = Vec::new();
+        while let [command, tail @ ..] = rest {
+            let (data, tail) = match mmc_has_data(*command) {
+                true => split_counted(tail, "command data")?,
+                false => (&[][..], tail),
+            };
+            commands.push(MmcCommand::decode(*command, data)?);
+            rest = tail;
+        }
+        Ok(Self { device_id: U7::new(*device_id)?, commands })
+    }
+
+    pub fn send(&self, port: &MidiInputPort) -> Result<usize, MachineControlError> {
+        Ok(port.send_all(&self.to_message()?.to_bytes())?)
+    }
+}
+
+/// Split off a count byte and the data it counts.
+pub(crate) fn split_counted<'a>(bytes: &'a [u8], what: &'static str) -> Result<(&'a [u8], &'a [u8]), MachineControlError> {
+    match bytes {
+        [count, rest @ ..] if rest.len() >= *count as usize => Ok(rest.split_at(*count as usize)),
+        _ => Err(MachineControlError::Truncated { what }),
+    }
+}
+
+#[cfg(test)]
+mod test_mmc_command {
+    use super::*;
+
+    #[test]
+    fn test_command_stream_round_trip() {
+        let locate = SmptePosition::new(SmpteTime::new(1, 2, 3, 4, SmpteRate::Fps25).unwrap(), 0);
+        let message = MmcMessage::new(ALL_CALL, vec![
+            MmcCommand::Locate(MmcLocateTarget::Time(locate)),
+            MmcCommand::Play,
+            MmcCommand::Read(vec![0x01]),
+        ]);
+        let sysex = message.to_sysex().unwrap();
+        assert_eq!(sysex, vec![0x7f, 0x7f, 0x06, 0x44, 0x06, 0x01, 0x21, 0x02, 0x03, 0x04, 0x00, 0x02, 0x42, 0x01, 0x01]);
+        assert_eq!(MmcMessage::from_sysex(&sysex), Ok(message));
+
+        assert_eq!(MmcMessage::from_sysex(&[0x7f, 0x10, 0x06, 0x44, 0x06, 0x01]), Err(MachineControlError::Truncated { what: "command data" }));
+        assert_eq!(MmcMessage::from_sysex(&[0x7e, 0x10, 0x06, 0x01]), Err(MachineControlError::NotMachineControl));
+    }
+
+    #[test]
+    fn test_unencodable_commands_are_rejected() {
+        let high_byte = MmcMessage::new(ALL_CALL, vec![MmcCommand::Read(vec![0x01, 0x80])]);
+        assert_eq!(high_byte.to_message(), Err(MachineControlError::Value(MidiValueError::SysExDataByte { index: 6, byte: 0x80 })));
+
+        let too_long = MmcMessage::new(ALL_CALL, vec![MmcCommand::Other { command: 0x60, data: vec![0; 128] }]);
+        assert_eq!(
+            too_long.to_sysex(),
+            Err(MachineControlError::Value(MidiValueError::OutOfRange { what: "MMC command data length", value: 128, min: 0, max: 127 }))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+// ---------------- [ File: basic-android-integration/src/mmc_response.rs ]
+crate::ix!();
+
+/// A few of the MMC response fields; 0x01-0x1F are full time code fields.
+pub const MMC_FIELD_SELECTED_TIME_CODE:   u8 = 0x01;
+pub const MMC_FIELD_SELECTED_MASTER_CODE: u8 = 0x02;
+pub const MMC_FIELD_GENERATOR_TIME_CODE:  u8 = 0x06;
+pub const MMC_FIELD_SIGNATURE:            u8 = 0x40;
+pub const MMC_FIELD_MOTION_CONTROL_TALLY: u8 = 0x48;
+pub const MMC_FIELD_RECORD_STATUS:        u8 = 0x4c;
+
+/// One field of an MMC response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MmcResponse {
+    TimeCode { field: u8, position: SmptePosition },
+    /// Short time code fields (0x21-0x3F, frames and status only) and
+    /// everything else, data as sent (without the count byte).
+    Other { field: u8, data: Vec<u8> },
+}
+
+impl MmcResponse {
+    pub fn field(&self) -> u8 {
+        match self {
+            MmcResponse::TimeCode { field, .. } | MmcResponse::Other { field, .. } => *field,
+        }
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) -> Result<(), MachineControlError> {
+        out.push(self.field());
+        match self {
+            MmcResponse::TimeCode { position, .. } => out.extend_from_slice(&position.to_bytes()),
+            MmcResponse::Other { field, data } => {
+                if (0x40..=0x77).contains(field) {
+                    out.push(count_byte(data.len(), "MMC response data length")?);
+                }
+                out.extend_from_slice(data);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One MMC response message: fields reported by the device `device_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MmcResponseMessage {
+    pub device_id: U7,
+    pub responses: Vec<MmcResponse>,
+}
+
+impl MmcResponseMessage {
+    pub fn new(device_id: U7, responses: Vec<MmcResponse>) -> Self {
+        Self { device_id, responses }
+    }
+
+    /// SysEx data, without `F0`/`F7`. Fails on over-long or non-7-bit
+    /// field data.
+    pub fn to_sysex(&self) -> Result<Vec<u8>, MachineControlError> {
+        let mut out = vec![UNIVERSAL_REAL_TIME, self.device_id.get(), MMC_RESPONSE_SUB_ID];
+        for response in &self.responses {
+            response.write_to(&mut out)?;
+        }
+        Ok(SysExData::new(out)?.into_vec())
+    }
+
+    pub fn to_message(&self) -> Result<MidiMessage, MachineControlError> {
+        Ok(MidiMessage::sysex(self.to_sysex()?)?)
+    }
+
+    pub fn from_sysex(data: &[u8]) -> Result<Self, MachineControlError> {
+        let [UNIVERSAL_REAL_TIME, device_id, MMC_RESPONSE_SUB_ID, rest @ ..] = data else {
+            return Err(MachineControlError::NotMachineControl);
+        };
+        let mut rest = rest;
+        let mut responses = Vec::new();
+        while let [field, tail @ ..] = rest {
+            let field = *field;
+            let (response, tail) = match field {
+                0x01..=0x1f => match tail {
+                    [hr, mn, sc, fr, sf, tail @ ..] => {
+                        let position = SmptePosition::from_bytes([*hr, *mn, *sc, *fr, *sf])?;
+                        (MmcResponse::TimeCode { field, position }, tail)
+                    }
+                    _ => return Err(MachineControlError::Truncated { what: "time code field" }),
+                },
+                0x21..=0x3f if tail.len() >= 2 => (MmcResponse::Other { field, data: tail[..2].to_vec() }, &tail[2..]),
+                0x21..=0x3f => return Err(MachineControlError::Truncated { what: "short time code field" }),
+                0x40..=0x77 => {
+                    let (data, tail) = split_counted(tail, "response data")?;
+                    (MmcResponse::Other { field, data: data.to_vec() }, tail)
+                }
+                _ => (MmcResponse::Other { field, data: Vec::new() }, tail),
+            };
+            responses.push(response);
+            rest = tail;
+        }
+        Ok(Self { device_id: U7::new(*device_id)?, responses })
+    }
+
+    pub fn send(&self, port: &MidiInputPort) -> Result<usize, MachineControlError> {
+        Ok(port.send_all(&self.to_message()?.to_bytes())?)
+    }
+}
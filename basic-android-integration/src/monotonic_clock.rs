@@ -0,0 +1,58 @@
+// ---------------- [ File: basic-android-integration/src/monotonic_clock.rs ]
+crate::ix!();
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Source of "now" in the time base AMidi timestamps use: nanoseconds of
+/// `CLOCK_MONOTONIC` (`System.nanoTime()` on the java side).
+pub trait MonotonicClock {
+    fn now_nanos(&self) -> i64;
+}
+
+/// The real `CLOCK_MONOTONIC`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemMonotonicClock;
+
+impl MonotonicClock for SystemMonotonicClock {
+    // time_t and c_long are 32-bit on armeabi-v7a, so the casts are needed there.
+    #[allow(clippy::unnecessary_cast)]
+    fn now_nanos(&self) -> i64 {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `ts` is a valid, writable timespec.
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+        ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can hold one handle while the code under test holds another.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualMonotonicClock {
+    now: Arc<AtomicI64>,
+}
+
+impl VirtualMonotonicClock {
+    pub fn starting_at(nanos: i64) -> Self {
+        Self { now: Arc::new(AtomicI64::new(nanos)) }
+    }
+
+    pub fn set(&self, nanos: i64) {
+        self.now.store(nanos, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, nanos: i64) {
+        self.now.fetch_add(nanos, Ordering::SeqCst);
+    }
+}
+
+impl MonotonicClock for VirtualMonotonicClock {
+    fn now_nanos(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+impl<C: MonotonicClock + ?Sized> MonotonicClock for &C {
+    fn now_nanos(&self) -> i64 {
+        (**self).now_nanos()
+    }
+}
@@ -0,0 +1,209 @@
+// ---------------- [ File: basic-android-integration/src/mpe_receiver.rs ]
+crate::ix!();
+
+use std::collections::BTreeSet;
+
+/// What an [`MpeReceiver`] makes of incoming messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MpeEvent {
+    /// `expression` is whatever the member channel carried before the note
+    /// started, as MPE senders set it up ahead of the note-on.
+    NoteOn { zone: MpeZoneKind, channel: Channel, note: U7, velocity: U7, expression: MpeExpression },
+    NoteExpression { zone: MpeZoneKind, channel: Channel, note: U7, expression: MpeExpression },
+    NoteOff { zone: MpeZoneKind, channel: Channel, note: U7, velocity: U7 },
+    /// A message on a zone's manager channel; applies to every note in it.
+    ZoneMessage { zone: MpeZoneKind, message: MidiMessage },
+    /// An MPE Configuration Message changed the zone layout.
+    Configured(MpeConfiguration),
+    /// Anything outside the zones, or not per-note on a member channel.
+    Other(MidiMessage),
+}
+
+#[derive(Debug, Clone)]
+struct MemberChannelState {
+    bend:     U14,
+    pressure: U7,
+    timbre:   U7,
+    notes:    BTreeSet<U7>,
+}
+
+impl Default for MemberChannelState {
+    fn default() -> Self {
+        let expression = MpeExpression::default();
+        Self { bend: U14::CENTER, pressure: expression.pressure, timbre: expression.timbre, notes: BTreeSet::new() }
+    }
+}
+
+/// Groups incoming per-channel messages from an MPE controller into
+/// per-note expression streams.
+///
+/// Starts from the configuration it is given and follows MPE Configuration
+/// Messages and pitch bend sensitivity (RPN 0) changes in the stream.
+#[derive(Debug, Clone)]
+pub struct MpeReceiver {
+    configuration: MpeConfiguration,
+    channels:      [MemberChannelState; 16],
+    parameters:    ParameterChangeDecoder,
+    parser:        MidiStreamParser,
+}
+
+impl MpeReceiver {
+    pub fn new(configuration: MpeConfiguration) -> Self {
+        let immediate = ParameterDecoderOptionsBuilder::default().msb_timeout(None::<std::time::Duration>).build().unwrap();
+        Self {
+            configuration,
+            channels:   Default::default(),
+            parameters: ParameterChangeDecoder::new(immediate),
+            parser:     MidiStreamParser::new(),
+        }
+    }
+
+    pub fn configuration(&self) -> &MpeConfiguration {
+        &self.configuration
+    }
+
+    /// Current expression of a member channel.
+    pub fn expression(&self, channel: Channel) -> MpeExpression {
+        let state = &self.channels[channel.get() as usize];
+        let pitch = self.configuration.zone_of(channel).map(|zone| zone.member_semitones(state.bend)).unwrap_or(0.0);
+        MpeExpression { pitch, pressure: state.pressure, timbre: state.timbre }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut sink: impl FnMut(MpeEvent)) {
+        let mut messages = Vec::new();
+        self.parser.feed(bytes, |message| messages.push(message));
+        for message in messages {
+            self.observe(&message, &mut sink);
+        }
+    }
+
+    /// [`feed`](Self::feed) every packet pending on `port`.
+    pub fn poll(&mut self, port: &MidiOutputPort, mut sink: impl FnMut(MpeEvent)) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, .. } => self.feed(bytes, &mut sink),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage, mut sink: impl FnMut(MpeEvent)) {
+        let mut changes = Vec::new();
+        self.parameters.observe(message, 0, |change| changes.push(change));
+        for change in changes {
+            self.apply_parameter(change, &mut sink);
+        }
+
+        let Some(channel) = message.channel() else {
+            return sink(MpeEvent::Other(message.clone()));
+        };
+        let Some(zone) = self.configuration.zone_of(channel).copied() else {
+            return sink(MpeEvent::Other(message.clone()));
+        };
+        if channel == zone.manager_channel() {
+            return sink(MpeEvent::ZoneMessage { zone: zone.kind(), message: message.clone() });
+        }
+
+        let kind  = zone.kind();
+        let state = &mut self.channels[channel.get() as usize];
+        match *message {
+            MidiMessage::NoteOn { note, velocity, .. } if velocity.get() > 0 => {
+                state.notes.insert(note);
+                let expression = self.expression(channel);
+                sink(MpeEvent::NoteOn { zone: kind, channel, note, velocity, expression });
+            }
+            MidiMessage::NoteOn { note, velocity, .. } | MidiMessage::NoteOff { note, velocity, .. } => {
+                state.notes.remove(&note);
+                sink(MpeEvent::NoteOff { zone: kind, channel, note, velocity });
+            }
+            MidiMessage::PitchBend { value, .. } => {
+                state.bend = value;
+                self.emit_expression(kind, channel, &mut sink);
+            }
+            MidiMessage::ChannelPressure { pressure, .. } => {
+                state.pressure = pressure;
+                self.emit_expression(kind, channel, &mut sink);
+            }
+            MidiMessage::ControlChange { controller, value, .. } if controller.get() == CC_TIMBRE => {
+                state.timbre = value;
+                self.emit_expression(kind, channel, &mut sink);
+            }
+            _ => sink(MpeEvent::Other(message.clone())),
+        }
+    }
+
+    fn emit_expression(&self, zone: MpeZoneKind, channel: Channel, sink: &mut impl FnMut(MpeEvent)) {
+        let expression = self.expression(channel);
+        for &note in &self.channels[channel.get() as usize].notes {
+            sink(MpeEvent::NoteExpression { zone, channel, note, expression });
+        }
+    }
+
+    fn apply_parameter(&mut self, change: ParameterChange, sink: &mut impl FnMut(MpeEvent)) {
+        if change.kind() != ParameterKind::Rpn {
+            return;
+        }
+        let semitones = change.value().msb().get();
+        match change.number().get() {
+            RPN_MPE_CONFIGURATION => {
+                if let Some(kind) = MpeZoneKind::from_manager_channel(change.channel()) {
+                    self.configuration.apply_configuration_message(kind, semitones);
+                    sink(MpeEvent::Configured(self.configuration));
+                }
+            }
+            0 => {
+                let Some(zone) = self.configuration.zone_of(change.channel()).copied() else {
+                    return;
+                };
+                let zone_mut = self.configuration.zone_mut(zone.kind()).unwrap();
+                if change.channel() == zone.manager_channel() {
+                    zone_mut.set_manager_pitch_bend_range(semitones);
+                } else {
+                    // Sent to any member, it applies to all of them.
+                    zone_mut.set_member_pitch_bend_range(semitones);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mpe_receiver {
+    use super::*;
+
+    #[test]
+    fn test_follows_configuration_and_groups_expression() {
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let output = virtual_device.output_port(0);
+        // MCM: lower zone with 3 members, then member bend range 24
+        output.push(vec![0xb0, 101, 0, 100, 6, 6, 3, 0xb1, 101, 0, 100, 0, 6, 24], 0);
+        // member channel 3: bend and timbre before the note, then pressure
+        output.push(vec![0xe2, 0x00, 0x50, 0xb2, 74, 90, 0x92, 60, 100, 0xd2, 33, 0x82, 60, 0], 1);
+        output.push(vec![0xb0, 64, 127, 0x9a, 36, 100], 2);
+
+        let device = virtual_device.open();
+        let port   = device.open_output_port(0).unwrap();
+        let mut receiver = MpeReceiver::new(MpeConfiguration::default());
+        let mut events   = Vec::new();
+        receiver.poll(&port, |event| events.push(event)).unwrap();
+
+        let lower = receiver.configuration().lower.unwrap();
+        assert_eq!((lower.member_count(), lower.member_pitch_bend_range()), (3, 24));
+        assert!(events.contains(&MpeEvent::Configured(MpeConfiguration { lower: Some(MpeZone::new(MpeZoneKind::Lower, 3)), upper: None })));
+
+        let ch2  = Channel::new(2).unwrap();
+        let note = U7::new(60).unwrap();
+        let per_note: Vec<&MpeEvent> = events.iter().filter(|e| !matches!(e, MpeEvent::ZoneMessage { .. } | MpeEvent::Configured(_) | MpeEvent::Other(_))).collect();
+        assert_eq!(per_note.len(), 3);
+        // 0x2800 is a quarter of the way up: 6 of 24 semitones
+        let expected = MpeExpression { pitch: 6.0, pressure: U7::MIN, timbre: U7::new(90).unwrap() };
+        assert_eq!(per_note[0], &MpeEvent::NoteOn { zone: MpeZoneKind::Lower, channel: ch2, note, velocity: U7::new(100).unwrap(), expression: expected });
+        assert_eq!(per_note[1], &MpeEvent::NoteExpression { zone: MpeZoneKind::Lower, channel: ch2, note, expression: MpeExpression { pressure: U7::new(33).unwrap(), ..expected } });
+        assert_eq!(per_note[2], &MpeEvent::NoteOff { zone: MpeZoneKind::Lower, channel: ch2, note, velocity: U7::MIN });
+
+        assert!(events.contains(&MpeEvent::ZoneMessage {
+            zone:    MpeZoneKind::Lower,
+            message: MidiMessage::control_change(Channel::MIN, U7::new(64).unwrap(), U7::MAX),
+        }));
+        assert!(matches!(events.last(), Some(MpeEvent::Other(MidiMessage::NoteOn { .. }))));
+    }
+}
@@ -0,0 +1,220 @@
+// ---------------- [ File: basic-android-integration/src/mpe_sender.rs ]
+crate::ix!();
+
+/// How an [`MpeSender`] picks a member channel for a new note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MpeChannelRotation {
+    /// Cycle through the members, skipping busy ones.
+    #[default]
+    RoundRobin,
+    /// The free member whose last note ended longest ago, so release tails
+    /// get the most time before the channel's expression is reset.
+    LeastRecentlyUsed,
+    /// Always the free member nearest the manager channel.
+    LowestAvailable,
+}
+
+/// A note started by [`MpeSender::note_on`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MpeNoteHandle {
+    pub channel: Channel,
+    pub note:    U7,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MemberChannelUse {
+    active:     usize,
+    /// Ordering stamp of the last note-on or note-off on this channel.
+    last_used:  u64,
+    /// Stamp of the oldest note still sounding, for sharing when full.
+    started_at: u64,
+}
+
+/// Sends per-note expression to an MPE synth through a `MidiInputPort`:
+/// allocates a member channel per note, sets its bend, pressure and timbre
+/// before the note-on, and configures the zone with the MPE Configuration
+/// Message.
+///
+/// When every member is busy, the new note shares the channel whose
+/// oldest note started first; expression then affects both notes, which
+/// is what the MPE spec recommends over cutting a note off.
+#[derive(Debug, Clone)]
+pub struct MpeSender {
+    zone:     MpeZone,
+    rotation: MpeChannelRotation,
+    members:  [MemberChannelUse; 16],
+    next:     usize,
+    clock:    u64,
+    encoder:  ParameterChangeEncoder,
+}
+
+impl MpeSender {
+    pub fn new(zone: MpeZone, rotation: MpeChannelRotation) -> Self {
+        Self {
+            zone,
+            rotation,
+            members: Default::default(),
+            next:    0,
+            clock:   0,
+            encoder: ParameterChangeEncoder::default(),
+        }
+    }
+
+    pub fn zone(&self) -> &MpeZone {
+        &self.zone
+    }
+
+    /// Send the MPE Configuration Message and pitch bend ranges.
+    pub fn configure(&mut self, port: &MidiInputPort) -> Result<usize, isize> {
+        info!("configuring MPE {:?} zone with {} members", self.zone.kind(), self.zone.member_count());
+        self.encoder.send(port, &self.zone.configuration_changes())
+    }
+
+    /// Send a zone-wide message on the manager channel (its channel field
+    /// is replaced).
+    pub fn send_zone_message(&mut self, port: &MidiInputPort, message: &MidiMessage) -> Result<usize, isize> {
+        port.send_message(&with_channel(message, self.zone.manager_channel()))
+    }
+
+    pub fn note_on(&mut self, port: &MidiInputPort, note: U7, velocity: U7, expression: MpeExpression) -> Result<MpeNoteHandle, isize> {
+        let channel = self.allocate();
+        let mut messages = self.expression_messages(channel, &expression);
+        messages.push(MidiMessage::note_on(channel, note, velocity));
+        port.send_messages(&messages)?;
+
+        self.clock += 1;
+        let member = &mut self.members[channel.get() as usize];
+        if member.active == 0 {
+            member.started_at = self.clock;
+        }
+        member.active   += 1;
+        member.last_used = self.clock;
+        trace!("MPE note {} on channel {}", note.get(), channel.get());
+        Ok(MpeNoteHandle { channel, note })
+    }
+
+    pub fn update(&mut self, port: &MidiInputPort, handle: MpeNoteHandle, expression: MpeExpression) -> Result<usize, isize> {
+        port.send_messages(&self.expression_messages(handle.channel, &expression))
+    }
+
+    pub fn note_off(&mut self, port: &MidiInputPort, handle: MpeNoteHandle, velocity: U7) -> Result<usize, isize> {
+        let sent = port.send_message(&MidiMessage::note_off(handle.channel, handle.note, velocity))?;
+        self.clock += 1;
+        let member = &mut self.members[handle.channel.get() as usize];
+        member.active    = member.active.saturating_sub(1);
+        member.last_used = self.clock;
+        Ok(sent)
+    }
+
+    fn expression_messages(&self, channel: Channel, expression: &MpeExpression) -> Vec<MidiMessage> {
+        vec![
+            MidiMessage::pitch_bend(channel, self.zone.member_bend(expression.pitch)),
+            MidiMessage::control_change(channel, U7::saturating(CC_TIMBRE), expression.timbre),
+            MidiMessage::channel_pressure(channel, expression.pressure),
+        ]
+    }
+
+    fn allocate(&mut self) -> Channel {
+        let members: Vec<Channel> = self.zone.member_channels().collect();
+        let free = |channel: &Channel| self.members[channel.get() as usize].active == 0;
+
+        let choice = match self.rotation {
+            MpeChannelRotation::RoundRobin => {
+                let start = self.next % members.len();
+                let found = (0..members.len()).map(|i| (start + i) % members.len()).find(|&i| free(&members[i]));
+                if let Some(index) = found {
+                    self.next = index + 1;
+                }
+                found.map(|index| members[index])
+            }
+            MpeChannelRotation::LeastRecentlyUsed => {
+                members.iter().copied().filter(free).min_by_key(|c| self.members[c.get() as usize].last_used)
+            }
+            MpeChannelRotation::LowestAvailable => members.iter().copied().find(free),
+        };
+
+        choice.unwrap_or_else(|| {
+            let shared = members.iter().copied().min_by_key(|c| self.members[c.get() as usize].started_at).unwrap();
+            debug!("all MPE members busy, sharing channel {}", shared.get());
+            shared
+        })
+    }
+}
+
+fn with_channel(message: &MidiMessage, channel: Channel) -> MidiMessage {
+    let mut message = message.clone();
+    match &mut message {
+        MidiMessage::NoteOff { channel: c, .. }
+        | MidiMessage::NoteOn { channel: c, .. }
+        | MidiMessage::PolyPressure { channel: c, .. }
+        | MidiMessage::ControlChange { channel: c, .. }
+        | MidiMessage::ProgramChange { channel: c, .. }
+        | MidiMessage::ChannelPressure { channel: c, .. }
+        | MidiMessage::PitchBend { channel: c, .. } => *c = channel,
+        _ => {}
+    }
+    message
+}
+
+#[cfg(test)]
+mod test_mpe_sender {
+    use super::*;
+
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    #[test]
+    fn test_configure_and_round_trip_through_receiver() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+
+        let mut sender = MpeSender::new(MpeZone::new(MpeZoneKind::Upper, 2), MpeChannelRotation::RoundRobin);
+        sender.configure(&port).unwrap();
+
+        let expression = MpeExpression { pitch: -12.0, pressure: u7(10), timbre: u7(70) };
+        let a = sender.note_on(&port, u7(60), u7(100), expression).unwrap();
+        let b = sender.note_on(&port, u7(64), u7(100), MpeExpression::default()).unwrap();
+        sender.update(&port, a, MpeExpression { pressure: u7(50), ..expression }).unwrap();
+        sender.note_off(&port, b, U7::MIN).unwrap();
+        let c = sender.note_on(&port, u7(67), u7(100), MpeExpression::default()).unwrap();
+        assert_eq!((a.channel.get(), b.channel.get(), c.channel.get()), (14, 13, 13));
+        // all busy: shares the channel of the oldest note
+        let d = sender.note_on(&port, u7(70), u7(100), MpeExpression::default()).unwrap();
+        assert_eq!(d.channel, a.channel);
+
+        let mut receiver = MpeReceiver::new(MpeConfiguration::default());
+        let mut events   = Vec::new();
+        receiver.feed(&virtual_device.input_port(0).sent_bytes(), |event| events.push(event));
+
+        assert_eq!(receiver.configuration().upper, Some(MpeZone::new(MpeZoneKind::Upper, 2)));
+        let first_on = events.iter().find(|e| matches!(e, MpeEvent::NoteOn { .. })).unwrap();
+        assert_eq!(first_on, &MpeEvent::NoteOn {
+            zone: MpeZoneKind::Upper,
+            channel: a.channel,
+            note: u7(60),
+            velocity: u7(100),
+            expression,
+        });
+    }
+
+    #[test]
+    fn test_rotation_policies() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let zone   = MpeZone::new(MpeZoneKind::Lower, 3);
+
+        let mut lowest = MpeSender::new(zone, MpeChannelRotation::LowestAvailable);
+        let a = lowest.note_on(&port, u7(60), u7(1), MpeExpression::default()).unwrap();
+        lowest.note_off(&port, a, U7::MIN).unwrap();
+        let b = lowest.note_on(&port, u7(61), u7(1), MpeExpression::default()).unwrap();
+        assert_eq!((a.channel.get(), b.channel.get()), (1, 1));
+
+        let mut lru = MpeSender::new(zone, MpeChannelRotation::LeastRecentlyUsed);
+        let handles: Vec<_> = (0..3).map(|n| lru.note_on(&port, u7(n), u7(1), MpeExpression::default()).unwrap()).collect();
+        lru.note_off(&port, handles[2], U7::MIN).unwrap();
+        lru.note_off(&port, handles[0], U7::MIN).unwrap();
+        let next = lru.note_on(&port, u7(9), u7(1), MpeExpression::default()).unwrap();
+        assert_eq!(next.channel, handles[2].channel);
+    }
+}
@@ -0,0 +1,221 @@
+// ---------------- [ File: basic-android-integration/src/mpe_zone.rs ]
+crate::ix!();
+
+/// RPN 6: MPE Configuration Message, member channel count in the data MSB.
+pub const RPN_MPE_CONFIGURATION: u16 = 6;
+/// MPE's third dimension of control ("timbre", "slide").
+pub const CC_TIMBRE:             u8 = 74;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MpeZoneKind {
+    /// Manager channel 1, members counting up from channel 2.
+    Lower,
+    /// Manager channel 16, members counting down from channel 15.
+    Upper,
+}
+
+impl MpeZoneKind {
+    pub fn manager_channel(&self) -> Channel {
+        match self {
+            MpeZoneKind::Lower => Channel::MIN,
+            MpeZoneKind::Upper => Channel::MAX,
+        }
+    }
+
+    /// The zone whose manager channel this is.
+    pub fn from_manager_channel(channel: Channel) -> Option<Self> {
+        match channel.get() {
+            0  => Some(MpeZoneKind::Lower),
+            15 => Some(MpeZoneKind::Upper),
+            _  => None,
+        }
+    }
+}
+
+/// One MPE zone: a manager channel plus 1-15 member channels.
+///
+/// The member count is only set through [`MpeZone::new`], which keeps it
+/// in range; everything that walks the members relies on there being one.
+#[derive(CopyGetters, Setters, Debug, Clone, Copy, PartialEq, Eq)]
+#[getset(get_copy = "pub")]
+pub struct MpeZone {
+    kind:                     MpeZoneKind,
+    member_count:             u8,
+    /// Semitones for full-scale pitch bend on member channels.
+    #[getset(set = "pub")]
+    member_pitch_bend_range:  u8,
+    /// Semitones for full-scale pitch bend on the manager channel.
+    #[getset(set = "pub")]
+    manager_pitch_bend_range: u8,
+}
+
+impl MpeZone {
+    pub const DEFAULT_MEMBER_PITCH_BEND_RANGE:  u8 = 48;
+    pub const DEFAULT_MANAGER_PITCH_BEND_RANGE: u8 = 2;
+
+    /// A zone with the default pitch bend ranges the MPE spec mandates
+    /// after a configuration message. `member_count` is clamped to 1-15.
+    pub fn new(kind: MpeZoneKind, member_count: u8) -> Self {
+        Self {
+            kind,
+            member_count:             member_count.clamp(1, 15),
+            member_pitch_bend_range:  Self::DEFAULT_MEMBER_PITCH_BEND_RANGE,
+            manager_pitch_bend_range: Self::DEFAULT_MANAGER_PITCH_BEND_RANGE,
+        }
+    }
+
+    pub fn manager_channel(&self) -> Channel {
+        self.kind.manager_channel()
+    }
+
+    /// Member channels, nearest to the manager first.
+    pub fn member_channels(&self) -> impl Iterator<Item = Channel> + use<> {
+        let (kind, count) = (self.kind, self.member_count);
+        (1..=count).map(move |i| match kind {
+            MpeZoneKind::Lower => Channel::new(i).unwrap(),
+            MpeZoneKind::Upper => Channel::new(15 - i).unwrap(),
+        })
+    }
+
+    pub fn is_member(&self, channel: Channel) -> bool {
+        let c = channel.get();
+        match self.kind {
+            MpeZoneKind::Lower => (1..=self.member_count).contains(&c),
+            MpeZoneKind::Upper => (15 - self.member_count..15).contains(&c),
+        }
+    }
+
+    /// The MPE Configuration Message followed by pitch bend sensitivity
+    /// (RPN 0) on the manager and on every member channel.
+    pub fn configuration_changes(&self) -> Vec<ParameterChange> {
+        let manager = self.manager_channel();
+        let mut changes = vec![
+            ParameterChange::rpn(manager, U14::new(RPN_MPE_CONFIGURATION).unwrap(), U14::from_msb_lsb(U7::saturating(self.member_count), U7::MIN)),
+            ParameterChange::rpn(manager, U14::MIN, U14::from_msb_lsb(U7::saturating(self.manager_pitch_bend_range), U7::MIN)),
+        ];
+        changes.extend(self.member_channels().map(|channel| {
+            ParameterChange::rpn(channel, U14::MIN, U14::from_msb_lsb(U7::saturating(self.member_pitch_bend_range), U7::MIN))
+        }));
+        changes
+    }
+
+    /// Pitch bend value for an offset in semitones on a member channel,
+    /// clamped to the bend range.
+    pub fn member_bend(&self, semitones: f32) -> U14 {
+        bend_for_semitones(semitones, self.member_pitch_bend_range)
+    }
+
+    /// Offset in semitones a member channel bend stands for.
+    pub fn member_semitones(&self, bend: U14) -> f32 {
+        bend.signed() as f32 / 8192.0 * self.member_pitch_bend_range as f32
+    }
+}
+
+pub(crate) fn bend_for_semitones(semitones: f32, range: u8) -> U14 {
+    if range == 0 {
+        return U14::CENTER;
+    }
+    let signed = (semitones / range as f32 * 8192.0).round().clamp(-8192.0, 8191.0) as i16;
+    U14::from_signed(signed).unwrap_or(U14::CENTER)
+}
+
+/// Both zones of an MPE instrument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MpeConfiguration {
+    pub lower: Option<MpeZone>,
+    pub upper: Option<MpeZone>,
+}
+
+impl MpeConfiguration {
+    pub fn zone(&self, kind: MpeZoneKind) -> Option<&MpeZone> {
+        match kind {
+            MpeZoneKind::Lower => self.lower.as_ref(),
+            MpeZoneKind::Upper => self.upper.as_ref(),
+        }
+    }
+
+    pub fn zone_mut(&mut self, kind: MpeZoneKind) -> Option<&mut MpeZone> {
+        match kind {
+            MpeZoneKind::Lower => self.lower.as_mut(),
+            MpeZoneKind::Upper => self.upper.as_mut(),
+        }
+    }
+
+    /// The zone `channel` is the manager or a member of.
+    pub fn zone_of(&self, channel: Channel) -> Option<&MpeZone> {
+        [self.lower.as_ref(), self.upper.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|zone| zone.manager_channel() == channel || zone.is_member(channel))
+    }
+
+    /// Apply an MPE Configuration Message. Zero members turns the zone off;
+    /// if the zones would overlap, the other zone shrinks to fit, as the
+    /// spec requires.
+    pub fn apply_configuration_message(&mut self, kind: MpeZoneKind, member_count: u8) {
+        let member_count = member_count.min(15);
+        let (this, other) = match kind {
+            MpeZoneKind::Lower => (&mut self.lower, &mut self.upper),
+            MpeZoneKind::Upper => (&mut self.upper, &mut self.lower),
+        };
+        *this = (member_count > 0).then(|| MpeZone::new(kind, member_count));
+        if let Some(zone) = other {
+            let room = 14u8.saturating_sub(member_count);
+            if room == 0 {
+                *other = None;
+            } else if zone.member_count > room {
+                zone.member_count = room;
+            }
+        }
+        info!("MPE configuration now {:?}", self);
+    }
+}
+
+/// Per-note expression: pitch offset from the note in semitones (member
+/// channel bend only), pressure and timbre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MpeExpression {
+    pub pitch:    f32,
+    pub pressure: U7,
+    pub timbre:   U7,
+}
+
+impl Default for MpeExpression {
+    fn default() -> Self {
+        Self { pitch: 0.0, pressure: U7::MIN, timbre: U7::saturating(64) }
+    }
+}
+
+#[cfg(test)]
+mod test_mpe_zone {
+    use super::*;
+
+    #[test]
+    fn test_zones_shrink_instead_of_overlapping() {
+        let mut configuration = MpeConfiguration::default();
+        configuration.apply_configuration_message(MpeZoneKind::Upper, 7);
+        configuration.apply_configuration_message(MpeZoneKind::Lower, 10);
+        assert_eq!(configuration.lower.unwrap().member_count(), 10);
+        assert_eq!(configuration.upper.unwrap().member_count(), 4);
+        assert_eq!(MpeZone::new(MpeZoneKind::Upper, 0).member_count(), 1);
+
+        let upper = configuration.upper.unwrap();
+        let members: Vec<u8> = upper.member_channels().map(Channel::get).collect();
+        assert_eq!(members, vec![14, 13, 12, 11]);
+        assert!(!upper.is_member(Channel::new(10).unwrap()));
+
+        configuration.apply_configuration_message(MpeZoneKind::Lower, 15);
+        assert_eq!(configuration.upper, None);
+        configuration.apply_configuration_message(MpeZoneKind::Lower, 0);
+        assert_eq!(configuration, MpeConfiguration::default());
+    }
+
+    #[test]
+    fn test_member_bend_scaling() {
+        let zone = MpeZone::new(MpeZoneKind::Lower, 15);
+        assert_eq!(zone.member_bend(0.0), U14::CENTER);
+        assert_eq!(zone.member_bend(48.0), U14::MAX);
+        assert_eq!(zone.member_bend(-100.0), U14::MIN);
+        assert_eq!(zone.member_semitones(zone.member_bend(12.0)), 12.0);
+    }
+}
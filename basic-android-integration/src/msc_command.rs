@@ -0,0 +1,292 @@
+// ---------------- [ File: basic-android-integration/src/msc_command.rs ]
+crate::ix!();
+
+/// Sub-id #1 of MIDI Show Control.
+pub const MSC_SUB_ID: u8 = 0x02;
+
+/// Which kind of equipment an MSC message is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MscCommandFormat(pub u8);
+
+impl MscCommandFormat {
+    pub const LIGHTING:        Self = Self(0x01);
+    pub const MOVING_LIGHTS:   Self = Self(0x02);
+    pub const SOUND:           Self = Self(0x10);
+    pub const MACHINERY:       Self = Self(0x20);
+    pub const VIDEO:           Self = Self(0x30);
+    pub const PROJECTION:      Self = Self(0x40);
+    pub const PROCESS_CONTROL: Self = Self(0x50);
+    pub const PYRO:            Self = Self(0x60);
+    pub const ALL_TYPES:       Self = Self(0x7f);
+}
+
+/// A cue number with optional list and path, e.g. `"23.5"` in list `"2"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MscCue {
+    number: String,
+    list:   Option<String>,
+    path:   Option<String>,
+}
+
+fn check_cue_field(field: &[u8]) -> Result<String, MachineControlError> {
+    match field.iter().find(|b| !b.is_ascii_digit() && **b != b'.') {
+        Some(byte) => Err(MachineControlError::BadCueCharacter { byte: *byte }),
+        None => Ok(String::from_utf8_lossy(field).into_owned()),
+    }
+}
+
+/// Like [`check_cue_field`], but an empty field is an error: fields are
+/// 0-separated, so an empty one would not read back.
+fn non_empty_cue_field(field: String, what: &'static str) -> Result<String, MachineControlError> {
+    if field.is_empty() {
+        return Err(MachineControlError::EmptyCueField { what });
+    }
+    check_cue_field(field.as_bytes())
+}
+
+impl MscCue {
+    pub fn new(number: impl Into<String>) -> Result<Self, MachineControlError> {
+        let number = non_empty_cue_field(number.into(), "number")?;
+        Ok(Self { number, list: None, path: None })
+    }
+
+    pub fn with_list(mut self, list: impl Into<String>) -> Result<Self, MachineControlError> {
+        self.list = Some(non_empty_cue_field(list.into(), "list")?);
+        Ok(self)
+    }
+
+    /// A path only makes sense with a list, so set the list first.
+    pub fn with_path(mut self, path: impl Into<String>) -> Result<Self, MachineControlError> {
+        if self.list.is_none() {
+            return Err(MachineControlError::CuePathWithoutList);
+        }
+        self.path = Some(non_empty_cue_field(path.into(), "path")?);
+        Ok(self)
+    }
+
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    pub fn list(&self) -> Option<&str> {
+        self.list.as_deref()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// `number 00 list 00 path`, stopping after the last field present.
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.number.as_bytes());
+        for field in [&self.list, &self.path] {
+            match field {
+                Some(field) => {
+                    out.push(0);
+                    out.extend_from_slice(field.as_bytes());
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// `None` for an empty field list (a GO without a cue number). Trailing
+    /// empty fields are ignored; an empty one before a field that is set
+    /// is an error, as [`new`](Self::new) and the `with_` setters would
+    /// not build that cue.
+    fn parse(bytes: &[u8]) -> Result<Option<Self>, MachineControlError> {
+        let mut fields = bytes.split(|b| *b == 0).map(check_cue_field);
+        let mut field  = || fields.next().transpose().map(|field| field.filter(|field| !field.is_empty()));
+        let (number, list, path) = (field()?, field()?, field()?);
+        match (number, list, path) {
+            (None, None, None) => Ok(None),
+            (None, _, _) => Err(MachineControlError::EmptyCueField { what: "number" }),
+            (Some(_), None, Some(_)) => Err(MachineControlError::CuePathWithoutList),
+            (Some(number), list, path) => Ok(Some(Self { number, list, path })),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MscCommand {
+    /// Without a cue: the next cue in sequence.
+    Go(Option<MscCue>),
+    Stop(Option<MscCue>),
+    Resume(Option<MscCue>),
+    TimedGo { time: SmptePosition, cue: Option<MscCue> },
+    Load(MscCue),
+    /// Set a generic control (fader, channel) to a value.
+    Set { control: U14, value: U14, time: Option<SmptePosition> },
+    /// Trigger a macro.
+    Fire(U7),
+    AllOff,
+    Restore,
+    Reset,
+    GoOff(Option<MscCue>),
+    Other { command: u8, data: Vec<u8> },
+}
+
+impl MscCommand {
+    pub fn code(&self) -> u8 {
+        match self {
+            MscCommand::Go(_) => 0x01,
+            MscCommand::Stop(_) => 0x02,
+            MscCommand::Resume(_) => 0x03,
+            MscCommand::TimedGo { .. } => 0x04,
+            MscCommand::Load(_) => 0x05,
+            MscCommand::Set { .. } => 0x06,
+            MscCommand::Fire(_) => 0x07,
+            MscCommand::AllOff => 0x08,
+            MscCommand::Restore => 0x09,
+            MscCommand::Reset => 0x0a,
+            MscCommand::GoOff(_) => 0x0b,
+            MscCommand::Other { command, .. } => *command,
+        }
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.push(self.code());
+        match self {
+            MscCommand::Go(cue) | MscCommand::Stop(cue) | MscCommand::Resume(cue) | MscCommand::GoOff(cue) => {
+                if let Some(cue) = cue {
+                    cue.write_to(out);
+                }
+            }
+            MscCommand::TimedGo { time, cue } => {
+                out.extend_from_slice(&time.to_bytes());
+                if let Some(cue) = cue {
+                    cue.write_to(out);
+                }
+            }
+            MscCommand::Load(cue) => cue.write_to(out),
+            MscCommand::Set { control, value, time } => {
+                out.extend_from_slice(&[control.lsb().get(), control.msb().get(), value.lsb().get(), value.msb().get()]);
+                if let Some(time) = time {
+                    out.extend_from_slice(&time.to_bytes());
+                }
+            }
+            MscCommand::Fire(macro_number) => out.push(macro_number.get()),
+            MscCommand::Other { data, .. } => out.extend_from_slice(data),
+            MscCommand::AllOff | MscCommand::Restore | MscCommand::Reset => {}
+        }
+    }
+
+    fn decode(command: u8, data: &[u8]) -> Result<Self, MachineControlError> {
+        let time = |bytes: &[u8]| -> Result<SmptePosition, MachineControlError> {
+            match *bytes {
+                [hr, mn, sc, fr, ff] => Ok(SmptePosition::from_bytes([hr, mn, sc, fr, ff])?),
+                _ => Err(MachineControlError::Truncated { what: "time" }),
+            }
+        };
+        Ok(match command {
+            0x01 => MscCommand::Go(MscCue::parse(data)?),
+            0x02 => MscCommand::Stop(MscCue::parse(data)?),
+            0x03 => MscCommand::Resume(MscCue::parse(data)?),
+            0x04 if data.len() >= 5 => MscCommand::TimedGo { time: time(&data[..5])?, cue: MscCue::parse(&data[5..])? },
+            0x04 => return Err(MachineControlError::Truncated { what: "time" }),
+            0x05 => MscCommand::Load(MscCue::parse(data)?.ok_or(MachineControlError::Truncated { what: "cue number" })?),
+            0x06 => match data {
+                [c_lsb, c_msb, v_lsb, v_msb, rest @ ..] => MscCommand::Set {
+                    control: U14::from_msb_lsb(U7::new(*c_msb)?, U7::new(*c_lsb)?),
+                    value:   U14::from_msb_lsb(U7::new(*v_msb)?, U7::new(*v_lsb)?),
+                    time:    if rest.is_empty() { None } else { Some(time(rest)?) },
+                },
+                _ => return Err(MachineControlError::Truncated { what: "set control" }),
+            },
+            0x07 => MscCommand::Fire(U7::new(*data.first().ok_or(MachineControlError::Truncated { what: "macro number" })?)?),
+            0x08 => MscCommand::AllOff,
+            0x09 => MscCommand::Restore,
+            0x0a => MscCommand::Reset,
+            0x0b => MscCommand::GoOff(MscCue::parse(data)?),
+            _ => MscCommand::Other { command, data: data.to_vec() },
+        })
+    }
+}
+
+/// One MSC message. `device_id` 0x00-0x6F addresses one device, 0x70-0x7E
+/// a group and 0x7F everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MscMessage {
+    pub device_id: U7,
+    pub format:    MscCommandFormat,
+    pub command:   MscCommand,
+}
+
+impl MscMessage {
+    pub fn new(device_id: U7, format: MscCommandFormat, command: MscCommand) -> Self {
+        Self { device_id, format, command }
+    }
+
+    /// SysEx data, without `F0`/`F7`. Fails if the format, command code or
+    /// a data byte isn't 7-bit.
+    pub fn to_sysex(&self) -> Result<Vec<u8>, MachineControlError> {
+        let mut out = vec![UNIVERSAL_REAL_TIME, self.device_id.get(), MSC_SUB_ID, self.format.0];
+        self.command.write_to(&mut out);
+        Ok(SysExData::new(out)?.into_vec())
+    }
+
+    pub fn to_message(&self) -> Result<MidiMessage, MachineControlError> {
+        Ok(MidiMessage::sysex(self.to_sysex()?)?)
+    }
+
+    pub fn from_sysex(data: &[u8]) -> Result<Self, MachineControlError> {
+        let [UNIVERSAL_REAL_TIME, device_id, MSC_SUB_ID, rest @ ..] = data else {
+            return Err(MachineControlError::NotMachineControl);
+        };
+        let [format, command, data @ ..] = rest else {
+            return Err(MachineControlError::Truncated { what: "command format" });
+        };
+        Ok(Self {
+            device_id: U7::new(*device_id)?,
+            format:    MscCommandFormat(*format),
+            command:   MscCommand::decode(*command, data)?,
+        })
+    }
+
+    pub fn send(&self, port: &MidiInputPort) -> Result<usize, MachineControlError> {
+        Ok(port.send_all(&self.to_message()?.to_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod test_msc_command {
+    use super::*;
+
+    #[test]
+    fn test_cue_encoding_and_round_trips() {
+        let cue = MscCue::new("23.5").unwrap().with_list("2").unwrap();
+        let go  = MscMessage::new(U7::new(1).unwrap(), MscCommandFormat::LIGHTING, MscCommand::Go(Some(cue.clone())));
+        let sysex = go.to_sysex().unwrap();
+        assert_eq!(sysex, b"\x7f\x01\x02\x01\x0123.5\x002".to_vec());
+        assert_eq!(MscMessage::from_sysex(&sysex), Ok(go));
+
+        let time = SmptePosition::new(SmpteTime::new(0, 0, 10, 0, SmpteRate::Fps30).unwrap(), 0);
+        for command in [
+            MscCommand::Go(None),
+            MscCommand::TimedGo { time, cue: Some(cue.with_path("7").unwrap()) },
+            MscCommand::Set { control: U14::new(300).unwrap(), value: U14::MAX, time: Some(time) },
+            MscCommand::Fire(U7::new(9).unwrap()),
+            MscCommand::AllOff,
+        ] {
+            let message = MscMessage::new(ALL_CALL, MscCommandFormat::SOUND, command);
+            assert_eq!(MscMessage::from_sysex(&message.to_sysex().unwrap()), Ok(message));
+        }
+
+        assert_eq!(MscCue::new("1a"), Err(MachineControlError::BadCueCharacter { byte: b'a' }));
+        assert_eq!(MscCue::new(""), Err(MachineControlError::EmptyCueField { what: "number" }));
+        assert_eq!(MscCue::new("1").unwrap().with_list(""), Err(MachineControlError::EmptyCueField { what: "list" }));
+        assert_eq!(MscCue::new("1").unwrap().with_path("7"), Err(MachineControlError::CuePathWithoutList));
+
+        // A cue that decodes re-encodes to the same fields; trailing empty
+        // fields are dropped.
+        let decode = |cue: &[u8]| MscMessage::from_sysex(&[b"\x7f\x01\x02\x01\x01", cue].concat());
+        let full   = decode(b"1\x002\x007").unwrap();
+        assert_eq!(full.to_sysex().unwrap(), b"\x7f\x01\x02\x01\x011\x002\x007".to_vec());
+        assert_eq!(decode(b"1\x00\x00").unwrap().to_sysex().unwrap(), b"\x7f\x01\x02\x01\x011".to_vec());
+        assert_eq!(decode(b"1\x00\x007"), Err(MachineControlError::CuePathWithoutList));
+        assert_eq!(decode(b"\x002"), Err(MachineControlError::EmptyCueField { what: "number" }));
+
+        let other = MscMessage::new(ALL_CALL, MscCommandFormat::SOUND, MscCommand::Other { command: 0x1b, data: vec![0x10, 0xf7] });
+        assert_eq!(other.to_message(), Err(MachineControlError::Value(MidiValueError::SysExDataByte { index: 6, byte: 0xf7 })));
+    }
+}
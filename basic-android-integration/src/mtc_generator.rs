@@ -0,0 +1,148 @@
+// ---------------- [ File: basic-android-integration/src/mtc_generator.rs ]
+crate::ix!();
+
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct MtcGeneratorOptions {
+    /// How far ahead of the monotonic clock quarter frames are handed to
+    /// the port. `pump` has to run at least this often.
+    lookahead: Duration,
+    /// Send a full frame message at the start position before the first
+    /// quarter frame, so receivers locate without waiting two frames.
+    full_frame_on_start: bool,
+    /// Device ID in the full frame message; 0x7f addresses every device.
+    device_id: U7,
+}
+
+impl Default for MtcGeneratorOptions {
+    fn default() -> Self {
+        Self {
+            lookahead:           Duration::from_millis(20),
+            full_frame_on_start: true,
+            device_id:           U7::MAX,
+        }
+    }
+}
+
+/// Where a running generator started.
+#[derive(Debug, Clone, Copy)]
+struct MtcRun {
+    start:        SmpteTime,
+    /// Wall time of quarter frame 0.
+    anchor:       i64,
+    next_quarter: u64,
+}
+
+/// Sends MTC quarter frames to a `MidiInputPort`, 4 per frame at the start
+/// time's rate.
+///
+/// Like [`SmfPlayer`] it owns no thread: each [`pump`](Self::pump) stamps the
+/// quarter frames due within the lookahead with their exact time and hands
+/// them to `send_with_timestamp`.
+pub struct MtcGenerator<C: MonotonicClock = SystemMonotonicClock> {
+    options:    MtcGeneratorOptions,
+    clock:      C,
+    run:        Option<MtcRun>,
+    full_frame: Option<Vec<u8>>,
+}
+
+impl<C: MonotonicClock> MtcGenerator<C> {
+    pub fn new(options: MtcGeneratorOptions, clock: C) -> Self {
+        Self { options, clock, run: None, full_frame: None }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    /// Start sending from `time`, one lookahead from now.
+    pub fn start(&mut self, time: SmpteTime) {
+        let anchor = self.clock.now_nanos() + self.options.lookahead.as_nanos() as i64;
+        self.run        = Some(MtcRun { start: time, anchor, next_quarter: 0 });
+        self.full_frame = self.options.full_frame_on_start.then(|| {
+            MidiMessage::SysEx(SysExData::from_data_bytes(time.full_frame_sysex(self.options.device_id))).to_bytes()
+        });
+    }
+
+    pub fn stop(&mut self) {
+        self.run        = None;
+        self.full_frame = None;
+    }
+
+    /// The frame playing now, while running.
+    pub fn position(&self) -> Option<SmpteTime> {
+        let run     = self.run?;
+        let elapsed = (self.clock.now_nanos() - run.anchor).max(0) as f64;
+        Some(run.start.add_frames((elapsed / run.start.rate().frame_nanos()) as i64))
+    }
+
+    /// Send every quarter frame due within the lookahead. Returns how many
+    /// were sent.
+    pub fn pump(&mut self, port: &MidiInputPort) -> Result<usize, isize> {
+        let Some(run) = self.run.as_mut() else {
+            return Ok(0);
+        };
+        let now      = self.clock.now_nanos();
+        let deadline = now + self.options.lookahead.as_nanos() as i64;
+        let quarter  = run.start.rate().frame_nanos() / 4.0;
+        let mut sent = 0;
+        loop {
+            let timestamp = run.anchor + (run.next_quarter as f64 * quarter).round() as i64;
+            if timestamp > deadline {
+                return Ok(sent);
+            }
+            // Each sequence of 8 names the frame its piece 0 starts.
+            let time  = run.start.add_frames(2 * (run.next_quarter / 8) as i64);
+            let piece = (run.next_quarter % 8) as u8;
+
+            let mut bytes = self.full_frame.take().unwrap_or_default();
+            MidiMessage::mtc_quarter_frame(time.quarter_frame(piece)).write_to(&mut bytes);
+            port.send_with_timestamp(&bytes, timestamp.max(now))?;
+            run.next_quarter += 1;
+            sent += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mtc_generator {
+    use super::*;
+
+    #[test]
+    fn test_generated_time_code_reads_back() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(0);
+        let mut generator = MtcGenerator::new(MtcGeneratorOptions::default(), clock.clone());
+
+        let start = SmpteTime::new(10, 0, 0, 0, SmpteRate::Fps24).unwrap();
+        generator.start(start);
+        while clock.now_nanos() < 500_000_000 {
+            generator.pump(&port).unwrap();
+            clock.advance(5_000_000);
+        }
+        assert_eq!(generator.position(), Some(start.add_frames(11)));
+
+        let sent = virtual_device.input_port(0).sent();
+        assert_eq!(sent[0].bytes[..2], [0xf0, 0x7f]);
+        // 24 fps: a quarter frame every 10.4ms from 20ms on.
+        assert_eq!(sent[1].timestamp, Some(20_000_000 + 10_416_667));
+
+        let mut reader = MtcReader::default();
+        let mut frames = Vec::new();
+        for packet in &sent {
+            reader.feed(&packet.bytes, packet.timestamp.unwrap(), |event| {
+                if let MtcEvent::Frame { time, .. } = event {
+                    frames.push(time);
+                }
+            });
+        }
+        assert!(reader.is_locked());
+        assert_eq!(frames.first(), Some(&start.add_frames(4)));
+        assert!(frames.windows(2).all(|pair| pair[1] == pair[0].add_frames(1)));
+    }
+}
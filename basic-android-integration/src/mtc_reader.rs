@@ -0,0 +1,255 @@
+// ---------------- [ File: basic-android-integration/src/mtc_reader.rs ]
+crate::ix!();
+
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct MtcReaderOptions {
+    /// Complete, consecutive quarter frame sequences needed before the
+    /// reader reports lock.
+    sequences_to_lock: u32,
+    /// Silence after which a locked reader reports a dropout. Quarter
+    /// frames arrive every 8-10ms at any rate.
+    dropout_timeout: Duration,
+}
+
+impl Default for MtcReaderOptions {
+    fn default() -> Self {
+        Self {
+            sequences_to_lock: 2,
+            dropout_timeout:   Duration::from_millis(100),
+        }
+    }
+}
+
+/// What an [`MtcReader`] makes of incoming time code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtcEvent {
+    /// Quarter frames have been running consistently; `Frame` events follow.
+    Locked { rate: SmpteRate },
+    /// The frame starting at `timestamp` (quarter frames 0 and 4 mark frame
+    /// starts). Only reported while locked.
+    Frame { time: SmpteTime, timestamp: i64 },
+    /// A full frame message jumped to `time`, typically while shuttling.
+    Located { time: SmpteTime, timestamp: i64 },
+    /// Quarter frames stopped or arrived out of sequence; `last` is the last
+    /// frame reported.
+    Dropout { last: SmpteTime },
+}
+
+/// Decodes MTC quarter frames and full frame SysEx into SMPTE positions.
+///
+/// A full quarter frame sequence spans two frames and carries the time of
+/// the frame in which its piece 0 was sent, so the reader only knows the
+/// time after piece 7; from then on it predicts every frame and checks the
+/// prediction against each new sequence. Sequences running backwards
+/// (reverse play) are treated as a dropout.
+#[derive(Debug, Clone)]
+pub struct MtcReader {
+    options:    MtcReaderOptions,
+    parser:     MidiStreamParser,
+    nibbles:    [u8; 8],
+    /// Next piece expected in the running sequence, `None` before a piece 0.
+    next_piece: Option<u8>,
+    /// The frame the next piece 0 or 4 starts, once a sequence completed.
+    next_frame: Option<SmpteTime>,
+    current:    Option<SmpteTime>,
+    consistent: u32,
+    locked:     bool,
+    last_seen:  Option<i64>,
+}
+
+impl Default for MtcReader {
+    fn default() -> Self {
+        Self::new(MtcReaderOptions::default())
+    }
+}
+
+impl MtcReader {
+    pub fn new(options: MtcReaderOptions) -> Self {
+        Self {
+            options,
+            parser:     MidiStreamParser::new(),
+            nibbles:    [0; 8],
+            next_piece: None,
+            next_frame: None,
+            current:    None,
+            consistent: 0,
+            locked:     false,
+            last_seen:  None,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// The frame last reported by `Frame` or `Located`.
+    pub fn position(&self) -> Option<SmpteTime> {
+        self.current
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], timestamp: i64, mut sink: impl FnMut(MtcEvent)) {
+        let mut messages = Vec::new();
+        self.parser.feed(bytes, |message| messages.push(message));
+        for message in messages {
+            self.observe(&message, timestamp, &mut sink);
+        }
+    }
+
+    /// Feed everything pending on `port`, then check for a dropout as of
+    /// `now`. Returns the number of data packets read.
+    pub fn poll(&mut self, port: &MidiOutputPort, now: i64, mut sink: impl FnMut(MtcEvent)) -> Result<usize, isize> {
+        let packets = port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, timestamp } => self.feed(bytes, timestamp, &mut sink),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })?;
+        self.check_dropout(now, sink);
+        Ok(packets)
+    }
+
+    /// Report a dropout if a locked stream has gone quiet.
+    pub fn check_dropout(&mut self, now: i64, sink: impl FnMut(MtcEvent)) {
+        let timeout = self.options.dropout_timeout.as_nanos() as i64;
+        if self.locked && self.last_seen.is_some_and(|last| now - last > timeout) {
+            self.unlock(sink);
+        }
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage, timestamp: i64, mut sink: impl FnMut(MtcEvent)) {
+        match message {
+            MidiMessage::MtcQuarterFrame(data) => self.quarter_frame(data.get(), timestamp, &mut sink),
+            MidiMessage::SysEx(data) => {
+                if let Some(time) = SmpteTime::from_full_frame_sysex(data) {
+                    if self.locked {
+                        self.unlock(&mut sink);
+                    }
+                    self.current = Some(time);
+                    sink(MtcEvent::Located { time, timestamp });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn quarter_frame(&mut self, data: u8, timestamp: i64, sink: &mut impl FnMut(MtcEvent)) {
+        let (piece, nibble) = (data >> 4 & 7, data & 0xf);
+        self.last_seen = Some(timestamp);
+
+        if piece != 0 && self.next_piece != Some(piece) {
+            if self.next_piece.is_some() && self.locked {
+                self.unlock(&mut *sink);
+            }
+            self.next_piece = None;
+            self.consistent = 0;
+            self.next_frame = None;
+            return;
+        }
+        self.nibbles[piece as usize] = nibble;
+        self.next_piece = Some(piece + 1);
+
+        if (piece == 0 || piece == 4) && let Some(frame) = self.next_frame {
+            if self.locked {
+                self.current = Some(frame);
+                sink(MtcEvent::Frame { time: frame, timestamp });
+            }
+            self.next_frame = Some(frame.add_frames(1));
+        }
+
+        if piece == 7 {
+            self.next_piece = None;
+            let Ok(time) = SmpteTime::from_quarter_frames(self.nibbles) else {
+                self.consistent = 0;
+                return;
+            };
+            // The next piece 0 starts two frames after the one this sequence named.
+            let predicted = time.add_frames(2);
+            self.consistent = if self.next_frame == Some(predicted) { self.consistent + 1 } else { 1 };
+            if self.locked && self.consistent == 1 {
+                // Consistent timing but a jump in the numbers: follow it.
+                debug!("mtc: time code jumped to {}", time);
+            }
+            self.next_frame = Some(predicted);
+            if !self.locked && self.consistent >= self.options.sequences_to_lock.max(1) {
+                self.locked = true;
+                sink(MtcEvent::Locked { rate: time.rate() });
+            }
+        }
+    }
+
+    fn unlock(&mut self, mut sink: impl FnMut(MtcEvent)) {
+        self.locked     = false;
+        self.consistent = 0;
+        self.next_frame = None;
+        self.next_piece = None;
+        if let Some(last) = self.current {
+            sink(MtcEvent::Dropout { last });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mtc_reader {
+    use super::*;
+
+    /// Quarter frames for `frames` frames from `start`, a quarter frame
+    /// period apart, as (data, timestamp).
+    fn quarter_frames(start: SmpteTime, frames: u64, from: i64) -> Vec<(u8, i64)> {
+        let quarter = start.rate().frame_nanos() / 4.0;
+        (0..frames * 4)
+            .map(|k| {
+                let time = start.add_frames(2 * (k / 8) as i64);
+                (time.quarter_frame((k % 8) as u8).get(), from + (k as f64 * quarter) as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_locks_reports_frames_and_detects_dropout() {
+        let start = SmpteTime::new(1, 0, 0, 0, SmpteRate::Fps25).unwrap();
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let output = virtual_device.output_port(0);
+        for (data, timestamp) in quarter_frames(start, 8, 0) {
+            output.push(vec![0xf1, data], timestamp);
+        }
+        let device = virtual_device.open();
+        let port   = device.open_output_port(0).unwrap();
+
+        let mut reader = MtcReader::default();
+        let mut events = Vec::new();
+        reader.poll(&port, 320_000_000, |event| events.push(event)).unwrap();
+
+        // Locked after the second sequence (frames 0-3); frames 4-7 reported.
+        assert_eq!(events[0], MtcEvent::Locked { rate: SmpteRate::Fps25 });
+        let frames: Vec<_> = events[1..].iter().map(|event| match event {
+            MtcEvent::Frame { time, timestamp } => (time.frames(), *timestamp),
+            other => panic!("{:?}", other),
+        }).collect();
+        assert_eq!(frames, vec![(4, 160_000_000), (5, 200_000_000), (6, 240_000_000), (7, 280_000_000)]);
+        assert!(reader.is_locked());
+
+        events.clear();
+        reader.check_dropout(500_000_000, |event| events.push(event));
+        assert_eq!(events, vec![MtcEvent::Dropout { last: start.add_frames(7) }]);
+        assert!(!reader.is_locked());
+    }
+
+    #[test]
+    fn test_full_frame_and_drop_frame_sequence() {
+        let mut reader = MtcReader::default();
+        let mut events = Vec::new();
+        let located = SmpteTime::new(0, 9, 59, 26, SmpteRate::Fps2997DropFrame).unwrap();
+        let sysex = MidiMessage::sysex(located.full_frame_sysex(U7::MAX)).unwrap();
+        reader.observe(&sysex, 5, |event| events.push(event));
+        assert_eq!(events, vec![MtcEvent::Located { time: located, timestamp: 5 }]);
+
+        // Runs across 00:10:00;00, a tenth minute, which keeps frames 0 and 1.
+        for (data, timestamp) in quarter_frames(located, 12, 10) {
+            reader.observe(&MidiMessage::mtc_quarter_frame(U7::new(data).unwrap()), timestamp, |event| events.push(event));
+        }
+        assert!(reader.is_locked());
+        assert_eq!(reader.position().unwrap().to_string(), "00:10:00;07");
+    }
+}
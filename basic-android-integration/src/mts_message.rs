@@ -0,0 +1,270 @@
+// ---------------- [ File: basic-android-integration/src/mts_message.rs ]
+crate::ix!();
+
+/// Sub-ID#1 of MIDI Tuning Standard messages.
+pub const MTS_SUB_ID: u8 = 0x08;
+
+/// An MTS frequency: a key number plus a 14-bit fraction of a semitone
+/// above it (`xx yy zz` on the wire).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MtsFrequency {
+    pub semitone: U7,
+    pub fraction: U14,
+}
+
+impl MtsFrequency {
+    /// `7F 7F 7F`: leave this key as it is.
+    pub const NO_CHANGE: MtsFrequency = MtsFrequency { semitone: U7::MAX, fraction: U14::MAX };
+
+    /// The nearest encodable frequency to a fractional MIDI note number,
+    /// clamped to 0..just under 127 + 16383/16384 (the top value is
+    /// reserved for [`NO_CHANGE`](Self::NO_CHANGE)).
+    pub fn from_pitch(pitch: f64) -> Self {
+        let steps = (pitch.max(0.0) * 16384.0).round().min((127 * 16384 + 0x3ffe) as f64) as u32;
+        Self {
+            semitone: U7::saturating((steps >> 14) as u8),
+            fraction: U14::new((steps & 0x3fff) as u16).unwrap_or(U14::MIN),
+        }
+    }
+
+    pub fn from_hz(hz: f64) -> Self {
+        Self::from_pitch(hz_to_pitch(hz))
+    }
+
+    pub fn pitch(&self) -> f64 {
+        self.semitone.get() as f64 + self.fraction.get() as f64 / 16384.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 3] {
+        [self.semitone.get(), self.fraction.msb().get(), self.fraction.lsb().get()]
+    }
+}
+
+/// How finely a scale/octave message gives each pitch class's offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MtsScaleResolution {
+    /// One byte per class, whole cents from -64 to +63.
+    #[default]
+    OneByte,
+    /// Two bytes per class, -100 to +100 cents in steps of 100/8192.
+    TwoByte,
+}
+
+/// A MIDI Tuning Standard message, encoded as SysEx data (without
+/// `F0`/`F7`) and sent to a synth through a [`MidiInputPort`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MtsMessage {
+    /// Bulk tuning dump (non-real-time `08 01`): every key of a tuning
+    /// program at once.
+    BulkDump {
+        device_id:   U7,
+        program:     U7,
+        /// Sent as 16 ASCII characters, space padded.
+        name:        String,
+        frequencies: Box<[MtsFrequency; 128]>,
+    },
+    /// Single note tuning change: real-time `08 02`, or `08 07` with a
+    /// bank, real-time or not as `realtime` says. Without a bank the
+    /// message is always real-time.
+    SingleNote {
+        device_id: U7,
+        realtime:  bool,
+        bank:      Option<U7>,
+        program:   U7,
+        changes:   Vec<(U7, MtsFrequency)>,
+    },
+    /// Scale/octave tuning (`08 08` or `08 09`): the same offset in cents
+    /// for a pitch class in every octave, on the given channels.
+    ScaleOctave {
+        device_id:  U7,
+        realtime:   bool,
+        channels:   Vec<Channel>,
+        resolution: MtsScaleResolution,
+        /// Cents from equal temperament for C, C#, ... B.
+        cents:      [f64; 12],
+    },
+}
+
+impl MtsMessage {
+    /// A bulk dump of `tuning`; silent keys are sent as
+    /// [`MtsFrequency::NO_CHANGE`].
+    pub fn bulk_dump(device_id: U7, program: U7, name: impl Into<String>, tuning: &MidiTuning) -> Self {
+        let mut frequencies = Box::new([MtsFrequency::NO_CHANGE; 128]);
+        for ((_, pitch), frequency) in tuning.iter().zip(frequencies.iter_mut()) {
+            if let Some(pitch) = pitch {
+                *frequency = MtsFrequency::from_pitch(pitch);
+            }
+        }
+        MtsMessage::BulkDump { device_id, program, name: name.into(), frequencies }
+    }
+
+    /// A real-time single note change for `keys`, taken from `tuning`.
+    /// One message carries at most 127 changes.
+    pub fn single_note(
+        device_id: U7,
+        program:   U7,
+        tuning:    &MidiTuning,
+        keys:      impl IntoIterator<Item = U7>,
+    ) -> Result<Self, MidiValueError> {
+        let changes: Vec<_> = keys
+            .into_iter()
+            .map(|key| (key, tuning.pitch(key).map(MtsFrequency::from_pitch).unwrap_or(MtsFrequency::NO_CHANGE)))
+            .collect();
+        count_byte(changes.len(), "single note tuning changes")?;
+        Ok(MtsMessage::SingleNote { device_id, realtime: true, bank: None, program, changes })
+    }
+
+    /// A scale/octave message carrying `tuning`'s
+    /// [`octave_cents`](MidiTuning::octave_cents).
+    pub fn scale_octave(
+        device_id:  U7,
+        channels:   impl IntoIterator<Item = Channel>,
+        resolution: MtsScaleResolution,
+        tuning:     &MidiTuning,
+    ) -> Self {
+        MtsMessage::ScaleOctave {
+            device_id,
+            realtime: true,
+            channels: channels.into_iter().collect(),
+            resolution,
+            cents: tuning.octave_cents(),
+        }
+    }
+
+    pub fn device_id(&self) -> U7 {
+        match self {
+            MtsMessage::BulkDump { device_id, .. }
+            | MtsMessage::SingleNote { device_id, .. }
+            | MtsMessage::ScaleOctave { device_id, .. } => *device_id,
+        }
+    }
+
+    /// SysEx data, without `F0`/`F7`. Fails if a single note change
+    /// carries more than 127 changes.
+    pub fn to_sysex(&self) -> Result<Vec<u8>, MidiValueError> {
+        if let MtsMessage::SingleNote { changes, .. } = self {
+            count_byte(changes.len(), "single note tuning changes")?;
+        }
+        Ok(SysExData::new(self.encode())?.into_vec())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let universal = |realtime| if realtime { UNIVERSAL_REAL_TIME } else { UNIVERSAL_NON_REAL_TIME };
+        let device_id = self.device_id().get();
+        match self {
+            MtsMessage::BulkDump { program, name, frequencies, .. } => {
+                let mut out = vec![UNIVERSAL_NON_REAL_TIME, device_id, MTS_SUB_ID, 0x01, program.get()];
+                let name = name.bytes().chain(std::iter::repeat(b' ')).take(16);
+                out.extend(name.map(|b| if (0x20..0x7f).contains(&b) { b } else { b'?' }));
+                for frequency in frequencies.iter() {
+                    out.extend_from_slice(&frequency.to_bytes());
+                }
+                out.push(xor_checksum(&out));
+                out
+            }
+            MtsMessage::SingleNote { realtime, bank, program, changes, .. } => {
+                let mut out = match bank {
+                    Some(bank) => vec![universal(*realtime), device_id, MTS_SUB_ID, 0x07, bank.get()],
+                    None => vec![UNIVERSAL_REAL_TIME, device_id, MTS_SUB_ID, 0x02],
+                };
+                out.extend_from_slice(&[program.get(), changes.len() as u8]);
+                for (key, frequency) in changes {
+                    out.push(key.get());
+                    out.extend_from_slice(&frequency.to_bytes());
+                }
+                out
+            }
+            MtsMessage::ScaleOctave { realtime, channels, resolution, cents, .. } => {
+                let sub_id2 = match resolution {
+                    MtsScaleResolution::OneByte => 0x08,
+                    MtsScaleResolution::TwoByte => 0x09,
+                };
+                let mask: u32 = channels.iter().fold(0, |mask, channel| mask | 1 << channel.get());
+                let mut out = vec![
+                    universal(*realtime),
+                    device_id,
+                    MTS_SUB_ID,
+                    sub_id2,
+                    (mask >> 14) as u8 & 0x03,
+                    (mask >> 7) as u8 & 0x7f,
+                    mask as u8 & 0x7f,
+                ];
+                for &cents in cents {
+                    match resolution {
+                        MtsScaleResolution::OneByte => out.push((cents.round().clamp(-64.0, 63.0) + 64.0) as u8),
+                        MtsScaleResolution::TwoByte => {
+                            let value = U14::new((8192.0 + cents * 81.92).round().clamp(0.0, 16383.0) as u16).unwrap_or(U14::CENTER);
+                            out.extend_from_slice(&[value.msb().get(), value.lsb().get()]);
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    pub fn to_message(&self) -> Result<MidiMessage, MidiValueError> {
+        MidiMessage::sysex(self.to_sysex()?)
+    }
+
+    pub fn send(&self, port: &MidiInputPort) -> Result<usize, SysExSendError> {
+        Ok(port.send_all(&self.to_message()?.to_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod test_mts_message {
+    use super::*;
+
+    #[test]
+    fn test_frequency_encoding() {
+        // The spec's examples: 8.1758 Hz is 00 00 00, 440 Hz is 45 00 00,
+        // 8372.0190 Hz is 78 00 00.
+        assert_eq!(MtsFrequency::from_hz(8.175799).to_bytes(), [0x00, 0x00, 0x00]);
+        assert_eq!(MtsFrequency::from_hz(440.0).to_bytes(), [0x45, 0x00, 0x00]);
+        assert_eq!(MtsFrequency::from_hz(8372.0190).to_bytes(), [0x78, 0x00, 0x00]);
+        // A quarter tone above middle C.
+        assert_eq!(MtsFrequency::from_pitch(60.5).to_bytes(), [60, 0x40, 0x00]);
+        assert_eq!(MtsFrequency::from_pitch(200.0).to_bytes(), [0x7f, 0x7f, 0x7e]);
+        assert_eq!(MtsFrequency::from_pitch(-3.0).to_bytes(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_bulk_dump_and_single_note() {
+        let mut tuning = MidiTuning::equal_temperament();
+        tuning.set_pitch(U7::saturating(0), None);
+        let dump = MtsMessage::bulk_dump(U7::saturating(0x10), U7::saturating(3), "Just", &tuning).to_sysex().unwrap();
+        assert_eq!(dump.len(), 5 + 16 + 128 * 3 + 1);
+        assert_eq!(&dump[..9], &[0x7e, 0x10, 0x08, 0x01, 0x03, b'J', b'u', b's', b't']);
+        assert_eq!(&dump[21..27], &[0x7f, 0x7f, 0x7f, 0x01, 0x00, 0x00]);
+        assert_eq!(dump.iter().fold(0, |sum, b| sum ^ b), 0);
+
+        tuning.set_pitch(U7::saturating(61), Some(60.5));
+        let keys    = [61, 62].map(U7::saturating);
+        let message = MtsMessage::single_note(ALL_CALL, U7::MIN, &tuning, keys).unwrap();
+        assert_eq!(message.to_sysex().unwrap(), vec![0x7f, 0x7f, 0x08, 0x02, 0x00, 0x02, 61, 60, 0x40, 0x00, 62, 62, 0x00, 0x00]);
+        assert!(MtsMessage::single_note(ALL_CALL, U7::MIN, &tuning, (0..=127).map(U7::saturating)).is_err());
+
+        let changes = vec![(U7::MIN, MtsFrequency::NO_CHANGE); 128];
+        let message = MtsMessage::SingleNote { device_id: ALL_CALL, realtime: true, bank: None, program: U7::MIN, changes };
+        assert!(message.to_message().is_err());
+    }
+
+    #[test]
+    fn test_scale_octave() {
+        let mut tuning = MidiTuning::equal_temperament();
+        tuning.set_pitch(U7::saturating(64), Some(63.8631)); // just major third, -13.7 cents
+        tuning.set_pitch(U7::saturating(71), Some(71.5));
+        let channels = [0, 7, 15].map(|c| Channel::new(c).unwrap());
+
+        let one = MtsMessage::scale_octave(ALL_CALL, channels, MtsScaleResolution::OneByte, &tuning).to_sysex().unwrap();
+        assert_eq!(&one[..7], &[0x7f, 0x7f, 0x08, 0x08, 0x02, 0x01, 0x01]);
+        assert_eq!(one[7 + 4], 64 - 14);
+        assert_eq!(one[7 + 11], 64 + 50);
+
+        let two = MtsMessage::scale_octave(ALL_CALL, channels, MtsScaleResolution::TwoByte, &tuning).to_sysex().unwrap();
+        assert_eq!(two.len(), 7 + 24);
+        assert_eq!(&two[7..9], &[0x40, 0x00]);
+        assert_eq!(U14::from_msb_lsb(U7::saturating(two[7 + 22]), U7::saturating(two[7 + 23])).get(), 8192 + 4096);
+    }
+}
@@ -0,0 +1,224 @@
+// ---------------- [ File: basic-android-integration/src/note_tracker.rs ]
+crate::ix!();
+
+pub const CC_SUSTAIN:               u8 = 64;
+pub const CC_SOSTENUTO:             u8 = 66;
+pub const CC_ALL_SOUND_OFF:         u8 = 120;
+pub const CC_RESET_ALL_CONTROLLERS: u8 = 121;
+pub const CC_ALL_NOTES_OFF:         u8 = 123;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ChannelNoteState {
+    /// Notes whose key is down (note-on without note-off yet), one bit per note.
+    held:      u128,
+    /// Notes released while a pedal kept them sounding.
+    sustained: u128,
+    /// Notes that were held when sostenuto went down.
+    latched:   u128,
+    sustain:   bool,
+    sostenuto: bool,
+}
+
+/// Which notes are sounding on each channel of an outgoing stream, and
+/// which pedals are down.
+///
+/// Feed it every message sent to a synth; [`panic_messages`](Self::panic_messages)
+/// then says exactly what to send to silence it. Follows the channel mode
+/// semantics: All Notes Off releases keys but leaves pedalled notes
+/// sounding, All Sound Off silences everything, Reset All Controllers lifts
+/// the pedals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteTracker {
+    channels: [ChannelNoteState; 16],
+}
+
+impl NoteTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage) {
+        match *message {
+            MidiMessage::NoteOn { channel, note, velocity } if velocity.get() > 0 => {
+                let state = &mut self.channels[channel.get() as usize];
+                state.held |= 1 << note.get();
+            }
+            MidiMessage::NoteOn { channel, note, .. } | MidiMessage::NoteOff { channel, note, .. } => {
+                let state = &mut self.channels[channel.get() as usize];
+                let bit   = 1u128 << note.get();
+                if state.held & bit != 0 {
+                    state.held &= !bit;
+                    if state.sustain || (state.sostenuto && state.latched & bit != 0) {
+                        state.sustained |= bit;
+                    }
+                }
+            }
+            MidiMessage::ControlChange { channel, controller, value } => {
+                self.observe_control(channel, controller.get(), value.get() >= 64)
+            }
+            MidiMessage::SystemReset => self.clear(),
+            _ => {}
+        }
+    }
+
+    fn observe_control(&mut self, channel: Channel, controller: u8, on: bool) {
+        let state = &mut self.channels[channel.get() as usize];
+        match controller {
+            CC_SUSTAIN => {
+                state.sustain = on;
+                if !on {
+                    state.sustained &= if state.sostenuto { state.latched } else { 0 };
+                }
+            }
+            CC_SOSTENUTO => {
+                if on && !state.sostenuto {
+                    state.latched = state.held;
+                }
+                if !on {
+                    state.latched = 0;
+                    if !state.sustain {
+                        state.sustained = 0;
+                    }
+                }
+                state.sostenuto = on;
+            }
+            CC_ALL_NOTES_OFF => {
+                if state.sustain {
+                    state.sustained |= state.held;
+                } else if state.sostenuto {
+                    state.sustained |= state.held & state.latched;
+                }
+                state.held = 0;
+            }
+            CC_ALL_SOUND_OFF => {
+                state.held      = 0;
+                state.sustained = 0;
+            }
+            CC_RESET_ALL_CONTROLLERS => {
+                state.sustain   = false;
+                state.sostenuto = false;
+                state.latched   = 0;
+                state.sustained = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget everything, e.g. after the synth was silenced out of band.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_sounding(&self, channel: Channel, note: U7) -> bool {
+        let state = &self.channels[channel.get() as usize];
+        (state.held | state.sustained) & (1 << note.get()) != 0
+    }
+
+    /// Notes sounding on `channel`, lowest first, whether held by a key or a pedal.
+    pub fn sounding_notes(&self, channel: Channel) -> impl Iterator<Item = U7> + '_ {
+        let bits = self.channels[channel.get() as usize].held | self.channels[channel.get() as usize].sustained;
+        (0..128u8).filter(move |n| bits & (1 << n) != 0).map(U7::saturating)
+    }
+
+    pub fn sustain(&self, channel: Channel) -> bool {
+        self.channels[channel.get() as usize].sustain
+    }
+
+    pub fn sostenuto(&self, channel: Channel) -> bool {
+        self.channels[channel.get() as usize].sostenuto
+    }
+
+    /// No notes sounding and no pedals down anywhere.
+    pub fn is_silent(&self) -> bool {
+        self.channels.iter().all(|state| *state == ChannelNoteState::default())
+    }
+
+    /// Note Off for every sounding note and pedal-up for every pedal down,
+    /// then All Notes Off and All Sound Off on all sixteen channels for
+    /// anything we did not see.
+    pub fn panic_messages(&self) -> Vec<MidiMessage> {
+        let mut messages = Vec::new();
+        for channel in Channel::all() {
+            messages.extend(self.sounding_notes(channel).map(|note| MidiMessage::note_off(channel, note, U7::MIN)));
+            if self.sustain(channel) {
+                messages.push(MidiMessage::control_change(channel, U7::saturating(CC_SUSTAIN), U7::MIN));
+            }
+            if self.sostenuto(channel) {
+                messages.push(MidiMessage::control_change(channel, U7::saturating(CC_SOSTENUTO), U7::MIN));
+            }
+        }
+        messages.extend(Self::blanket_panic_messages());
+        messages
+    }
+
+    /// All Notes Off and All Sound Off on every channel.
+    pub fn blanket_panic_messages() -> impl Iterator<Item = MidiMessage> {
+        Channel::all().flat_map(|channel| {
+            [
+                MidiMessage::control_change(channel, U7::saturating(CC_ALL_NOTES_OFF), U7::MIN),
+                MidiMessage::control_change(channel, U7::saturating(CC_ALL_SOUND_OFF), U7::MIN),
+            ]
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_note_tracker {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+    fn on(c: u8, n: u8) -> MidiMessage { MidiMessage::note_on(ch(c), u7(n), u7(100)) }
+    fn off(c: u8, n: u8) -> MidiMessage { MidiMessage::note_on(ch(c), u7(n), U7::MIN) }
+    fn cc(c: u8, controller: u8, value: u8) -> MidiMessage { MidiMessage::control_change(ch(c), u7(controller), u7(value)) }
+
+    fn sounding(tracker: &NoteTracker, c: u8) -> Vec<u8> {
+        tracker.sounding_notes(ch(c)).map(U7::get).collect()
+    }
+
+    #[test]
+    fn test_sustain_and_sostenuto() {
+        let mut tracker = NoteTracker::new();
+        for message in [on(0, 60), on(0, 64), cc(0, CC_SOSTENUTO, 127), on(0, 67), off(0, 60), off(0, 67)] {
+            tracker.observe(&message);
+        }
+        // 60 was down when sostenuto caught it; 67 came later.
+        assert_eq!(sounding(&tracker, 0), vec![60, 64]);
+
+        tracker.observe(&cc(0, CC_SUSTAIN, 127));
+        tracker.observe(&off(0, 64));
+        tracker.observe(&cc(0, CC_SOSTENUTO, 0));
+        assert_eq!(sounding(&tracker, 0), vec![60, 64]);
+        tracker.observe(&cc(0, CC_SUSTAIN, 0));
+        assert!(tracker.is_silent());
+    }
+
+    #[test]
+    fn test_channel_mode_messages() {
+        let mut tracker = NoteTracker::new();
+        for message in [on(3, 40), cc(3, CC_SUSTAIN, 100), on(3, 41), cc(3, CC_ALL_NOTES_OFF, 0), on(5, 50)] {
+            tracker.observe(&message);
+        }
+        assert_eq!(sounding(&tracker, 3), vec![40, 41]);
+        tracker.observe(&cc(3, CC_ALL_SOUND_OFF, 0));
+        assert_eq!(sounding(&tracker, 3), Vec::<u8>::new());
+        assert!(tracker.sustain(ch(3)));
+        tracker.observe(&cc(3, CC_RESET_ALL_CONTROLLERS, 0));
+        assert!(!tracker.sustain(ch(3)));
+        assert_eq!(sounding(&tracker, 5), vec![50]);
+    }
+
+    #[test]
+    fn test_panic_messages_are_targeted_first() {
+        let mut tracker = NoteTracker::new();
+        for message in [on(1, 60), cc(1, CC_SUSTAIN, 127)] {
+            tracker.observe(&message);
+        }
+        let messages = tracker.panic_messages();
+        assert_eq!(messages.len(), 2 + 32);
+        assert_eq!(messages[0], MidiMessage::note_off(ch(1), u7(60), U7::MIN));
+        assert_eq!(messages[1], cc(1, CC_SUSTAIN, 0));
+        assert_eq!(messages[2], cc(0, CC_ALL_NOTES_OFF, 0));
+        assert_eq!(messages[3], cc(0, CC_ALL_SOUND_OFF, 0));
+    }
+}
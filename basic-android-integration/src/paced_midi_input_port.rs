@@ -0,0 +1,202 @@
+// ---------------- [ File: basic-android-integration/src/paced_midi_input_port.rs ]
+crate::ix!();
+
+use std::time::Duration;
+
+/// Model of the physical link behind a port, e.g. a USB-to-DIN interface
+/// that cannot buffer more than the 5-pin wire drains.
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct WirePacing {
+    /// Output bandwidth. DIN MIDI runs at 31250 baud with 10 bits per byte,
+    /// i.e. 3125 bytes per second.
+    bytes_per_second: u32,
+
+    /// Silence between the end of one SysEx and the start of the next, for
+    /// gear that needs time to digest each dump.
+    inter_sysex_gap: Duration,
+
+    /// SysEx is scheduled in pieces of this many bytes, so a long dump is
+    /// spread over its wire time instead of arriving as one burst.
+    sysex_chunk_size: usize,
+}
+
+impl Default for WirePacing {
+    fn default() -> Self {
+        Self {
+            bytes_per_second: 3125,
+            inter_sysex_gap:  Duration::ZERO,
+            sysex_chunk_size: 32,
+        }
+    }
+}
+
+impl WirePacing {
+    pub fn nanos_per_byte(&self) -> i64 {
+        1_000_000_000 / self.bytes_per_second.max(1) as i64
+    }
+}
+
+/// Sends through a `MidiInputPort` no faster than [`WirePacing`] allows.
+///
+/// Each message (or SysEx chunk) is handed to `send_with_timestamp` with the
+/// time the modelled wire becomes free, so libamidi releases it at wire
+/// speed. System realtime bytes (clock, start, stop, ...) bypass the schedule
+/// and go out immediately, even when they are interleaved inside a SysEx.
+pub struct PacedMidiInputPort<'p, 'lib, C: MonotonicClock = SystemMonotonicClock> {
+    port:           &'p MidiInputPort<'lib>,
+    pacing:         WirePacing,
+    clock:          C,
+    send_policy:    SendAllPolicy,
+    wire_free_at:   i64,
+    last_sysex_end: Option<i64>,
+    scratch:        Vec<u8>,
+}
+
+impl<'p, 'lib> PacedMidiInputPort<'p, 'lib, SystemMonotonicClock> {
+    pub fn new(port: &'p MidiInputPort<'lib>, pacing: WirePacing) -> Self {
+        Self::with_clock(port, pacing, SystemMonotonicClock)
+    }
+}
+
+impl<'p, 'lib, C: MonotonicClock> PacedMidiInputPort<'p, 'lib, C> {
+    pub fn with_clock(port: &'p MidiInputPort<'lib>, pacing: WirePacing, clock: C) -> Self {
+        Self {
+            port,
+            pacing,
+            clock,
+            send_policy:    SendAllPolicy::default(),
+            wire_free_at:   i64::MIN,
+            last_sysex_end: None,
+            scratch:        Vec::new(),
+        }
+    }
+
+    /// Policy used for the underlying `send_all_at` calls.
+    pub fn set_send_policy(&mut self, policy: SendAllPolicy) {
+        self.send_policy = policy;
+    }
+
+    pub fn pacing(&self) -> &WirePacing {
+        &self.pacing
+    }
+
+    /// When the modelled wire will have drained everything scheduled so far.
+    pub fn wire_free_at(&self) -> i64 {
+        self.wire_free_at.max(self.clock.now_nanos())
+    }
+
+    pub fn send_message(&mut self, message: &MidiMessage) -> Result<i64, SendAllError> {
+        let bytes = message.to_bytes();
+        self.send(&bytes)
+    }
+
+    /// Schedule `bytes` at wire speed. Returns the time the wire is expected
+    /// to be idle again.
+    pub fn send(&mut self, bytes: &[u8]) -> Result<i64, SendAllError> {
+        for segment in midi_byte_segments(bytes) {
+            let segment_bytes = &bytes[segment.range.clone()];
+            match segment.kind {
+                MidiSegmentKind::Realtime => self.send_realtime(segment_bytes)?,
+                MidiSegmentKind::SysEx    => self.send_sysex(segment_bytes)?,
+                _                         => self.schedule(segment_bytes)?,
+            }
+        }
+        Ok(self.wire_free_at())
+    }
+
+    fn send_realtime(&mut self, byte: &[u8]) -> Result<(), SendAllError> {
+        trace!("pacing: realtime {:02x?} bypasses the queue", byte);
+        self.port.send_all_at(byte, None, &self.send_policy)?;
+        // The byte still occupies the wire, pushing queued data back a little.
+        self.wire_free_at = self.wire_free_at() + self.pacing.nanos_per_byte() * byte.len() as i64;
+        Ok(())
+    }
+
+    fn send_sysex(&mut self, segment: &[u8]) -> Result<(), SendAllError> {
+        let mut data = std::mem::take(&mut self.scratch);
+        data.clear();
+
+        let mut result = Ok(());
+        for &byte in segment {
+            if byte >= 0xf8 {
+                result = result.and_then(|_| self.send_realtime(&[byte]));
+            } else {
+                data.push(byte);
+            }
+        }
+
+        if result.is_ok() {
+            if let Some(end) = self.last_sysex_end {
+                let gap = self.pacing.inter_sysex_gap().as_nanos() as i64;
+                self.wire_free_at = self.wire_free_at().max(end + gap);
+            }
+            for chunk in data.chunks(self.pacing.sysex_chunk_size().max(1)) {
+                result = self.schedule(chunk);
+                if result.is_err() {
+                    break;
+                }
+            }
+            self.last_sysex_end = Some(self.wire_free_at);
+        }
+
+        self.scratch = data;
+        result
+    }
+
+    fn schedule(&mut self, bytes: &[u8]) -> Result<(), SendAllError> {
+        let at = self.wire_free_at();
+        trace!("pacing: {} bytes at {}", bytes.len(), at);
+        self.port.send_all_at(bytes, Some(at), &self.send_policy)?;
+        self.wire_free_at = at + self.pacing.nanos_per_byte() * bytes.len() as i64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_paced_midi_input_port {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_spaced_at_wire_speed() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(1_000_000);
+
+        let pacing = WirePacingBuilder::default()
+            .inter_sysex_gap(Duration::from_millis(10))
+            .sysex_chunk_size(4usize)
+            .build()
+            .unwrap();
+        let mut paced = PacedMidiInputPort::with_clock(&port, pacing, clock.clone());
+
+        // Two notes, then two SysEx dumps with a realtime clock inside the first.
+        paced.send(&[0x90, 60, 100, 0x80, 60, 0]).unwrap();
+        paced.send(&[0xf0, 1, 2, 0xf8, 3, 0xf7]).unwrap();
+        paced.send(&[0xf0, 4, 0xf7]).unwrap();
+
+        let sent: Vec<_> = virtual_device
+            .input_port(0)
+            .sent()
+            .into_iter()
+            .map(|p| (p.bytes, p.timestamp))
+            .collect();
+
+        let byte = 320_000;
+        let t0   = 1_000_000;
+        assert_eq!(
+            sent,
+            vec![
+                (vec![0x90, 60, 100], Some(t0)),
+                (vec![0x80, 60, 0], Some(t0 + 3 * byte)),
+                (vec![0xf8], None),
+                // the realtime byte took one byte of wire time
+                (vec![0xf0, 1, 2, 3], Some(t0 + 7 * byte)),
+                (vec![0xf7], Some(t0 + 11 * byte)),
+                (vec![0xf0, 4, 0xf7], Some(t0 + 12 * byte + 10_000_000)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+// ---------------- [ File: basic-android-integration/src/parameter_change.rs ]
+crate::ix!();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ParameterKind {
+    /// Registered parameter (CC 101/100 select, data entry on 6/38).
+    Rpn,
+    /// Non-registered parameter (CC 99/98 select, data entry on 6/38).
+    Nrpn,
+    /// A 14-bit controller pair; `number` is the MSB controller (0-31).
+    Cc14,
+}
+
+/// A complete parameter value, reassembled from (or to be split into)
+/// control changes.
+///
+/// Built only through the constructors, so a [`ParameterKind::Cc14`]
+/// number is always a controller [`cc14`](Self::cc14) accepts.
+#[derive(CopyGetters, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[getset(get_copy = "pub")]
+pub struct ParameterChange {
+    channel: Channel,
+    kind:    ParameterKind,
+    number:  U14,
+    value:   U14,
+}
+
+impl ParameterChange {
+    pub fn rpn(channel: Channel, number: U14, value: U14) -> Self {
+        Self { channel, kind: ParameterKind::Rpn, number, value }
+    }
+
+    pub fn nrpn(channel: Channel, number: U14, value: U14) -> Self {
+        Self { channel, kind: ParameterKind::Nrpn, number, value }
+    }
+
+    /// Fails unless `msb_controller` is 0-31 and not data entry (6).
+    pub fn cc14(channel: Channel, msb_controller: U7, value: U14) -> Result<Self, MidiValueError> {
+        if !is_cc14_msb(msb_controller.get()) {
+            return Err(MidiValueError::OutOfRange { what: "14-bit controller", value: msb_controller.get() as i64, min: 0, max: 31 });
+        }
+        Ok(Self { channel, kind: ParameterKind::Cc14, number: U14::from_msb_lsb(U7::MIN, msb_controller), value })
+    }
+
+    /// For the decoder, which only builds 14-bit controller changes from
+    /// controllers that pass [`is_cc14_msb`].
+    pub(crate) fn from_parts(channel: Channel, kind: ParameterKind, number: U14, value: U14) -> Self {
+        debug_assert!(kind != ParameterKind::Cc14 || (number.get() < 32 && is_cc14_msb(number.get() as u8)));
+        Self { channel, kind, number, value }
+    }
+}
+
+/// MSB controllers that pair with an LSB 32 higher. Data entry (6/38)
+/// belongs to RPN/NRPN instead.
+pub(crate) fn is_cc14_msb(controller: u8) -> bool {
+    controller < 32 && controller != CC_DATA_ENTRY_MSB
+}
@@ -0,0 +1,270 @@
+// ---------------- [ File: basic-android-integration/src/parameter_change_decoder.rs ]
+crate::ix!();
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct ParameterDecoderOptions {
+    /// How long to wait for an LSB after an MSB before reporting the MSB
+    /// alone (with an LSB of 0). `None` reports every MSB immediately and
+    /// again when its LSB arrives.
+    msb_timeout: Option<Duration>,
+}
+
+impl Default for ParameterDecoderOptions {
+    fn default() -> Self {
+        Self {
+            msb_timeout: Some(Duration::from_millis(10)),
+        }
+    }
+}
+
+/// What a held MSB belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingTarget {
+    Cc14(u8),
+    Parameter(ParameterKind, U14),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingMsb {
+    target: PendingTarget,
+    msb:    U7,
+    since:  i64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChannelDecoderState {
+    /// Last MSB seen per 14-bit controller, for LSB-only updates.
+    cc_msb:    [Option<U7>; 32],
+    rpn:       (Option<U7>, Option<U7>),
+    nrpn:      (Option<U7>, Option<U7>),
+    selected:  Option<(ParameterKind, U14)>,
+    /// Current value per parameter, for data entry LSB and increment/decrement.
+    values:    HashMap<(ParameterKind, U14), U14>,
+    pending:   Option<PendingMsb>,
+}
+
+/// Reassembles RPN, NRPN and 14-bit controller values from control changes.
+///
+/// Feed every incoming message with its timestamp; changes come out through
+/// the sink. An MSB is held until its LSB arrives or the MSB timeout
+/// passes (checked on the next message and by [`poll`](Self::poll)), so
+/// senders that transmit MSB then LSB produce one change, and MSB-only
+/// senders still produce one. Parameter selection follows the spec: the
+/// last of 101/100 or 99/98 wins, 127/127 deselects, data entry without a
+/// selection is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterChangeDecoder {
+    options:  ParameterDecoderOptions,
+    channels: [ChannelDecoderState; 16],
+}
+
+impl ParameterChangeDecoder {
+    pub fn new(options: ParameterDecoderOptions) -> Self {
+        Self { options, channels: Default::default() }
+    }
+
+    pub fn reset(&mut self) {
+        self.channels = Default::default();
+    }
+
+    pub fn observe(&mut self, message: &MidiMessage, timestamp: i64, mut sink: impl FnMut(ParameterChange)) {
+        self.poll(timestamp, &mut sink);
+
+        let MidiMessage::ControlChange { channel, controller, value } = *message else {
+            if let MidiMessage::SystemReset = message {
+                self.reset();
+            }
+            return;
+        };
+        let immediate = self.options.msb_timeout().is_none();
+        let state     = &mut self.channels[channel.get() as usize];
+        let cc        = controller.get();
+
+        // Anything but the matching LSB resolves a held MSB first.
+        let lsb_target = match cc {
+            CC_DATA_ENTRY_LSB => state.selected.map(|(kind, number)| PendingTarget::Parameter(kind, number)),
+            32..=63 if is_cc14_msb(cc - 32) => Some(PendingTarget::Cc14(cc - 32)),
+            _ => None,
+        };
+        if let Some(pending) = state.pending
+            && Some(pending.target) != lsb_target
+        {
+            state.pending = None;
+            sink(state.change(channel, pending.target, U14::from_msb_lsb(pending.msb, U7::MIN)));
+        }
+
+        let target = match cc {
+            CC_RPN_MSB => return state.select(ParameterKind::Rpn, Some(value), None),
+            CC_RPN_LSB => return state.select(ParameterKind::Rpn, None, Some(value)),
+            CC_NRPN_MSB => return state.select(ParameterKind::Nrpn, Some(value), None),
+            CC_NRPN_LSB => return state.select(ParameterKind::Nrpn, None, Some(value)),
+            _ if is_cc14_msb(cc) => {
+                state.cc_msb[cc as usize] = Some(value);
+                PendingTarget::Cc14(cc)
+            }
+            32..=63 if is_cc14_msb(cc - 32) => PendingTarget::Cc14(cc - 32),
+            CC_DATA_ENTRY_MSB | CC_DATA_ENTRY_LSB | CC_DATA_INCREMENT | CC_DATA_DECREMENT => match state.selected {
+                Some((kind, number)) => PendingTarget::Parameter(kind, number),
+                None => return,
+            },
+            _ => return,
+        };
+
+        let change = match cc {
+            CC_DATA_ENTRY_MSB | 0..=31 if !immediate => {
+                state.pending = Some(PendingMsb { target, msb: value, since: timestamp });
+                return;
+            }
+            CC_DATA_ENTRY_MSB | 0..=31 => U14::from_msb_lsb(value, U7::MIN),
+            CC_DATA_INCREMENT => U14::new(state.current(target).get().saturating_add(1)).unwrap_or(U14::MAX),
+            CC_DATA_DECREMENT => U14::new(state.current(target).get().saturating_sub(1)).unwrap(),
+            // an LSB
+            _ => {
+                let msb = match state.pending.take() {
+                    Some(pending) => pending.msb,
+                    None => match target {
+                        PendingTarget::Cc14(msb_cc) => match state.cc_msb[msb_cc as usize] {
+                            Some(msb) => msb,
+                            // LSB with no MSB ever seen: nothing to pair it with.
+                            None => return,
+                        },
+                        PendingTarget::Parameter(..) => state.current(target).msb(),
+                    },
+                };
+                U14::from_msb_lsb(msb, value)
+            }
+        };
+        sink(state.change(channel, target, change));
+    }
+
+    /// Report MSBs whose LSB did not arrive within the timeout.
+    pub fn poll(&mut self, now: i64, mut sink: impl FnMut(ParameterChange)) {
+        let Some(timeout) = self.options.msb_timeout() else {
+            return;
+        };
+        let timeout = timeout.as_nanos() as i64;
+        for (channel, state) in Channel::all().zip(self.channels.iter_mut()) {
+            if let Some(pending) = state.pending
+                && now - pending.since >= timeout
+            {
+                state.pending = None;
+                sink(state.change(channel, pending.target, U14::from_msb_lsb(pending.msb, U7::MIN)));
+            }
+        }
+    }
+
+    /// Report every held MSB now, e.g. at the end of a stream.
+    pub fn flush(&mut self, mut sink: impl FnMut(ParameterChange)) {
+        for (channel, state) in Channel::all().zip(self.channels.iter_mut()) {
+            if let Some(pending) = state.pending.take() {
+                sink(state.change(channel, pending.target, U14::from_msb_lsb(pending.msb, U7::MIN)));
+            }
+        }
+    }
+}
+
+impl ChannelDecoderState {
+    fn current(&self, target: PendingTarget) -> U14 {
+        match target {
+            PendingTarget::Parameter(kind, number) => self.values.get(&(kind, number)).copied().unwrap_or(U14::MIN),
+            PendingTarget::Cc14(_) => U14::MIN,
+        }
+    }
+
+    /// Build the change, remembering parameter values for later data entry.
+    fn change(&mut self, channel: Channel, target: PendingTarget, value: U14) -> ParameterChange {
+        match target {
+            PendingTarget::Cc14(msb_cc) => {
+                ParameterChange::from_parts(channel, ParameterKind::Cc14, U14::from_msb_lsb(U7::MIN, U7::saturating(msb_cc)), value)
+            }
+            PendingTarget::Parameter(kind, number) => {
+                self.values.insert((kind, number), value);
+                ParameterChange::from_parts(channel, kind, number, value)
+            }
+        }
+    }
+
+    fn select(&mut self, kind: ParameterKind, msb: Option<U7>, lsb: Option<U7>) {
+        let slot = if kind == ParameterKind::Rpn { &mut self.rpn } else { &mut self.nrpn };
+        if msb.is_some() {
+            slot.0 = msb;
+        }
+        if lsb.is_some() {
+            slot.1 = lsb;
+        }
+        let (msb, lsb) = (slot.0.unwrap_or(U7::MAX), slot.1.unwrap_or(U7::MAX));
+        self.selected = if msb == U7::MAX && lsb == U7::MAX {
+            None
+        } else {
+            Some((kind, U14::from_msb_lsb(msb, lsb)))
+        };
+    }
+}
+
+#[cfg(test)]
+mod test_parameter_change_decoder {
+    use super::*;
+    use std::time::Duration;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u14(n: u16) -> U14 { U14::new(n).unwrap() }
+    fn cc(c: u8, controller: u8, value: u8) -> MidiMessage { MidiMessage::control_change(ch(c), U7::new(controller).unwrap(), U7::new(value).unwrap()) }
+
+    fn decode(decoder: &mut ParameterChangeDecoder, messages: &[(MidiMessage, i64)]) -> Vec<ParameterChange> {
+        let mut out = Vec::new();
+        for (message, timestamp) in messages {
+            decoder.observe(message, *timestamp, |change| out.push(change));
+        }
+        out
+    }
+
+    #[test]
+    fn test_decoder_pairs_and_times_out() {
+        let ms = 1_000_000;
+        let mut decoder = ParameterChangeDecoder::default();
+        let changes = decode(&mut decoder, &[
+            (cc(0, 1, 64), 0),
+            (cc(0, 33, 5), ms),               // paired with its MSB
+            (cc(0, 7, 100), 2 * ms),          // MSB only...
+            (MidiMessage::TimingClock, 20 * ms), // ...reported once the timeout passes
+            (cc(0, 39, 1), 21 * ms),          // LSB-only update of CC 7
+            (cc(0, 101, 0), 22 * ms),
+            (cc(0, 100, 0), 22 * ms),
+            (cc(0, 6, 2), 22 * ms),
+            (cc(0, 38, 0), 22 * ms),
+            (cc(0, 96, 0), 23 * ms),
+            (cc(0, 101, 127), 24 * ms),
+            (cc(0, 100, 127), 24 * ms),
+            (cc(0, 6, 9), 25 * ms),           // no parameter selected
+        ]);
+        let cc14 = |msb_cc: u8, value| ParameterChange::cc14(ch(0), U7::new(msb_cc).unwrap(), value).unwrap();
+        assert_eq!(changes, vec![
+            cc14(1, u14(64 << 7 | 5)),
+            cc14(7, u14(100 << 7)),
+            cc14(7, u14(100 << 7 | 1)),
+            ParameterChange::rpn(ch(0), u14(0), u14(2 << 7)),
+            ParameterChange::rpn(ch(0), u14(0), u14(2 << 7 | 1)),
+        ]);
+    }
+
+    #[test]
+    fn test_decoder_without_timeout_and_nrpn() {
+        let options = ParameterDecoderOptionsBuilder::default().msb_timeout(None::<Duration>).build().unwrap();
+        let mut decoder = ParameterChangeDecoder::new(options);
+        let changes = decode(&mut decoder, &[
+            (cc(4, 99, 1), 0),
+            (cc(4, 98, 8), 0),
+            (cc(4, 6, 3), 0),
+            (cc(4, 38, 4), 0),
+        ]);
+        assert_eq!(changes, vec![
+            ParameterChange::nrpn(ch(4), u14(1 << 7 | 8), u14(3 << 7)),
+            ParameterChange::nrpn(ch(4), u14(1 << 7 | 8), u14(3 << 7 | 4)),
+        ]);
+    }
+}
@@ -0,0 +1,173 @@
+// ---------------- [ File: basic-android-integration/src/parameter_change_encoder.rs ]
+crate::ix!();
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct ParameterEncoderOptions {
+    /// End every RPN/NRPN write with the null parameter (101/100 = 127/127)
+    /// so a stray data entry later can't change it.
+    null_terminate: bool,
+}
+
+impl Default for ParameterEncoderOptions {
+    fn default() -> Self {
+        Self {
+            null_terminate: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChannelEncoderState {
+    /// Parameter the receiver has selected, as far as we know.
+    selected: Option<(ParameterKind, U14)>,
+    /// Last value sent per parameter while it stayed selected.
+    selected_value: Option<U14>,
+    /// Last value sent per 14-bit controller.
+    cc14: [Option<U14>; 32],
+}
+
+/// Turns [`ParameterChange`]s into the shortest correct control change
+/// sequence, remembering what the receiver has already been told.
+///
+/// A 14-bit controller whose MSB is unchanged is updated with its LSB
+/// alone; a parameter is only reselected when the selection changed (or
+/// was nulled), and its data entry MSB is skipped when only the LSB moved.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterChangeEncoder {
+    options:  ParameterEncoderOptions,
+    channels: [ChannelEncoderState; 16],
+}
+
+impl ParameterChangeEncoder {
+    pub fn new(options: ParameterEncoderOptions) -> Self {
+        Self { options, channels: Default::default() }
+    }
+
+    /// Forget what the receiver knows; the next change is sent in full.
+    pub fn reset(&mut self) {
+        self.channels = Default::default();
+    }
+
+    pub fn encode(&mut self, change: &ParameterChange, out: &mut Vec<MidiMessage>) {
+        let channel = change.channel();
+        let state   = &mut self.channels[channel.get() as usize];
+        let cc      = |controller: u8, value: U7| MidiMessage::control_change(channel, U7::saturating(controller), value);
+        let (msb, lsb) = (change.value().msb(), change.value().lsb());
+
+        match change.kind() {
+            ParameterKind::Cc14 => {
+                let controller = change.number().lsb().get();
+                let previous   = state.cc14.get(controller as usize).copied().flatten();
+                if previous.map(U14::msb) != Some(msb) {
+                    out.push(cc(controller, msb));
+                }
+                out.push(cc(controller + 32, lsb));
+                if let Some(slot) = state.cc14.get_mut(controller as usize) {
+                    *slot = Some(change.value());
+                }
+            }
+            ParameterKind::Rpn | ParameterKind::Nrpn => {
+                let (msb_cc, lsb_cc) = match change.kind() {
+                    ParameterKind::Rpn => (CC_RPN_MSB, CC_RPN_LSB),
+                    _ => (CC_NRPN_MSB, CC_NRPN_LSB),
+                };
+                let parameter = (change.kind(), change.number());
+                if state.selected != Some(parameter) {
+                    out.push(cc(msb_cc, change.number().msb()));
+                    out.push(cc(lsb_cc, change.number().lsb()));
+                    state.selected       = Some(parameter);
+                    state.selected_value = None;
+                }
+                if state.selected_value.map(U14::msb) != Some(msb) {
+                    out.push(cc(CC_DATA_ENTRY_MSB, msb));
+                }
+                out.push(cc(CC_DATA_ENTRY_LSB, lsb));
+                state.selected_value = Some(change.value());
+
+                if self.options.null_terminate() {
+                    out.push(cc(CC_RPN_MSB, U7::MAX));
+                    out.push(cc(CC_RPN_LSB, U7::MAX));
+                    state.selected       = None;
+                    state.selected_value = None;
+                }
+            }
+        }
+    }
+
+    pub fn encode_all(&mut self, changes: &[ParameterChange]) -> Vec<MidiMessage> {
+        let mut out = Vec::new();
+        for change in changes {
+            self.encode(change, &mut out);
+        }
+        out
+    }
+
+    /// Encode and send in one call. On a short or failed send the receiver's
+    /// state is unknown, so the encoder forgets it.
+    pub fn send(&mut self, port: &MidiInputPort, changes: &[ParameterChange]) -> Result<usize, isize> {
+        let messages = self.encode_all(changes);
+        let expected: usize = messages.iter().map(MidiMessage::encoded_len).sum();
+        let result = port.send_messages(&messages);
+        if result != Ok(expected) {
+            debug!("parameter encoder reset after short or failed send: {:?}", result);
+            self.reset();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_parameter_change_encoder {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+    fn u14(n: u16) -> U14 { U14::new(n).unwrap() }
+    fn cc(c: u8, controller: u8, value: u8) -> MidiMessage { MidiMessage::control_change(ch(c), u7(controller), u7(value)) }
+
+    #[test]
+    fn test_encoder_is_minimal_and_round_trips() {
+        let changes = vec![
+            ParameterChange::cc14(ch(1), u7(1), u14(64 << 7 | 5)).unwrap(),
+            ParameterChange::cc14(ch(1), u7(1), u14(64 << 7 | 6)).unwrap(),
+            ParameterChange::rpn(ch(1), u14(0), u14(12 << 7)),
+            ParameterChange::nrpn(ch(1), u14(300), u14(1)),
+        ];
+        assert!(ParameterChange::cc14(ch(1), u7(6), u14(0)).is_err());
+
+        let mut encoder = ParameterChangeEncoder::default();
+        let messages = encoder.encode_all(&changes);
+        assert_eq!(messages, vec![
+            cc(1, 1, 64), cc(1, 33, 5),
+            cc(1, 33, 6),
+            cc(1, 101, 0), cc(1, 100, 0), cc(1, 6, 12), cc(1, 38, 0), cc(1, 101, 127), cc(1, 100, 127),
+            cc(1, 99, 2), cc(1, 98, 44), cc(1, 6, 0), cc(1, 38, 1), cc(1, 101, 127), cc(1, 100, 127),
+        ]);
+
+        let mut decoder = ParameterChangeDecoder::default();
+        let mut decoded: Vec<_> = messages.iter().flat_map(|m| {
+            let mut out = Vec::new();
+            decoder.observe(m, 0, |c| out.push(c));
+            out
+        }).collect();
+        decoder.flush(|c| decoded.push(c));
+        assert_eq!(decoded, changes);
+
+        let unterminated = ParameterEncoderOptionsBuilder::default().null_terminate(false).build().unwrap();
+        let mut encoder = ParameterChangeEncoder::new(unterminated);
+        encoder.encode_all(&changes[2..3]);
+        assert_eq!(encoder.encode_all(&[ParameterChange::rpn(ch(1), u14(0), u14(12 << 7 | 50))]), vec![cc(1, 38, 50)]);
+    }
+
+    #[test]
+    fn test_send_through_port() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let mut encoder = ParameterChangeEncoder::default();
+        encoder.send(&port, &[ParameterChange::cc14(ch(0), u7(7), u14(1)).unwrap()]).unwrap();
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![0xb0, 7, 0, 0xb0, 39, 1]);
+    }
+}
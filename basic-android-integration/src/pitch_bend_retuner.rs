@@ -0,0 +1,211 @@
+// ---------------- [ File: basic-android-integration/src/pitch_bend_retuner.rs ]
+crate::ix!();
+
+/// A note started by [`PitchBendRetuner::note_on`]: the key that was
+/// asked for and the channel and nearest equal-tempered note that play it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetunedNote {
+    pub key:     U7,
+    pub channel: Channel,
+    pub note:    U7,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RetunerChannel {
+    /// Bend last sent on this channel; `None` until configured or used.
+    bend:      Option<U14>,
+    /// Sounding notes with the stamp of their note-on.
+    notes:     Vec<(U7, u64)>,
+    last_used: u64,
+}
+
+/// Microtonal playback for synths without MTS: each key is played as the
+/// nearest equal-tempered note, bent on a channel of its own.
+///
+/// Pitch bend is per channel, so notes only share a channel when they need
+/// the same bend. Otherwise a free channel is taken (one already bent the
+/// right way first, then the least recently used); when all are busy the
+/// channel whose oldest note started first is stolen and its notes are
+/// ended.
+#[derive(Debug, Clone)]
+pub struct PitchBendRetuner {
+    tuning:     MidiTuning,
+    channels:   Vec<Channel>,
+    bend_range: u8,
+    state:      [RetunerChannel; 16],
+    clock:      u64,
+    encoder:    ParameterChangeEncoder,
+}
+
+impl PitchBendRetuner {
+    /// `bend_range` is in semitones; [`configure`](Self::configure) sets it
+    /// on the synth.
+    pub fn new(tuning: MidiTuning, channels: impl IntoIterator<Item = Channel>, bend_range: u8) -> Self {
+        Self {
+            tuning,
+            channels: channels.into_iter().collect(),
+            bend_range,
+            state:    Default::default(),
+            clock:    0,
+            encoder:  ParameterChangeEncoder::default(),
+        }
+    }
+
+    pub fn tuning(&self) -> &MidiTuning {
+        &self.tuning
+    }
+
+    /// Takes effect from the next note-on.
+    pub fn set_tuning(&mut self, tuning: MidiTuning) {
+        self.tuning = tuning;
+    }
+
+    /// Send the pitch bend range (RPN 0) and a centred bend on every channel.
+    pub fn configure(&mut self, port: &MidiInputPort) -> Result<usize, isize> {
+        info!("configuring pitch bend retuning on {} channels, range {}", self.channels.len(), self.bend_range);
+        let range   = U14::from_msb_lsb(U7::saturating(self.bend_range), U7::MIN);
+        let changes: Vec<_> = self.channels.iter().map(|&channel| ParameterChange::rpn(channel, U14::MIN, range)).collect();
+        let mut sent = self.encoder.send(port, &changes)?;
+        for &channel in &self.channels {
+            sent += port.send_message(&MidiMessage::pitch_bend(channel, U14::CENTER))?;
+            self.state[channel.get() as usize].bend = Some(U14::CENTER);
+        }
+        Ok(sent)
+    }
+
+    /// Messages that start `key`, appended to `out`; `None` if the key is
+    /// silent in the tuning or there are no channels.
+    pub fn note_on_messages(&mut self, key: U7, velocity: U7, out: &mut Vec<MidiMessage>) -> Option<RetunedNote> {
+        let pitch   = self.tuning.pitch(key)?;
+        let nearest = pitch.round().clamp(0.0, 127.0);
+        let note    = U7::saturating(nearest as u8);
+        let bend    = bend_for_semitones((pitch - nearest) as f32, self.bend_range);
+        let channel = self.allocate(note, bend, out)?;
+
+        self.clock += 1;
+        let state = &mut self.state[channel.get() as usize];
+        if state.bend != Some(bend) {
+            out.push(MidiMessage::pitch_bend(channel, bend));
+            state.bend = Some(bend);
+        }
+        out.push(MidiMessage::note_on(channel, note, velocity));
+        state.notes.push((note, self.clock));
+        state.last_used = self.clock;
+        trace!("key {} retuned to note {} bend {} on channel {}", key.get(), note.get(), bend.signed(), channel.get());
+        Some(RetunedNote { key, channel, note })
+    }
+
+    /// Messages that end `handle`; nothing if the note was already ended
+    /// by a steal.
+    pub fn note_off_messages(&mut self, handle: RetunedNote, velocity: U7, out: &mut Vec<MidiMessage>) {
+        let state = &mut self.state[handle.channel.get() as usize];
+        let Some(index) = state.notes.iter().position(|&(note, _)| note == handle.note) else {
+            return;
+        };
+        state.notes.remove(index);
+        self.clock     += 1;
+        state.last_used = self.clock;
+        out.push(MidiMessage::note_off(handle.channel, handle.note, velocity));
+    }
+
+    pub fn note_on(&mut self, port: &MidiInputPort, key: U7, velocity: U7) -> Result<Option<RetunedNote>, isize> {
+        let mut messages = Vec::new();
+        let handle = self.note_on_messages(key, velocity, &mut messages);
+        port.send_messages(&messages)?;
+        Ok(handle)
+    }
+
+    pub fn note_off(&mut self, port: &MidiInputPort, handle: RetunedNote, velocity: U7) -> Result<usize, isize> {
+        let mut messages = Vec::new();
+        self.note_off_messages(handle, velocity, &mut messages);
+        port.send_messages(&messages)
+    }
+
+    /// End every note the retuner started.
+    pub fn all_notes_off(&mut self, port: &MidiInputPort) -> Result<usize, isize> {
+        let mut messages = Vec::new();
+        for &channel in &self.channels {
+            let state = &mut self.state[channel.get() as usize];
+            messages.extend(state.notes.drain(..).map(|(note, _)| MidiMessage::note_off(channel, note, U7::MIN)));
+        }
+        port.send_messages(&messages)
+    }
+
+    fn allocate(&mut self, note: U7, bend: U14, out: &mut Vec<MidiMessage>) -> Option<Channel> {
+        let state  = &self.state;
+        let use_of = |channel: &Channel| &state[channel.get() as usize];
+
+        let shared = self.channels.iter().copied().find(|c| {
+            let state = use_of(c);
+            !state.notes.is_empty() && state.bend == Some(bend) && state.notes.iter().all(|&(n, _)| n != note)
+        });
+        let free = || {
+            let free = self.channels.iter().copied().filter(|c| use_of(c).notes.is_empty());
+            let mut same_bend = free.clone().filter(|c| use_of(c).bend == Some(bend));
+            same_bend.next().or_else(|| free.min_by_key(|c| use_of(c).last_used))
+        };
+        if let Some(channel) = shared.or_else(free) {
+            return Some(channel);
+        }
+
+        let stolen = self.channels.iter().copied().min_by_key(|c| use_of(c).notes.iter().map(|&(_, at)| at).min())?;
+        debug!("all retuning channels busy, stealing channel {}", stolen.get());
+        let state = &mut self.state[stolen.get() as usize];
+        out.extend(state.notes.drain(..).map(|(note, _)| MidiMessage::note_off(stolen, note, U7::MIN)));
+        Some(stolen)
+    }
+}
+
+#[cfg(test)]
+mod test_pitch_bend_retuner {
+    use super::*;
+
+    fn key(k: u8) -> U7 {
+        U7::saturating(k)
+    }
+
+    #[test]
+    fn test_channel_allocation() {
+        // Quarter tones: even keys in tune, odd keys a quarter tone sharp.
+        let tuning = MidiTuning::from_fn(|k| Some(if k % 2 == 0 { k as f64 } else { k as f64 - 0.5 }));
+        let channels = [1, 2].map(|c| Channel::new(c).unwrap());
+        let mut retuner = PitchBendRetuner::new(tuning, channels, 2);
+
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        retuner.configure(&port).unwrap();
+        virtual_device.input_port(0).clear_sent();
+
+        let velocity = U7::saturating(100);
+        let c  = retuner.note_on(&port, key(60), velocity).unwrap().unwrap();
+        let e  = retuner.note_on(&port, key(64), velocity).unwrap().unwrap();
+        let cq = retuner.note_on(&port, key(61), velocity).unwrap().unwrap();
+        assert_eq!((c.channel.get(), e.channel.get(), cq.channel.get()), (1, 1, 2));
+        // 60.5 rounds up to 61, bent down a quarter tone.
+        assert_eq!(cq.note.get(), 61);
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![
+            0x91, 60, 100,
+            0x91, 64, 100,
+            0xe2, 0x00, 0x30, 0x92, 61, 100,
+        ]);
+
+        // A third bend with both channels busy steals channel 1 (oldest note).
+        virtual_device.input_port(0).clear_sent();
+        let tuning = MidiTuning::from_fn(|k| Some(k as f64 + 0.25));
+        retuner.set_tuning(tuning);
+        let g = retuner.note_on(&port, key(67), velocity).unwrap().unwrap();
+        assert_eq!(g.channel.get(), 1);
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![
+            0x81, 60, 0, 0x81, 64, 0,
+            0xe1, 0x00, 0x48, 0x91, 67, 100,
+        ]);
+
+        // The stolen note's handle no longer sends anything.
+        virtual_device.input_port(0).clear_sent();
+        assert_eq!(retuner.note_off(&port, c, U7::MIN).unwrap(), 0);
+        retuner.note_off(&port, cq, U7::MIN).unwrap();
+        retuner.all_notes_off(&port).unwrap();
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![0x82, 61, 0, 0x81, 67, 0]);
+    }
+}
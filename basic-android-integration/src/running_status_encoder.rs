@@ -0,0 +1,162 @@
+// ---------------- [ File: basic-android-integration/src/running_status_encoder.rs ]
+crate::ix!();
+
+/// Serializes messages with running status: a channel message repeating the
+/// previous channel message's status byte is sent without it.
+///
+/// SysEx and system common messages cancel running status on the receiver,
+/// so they reset the encoder too; realtime messages leave it untouched.
+#[derive(Debug, Clone, Default)]
+pub struct RunningStatusEncoder {
+    running_status: Option<u8>,
+}
+
+impl RunningStatusEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the current running status so the next channel message carries
+    /// its status byte again. Call this whenever other bytes may have reached
+    /// the receiver behind the encoder's back.
+    pub fn reset(&mut self) {
+        self.running_status = None;
+    }
+
+    pub fn running_status(&self) -> Option<u8> {
+        self.running_status
+    }
+
+    pub fn encode(&mut self, message: &MidiMessage, out: &mut Vec<u8>) {
+        let status = message.status();
+
+        if message.channel().is_some() {
+            if self.running_status == Some(status) {
+                let start = out.len();
+                message.write_to(out);
+                out.remove(start);
+                return;
+            }
+            self.running_status = Some(status);
+        } else if !message.is_realtime() {
+            self.running_status = None;
+        }
+
+        message.write_to(out);
+    }
+
+    pub fn encode_all<'m>(&mut self, messages: impl IntoIterator<Item = &'m MidiMessage>, out: &mut Vec<u8>) {
+        for message in messages {
+            self.encode(message, out);
+        }
+    }
+}
+
+/// A `MidiInputPort` whose `send_message*` calls use running status.
+///
+/// The encoder assumes everything on this port goes through the wrapper; call
+/// [`reset`](Self::reset) after sending raw bytes through the port directly.
+pub struct RunningStatusMidiInputPort<'p, 'lib> {
+    port:    &'p MidiInputPort<'lib>,
+    encoder: RunningStatusEncoder,
+    scratch: Vec<u8>,
+}
+
+impl<'p, 'lib> RunningStatusMidiInputPort<'p, 'lib> {
+    pub fn new(port: &'p MidiInputPort<'lib>) -> Self {
+        Self {
+            port,
+            encoder: RunningStatusEncoder::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn port(&self) -> &'p MidiInputPort<'lib> {
+        self.port
+    }
+
+    pub fn reset(&mut self) {
+        self.encoder.reset();
+    }
+
+    pub fn send_message(&mut self, message: &MidiMessage) -> Result<usize, isize> {
+        self.send_messages(std::slice::from_ref(message))
+    }
+
+    pub fn send_messages(&mut self, messages: &[MidiMessage]) -> Result<usize, isize> {
+        self.scratch.clear();
+        self.encoder.encode_all(messages, &mut self.scratch);
+        let result = self.port.send(&self.scratch);
+        self.reset_unless_fully_sent(&result);
+        result
+    }
+
+    pub fn send_message_at(&mut self, message: &MidiMessage, timestamp: i64) -> Result<usize, isize> {
+        self.scratch.clear();
+        self.encoder.encode(message, &mut self.scratch);
+        let result = self.port.send_with_timestamp(&self.scratch, timestamp);
+        self.reset_unless_fully_sent(&result);
+        result
+    }
+
+    /// If the port took only part of the bytes, the receiver's running
+    /// status is unknown; start the next message with a status byte.
+    fn reset_unless_fully_sent(&mut self, result: &Result<usize, isize>) {
+        if *result != Ok(self.scratch.len()) {
+            debug!("running status reset after short or failed send: {:?}", result);
+            self.encoder.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_running_status_encoder {
+    use super::*;
+
+    fn cc(channel: u8, controller: u8, value: u8) -> MidiMessage {
+        MidiMessage::control_change(Channel::new(channel).unwrap(), U7::new(controller).unwrap(), U7::new(value).unwrap())
+    }
+
+    #[test]
+    fn test_dense_cc_round_trips_through_parser() {
+        let mut messages: Vec<_> = (0..64).map(|v| cc(0, 1, v)).collect();
+        messages.insert(10, MidiMessage::TimingClock);
+        messages.insert(20, MidiMessage::sysex(vec![0x7d, 1]).unwrap());
+        messages.insert(30, MidiMessage::song_select(U7::new(3).unwrap()));
+        messages.insert(40, cc(1, 1, 0));
+
+        let mut encoded = Vec::new();
+        RunningStatusEncoder::new().encode_all(&messages, &mut encoded);
+
+        let plain: usize = messages.iter().map(MidiMessage::encoded_len).sum();
+        // 65 channel messages, but status bytes are still needed for the first
+        // one, after the SysEx, after the song select, and on both sides of
+        // the switch to channel 2.
+        assert_eq!(encoded.len(), plain - (65 - 5));
+        assert_eq!(MidiStreamParser::parse_all(&encoded), messages);
+    }
+
+    #[test]
+    fn test_realtime_keeps_running_status() {
+        let mut encoder = RunningStatusEncoder::new();
+        let mut out     = Vec::new();
+        encoder.encode_all(&[cc(0, 7, 1), MidiMessage::TimingClock, cc(0, 7, 2)], &mut out);
+        assert_eq!(out, vec![0xb0, 7, 1, 0xf8, 7, 2]);
+    }
+
+    #[test]
+    fn test_wrapper_resets_after_partial_send() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let mut rs = RunningStatusMidiInputPort::new(&port);
+
+        virtual_device.input_port(0).script_sends([VirtualSendResult::AcceptUpTo(0), VirtualSendResult::AcceptUpTo(2)]);
+        rs.send_message(&cc(0, 7, 1)).unwrap();
+        rs.send_message(&cc(0, 7, 2)).unwrap();
+        rs.send_message(&cc(0, 7, 3)).unwrap();
+        rs.send_message(&cc(0, 7, 4)).unwrap();
+
+        assert_eq!(virtual_device.input_port(0).sent_bytes(), vec![0xb0, 7, 0xb0, 7, 3, 7, 4]);
+    }
+}
@@ -0,0 +1,59 @@
+// ---------------- [ File: basic-android-integration/src/scala_error.rs ]
+crate::ix!();
+
+/// Why a Scala `.scl` or `.kbm` file could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScalaErrorKind {
+    /// The file ended before `expected` values were read.
+    UnexpectedEof { expected: &'static str },
+    /// A line that should hold a number doesn't.
+    BadNumber(String),
+    /// A pitch that is neither cents (with a `.`) nor a positive ratio.
+    BadPitch(String),
+    /// A keyboard mapping field outside its range (e.g. a note above 127).
+    OutOfRange { what: &'static str, value: i64 },
+    /// The keyboard mapping leaves its reference note unmapped, so the
+    /// reference frequency can't be placed.
+    UnmappedReference(u8),
+}
+
+/// A [`ScalaErrorKind`] plus the 1-based line it was detected on (0 when it
+/// is about the file as a whole).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalaError {
+    pub line: usize,
+    pub kind: ScalaErrorKind,
+}
+
+impl ScalaError {
+    pub fn new(line: usize, kind: ScalaErrorKind) -> Self {
+        Self { line, kind }
+    }
+}
+
+impl std::fmt::Display for ScalaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ScalaErrorKind::UnexpectedEof { expected } => write!(f, "file ends before {}", expected),
+            ScalaErrorKind::BadNumber(text) => write!(f, "line {}: expected a number, found {:?}", self.line, text),
+            ScalaErrorKind::BadPitch(text) => write!(f, "line {}: {:?} is not a cents value or ratio", self.line, text),
+            ScalaErrorKind::OutOfRange { what, value } => write!(f, "line {}: {} {} is out of range", self.line, what, value),
+            ScalaErrorKind::UnmappedReference(note) => write!(f, "reference note {} is unmapped", note),
+        }
+    }
+}
+
+impl std::error::Error for ScalaError {}
+
+/// Non-comment lines of a Scala file with their 1-based line numbers.
+/// Comment lines start with `!`.
+pub(crate) fn scala_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().filter(|(_, line)| !line.starts_with('!')).map(|(index, line)| (index + 1, line))
+}
+
+/// The first whitespace-separated word of `line`, parsed; Scala allows
+/// trailing text after every value.
+pub(crate) fn scala_number<T: std::str::FromStr>(line: usize, text: &str) -> Result<T, ScalaError> {
+    let word = text.split_whitespace().next().unwrap_or("");
+    word.parse().map_err(|_| ScalaError::new(line, ScalaErrorKind::BadNumber(text.trim().to_string())))
+}
@@ -0,0 +1,141 @@
+// ---------------- [ File: basic-android-integration/src/scala_keyboard_mapping.rs ]
+crate::ix!();
+
+/// A Scala `.kbm` keyboard mapping: which scale degree each MIDI key plays
+/// and which key sounds at the reference frequency.
+///
+/// Keys from the middle note upwards walk through `mapping` (one entry per
+/// key, `None` for unmapped keys), each repetition of the pattern moving
+/// up by `octave_degree` scale degrees. A `map_size` of 0 (an empty
+/// `mapping`) maps consecutive keys to consecutive degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalaKeyboardMapping {
+    pub first_note:          u8,
+    pub last_note:           u8,
+    /// The key that plays degree 0 of the scale.
+    pub middle_note:         u8,
+    pub reference_note:      u8,
+    pub reference_frequency: f64,
+    /// Degrees per repetition of `mapping`; 0 means the scale's length.
+    pub octave_degree:       usize,
+    pub mapping:             Vec<Option<usize>>,
+}
+
+impl Default for ScalaKeyboardMapping {
+    /// Linear mapping over every key, degree 0 on middle C, A 440 on key 69.
+    fn default() -> Self {
+        Self {
+            first_note:          0,
+            last_note:           127,
+            middle_note:         60,
+            reference_note:      69,
+            reference_frequency: 440.0,
+            octave_degree:       0,
+            mapping:             Vec::new(),
+        }
+    }
+}
+
+impl ScalaKeyboardMapping {
+    pub fn parse(text: &str) -> Result<Self, ScalaError> {
+        let mut lines = scala_lines(text).filter(|(_, line)| !line.trim().is_empty());
+        let mut next  = |expected| lines.next().ok_or(ScalaError::new(0, ScalaErrorKind::UnexpectedEof { expected }));
+
+        let note = |(line, text): (usize, &str), what| {
+            let value: i64 = scala_number(line, text)?;
+            u8::try_from(value)
+                .ok()
+                .filter(|&note| note <= 127)
+                .ok_or(ScalaError::new(line, ScalaErrorKind::OutOfRange { what, value }))
+        };
+
+        let (line, text)        = next("the map size")?;
+        let map_size: usize     = scala_number(line, text)?;
+        let first_note          = note(next("the first note")?, "first note")?;
+        let last_note           = note(next("the last note")?, "last note")?;
+        let middle_note         = note(next("the middle note")?, "middle note")?;
+        let reference_note      = note(next("the reference note")?, "reference note")?;
+        let (line, text)        = next("the reference frequency")?;
+        let reference_frequency = scala_number(line, text)?;
+        let (line, text)        = next("the octave degree")?;
+        let octave_degree       = scala_number(line, text)?;
+
+        let mapping = (0..map_size)
+            .map(|_| {
+                let (line, text) = next("the last mapping entry")?;
+                match text.split_whitespace().next() {
+                    Some("x") => Ok(None),
+                    _ => scala_number(line, text).map(Some),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { first_note, last_note, middle_note, reference_note, reference_frequency, octave_degree, mapping })
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// The scale degree `key` plays, counted from the middle note and
+    /// running into further periods; `None` if the key is unmapped or
+    /// outside first..=last.
+    pub fn degree(&self, scale: &ScalaScale, key: u8) -> Option<i64> {
+        if !(self.first_note..=self.last_note).contains(&key) {
+            return None;
+        }
+        let offset = key as i64 - self.middle_note as i64;
+        if self.mapping.is_empty() {
+            return Some(offset);
+        }
+        let size          = self.mapping.len() as i64;
+        let octave_degree = if self.octave_degree == 0 { scale.len() } else { self.octave_degree } as i64;
+        let entry         = self.mapping[offset.rem_euclid(size) as usize]?;
+        Some(offset.div_euclid(size) * octave_degree + entry as i64)
+    }
+
+    /// Frequency in Hz of `key` under `scale`, `None` if it is unmapped.
+    pub fn frequency(&self, scale: &ScalaScale, key: u8) -> Result<Option<f64>, ScalaError> {
+        let reference = self
+            .degree(scale, self.reference_note)
+            .ok_or(ScalaError::new(0, ScalaErrorKind::UnmappedReference(self.reference_note)))?;
+        Ok(self.degree(scale, key).map(|degree| {
+            let cents = scale.degree_cents(degree) - scale.degree_cents(reference);
+            self.reference_frequency * (cents / 1200.0).exp2()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test_scala_keyboard_mapping {
+    use super::*;
+
+    #[test]
+    fn test_white_keys_mapping() {
+        // A 7-note scale on the white keys, black keys silent, A 432 on key 69.
+        let text = "! white.kbm\n12\n0\n127\n60\n69\n432.0\n7\n0\nx\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n";
+        let kbm  = ScalaKeyboardMapping::parse(text).unwrap();
+        assert_eq!(kbm.mapping.len(), 12);
+        assert_eq!(kbm.mapping[1], None);
+
+        let scale = ScalaScale::parse("pythagorean major\n7\n9/8\n81/64\n4/3\n3/2\n27/16\n243/128\n2/1\n").unwrap();
+        assert_eq!(kbm.degree(&scale, 61), None);
+        assert_eq!(kbm.degree(&scale, 72), Some(7));
+        assert_eq!(kbm.degree(&scale, 59), Some(-1));
+
+        assert!((kbm.frequency(&scale, 69).unwrap().unwrap() - 432.0).abs() < 1e-9);
+        // C below is a Pythagorean major sixth under A: 432 * 16/27.
+        assert!((kbm.frequency(&scale, 60).unwrap().unwrap() - 256.0).abs() < 1e-9);
+        assert_eq!(kbm.frequency(&scale, 70).unwrap(), None);
+    }
+
+    #[test]
+    fn test_bad_mappings() {
+        let err = ScalaKeyboardMapping::parse("0\n0\n128\n60\n69\n440.0\n0\n").unwrap_err();
+        assert_eq!(err, ScalaError::new(3, ScalaErrorKind::OutOfRange { what: "last note", value: 128 }));
+
+        let kbm = ScalaKeyboardMapping::parse("2\n0\n127\n60\n61\n440.0\n1\n0\nx\n").unwrap();
+        let err = kbm.frequency(&ScalaScale::equal_temperament(12), 60).unwrap_err();
+        assert_eq!(err.kind, ScalaErrorKind::UnmappedReference(61));
+    }
+}
@@ -0,0 +1,112 @@
+// ---------------- [ File: basic-android-integration/src/scala_scale.rs ]
+crate::ix!();
+
+/// One scale degree above the tonic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalaPitch {
+    Cents(f64),
+    Ratio(u64, u64),
+}
+
+impl ScalaPitch {
+    pub fn cents(&self) -> f64 {
+        match *self {
+            ScalaPitch::Cents(cents) => cents,
+            ScalaPitch::Ratio(num, den) => 1200.0 * (num as f64 / den as f64).log2(),
+        }
+    }
+
+    fn parse(line: usize, text: &str) -> Result<Self, ScalaError> {
+        let word = text.split_whitespace().next().unwrap_or("");
+        let bad  = || ScalaError::new(line, ScalaErrorKind::BadPitch(text.trim().to_string()));
+        if word.contains('.') {
+            return word.parse().map(ScalaPitch::Cents).map_err(|_| bad());
+        }
+        let (num, den) = word.split_once('/').unwrap_or((word, "1"));
+        match (num.parse::<u64>(), den.parse::<u64>()) {
+            (Ok(num), Ok(den)) if num > 0 && den > 0 => Ok(ScalaPitch::Ratio(num, den)),
+            _ => Err(bad()),
+        }
+    }
+}
+
+/// A Scala `.scl` scale: the degrees above the tonic, the last one being
+/// the period (usually the octave, 2/1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalaScale {
+    pub description: String,
+    pub pitches:     Vec<ScalaPitch>,
+}
+
+impl ScalaScale {
+    pub fn parse(text: &str) -> Result<Self, ScalaError> {
+        let mut lines = scala_lines(text);
+        let eof = |expected| ScalaError::new(0, ScalaErrorKind::UnexpectedEof { expected });
+
+        let (_, description) = lines.next().ok_or_else(|| eof("the description"))?;
+        let (line, count)    = lines.next().ok_or_else(|| eof("the note count"))?;
+        let count: usize     = scala_number(line, count)?;
+
+        let pitches = (0..count)
+            .map(|_| {
+                let (line, text) = lines.next().ok_or_else(|| eof("the last pitch"))?;
+                ScalaPitch::parse(line, text)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { description: description.trim().to_string(), pitches })
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// `n` equal divisions of the octave.
+    pub fn equal_temperament(n: usize) -> Self {
+        let pitches = (1..=n).map(|step| ScalaPitch::Cents(1200.0 * step as f64 / n as f64)).collect();
+        Self { description: format!("{}-EDO", n), pitches }
+    }
+
+    /// Degrees per period.
+    pub fn len(&self) -> usize {
+        self.pitches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pitches.is_empty()
+    }
+
+    /// Size of the period in cents (0 for an empty scale).
+    pub fn period_cents(&self) -> f64 {
+        self.pitches.last().map(ScalaPitch::cents).unwrap_or(0.0)
+    }
+
+    /// Cents above the tonic of `degree`; degrees past the scale (or below
+    /// 0) continue into further periods.
+    pub fn degree_cents(&self, degree: i64) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let (periods, step) = (degree.div_euclid(self.len() as i64), degree.rem_euclid(self.len() as i64));
+        let within = if step == 0 { 0.0 } else { self.pitches[step as usize - 1].cents() };
+        periods as f64 * self.period_cents() + within
+    }
+}
+
+#[cfg(test)]
+mod test_scala_scale {
+    use super::*;
+
+    #[test]
+    fn test_parse_scl() {
+        let text = "! meanquar.scl\n!\n1/4-comma meantone scale\n 4\n!\n 76.04900 cents\n 5/4\n 696.57843\n 2\n";
+        let scale = ScalaScale::parse(text).unwrap();
+        assert_eq!(scale.description, "1/4-comma meantone scale");
+        assert_eq!(scale.pitches[1], ScalaPitch::Ratio(5, 4));
+        assert_eq!(scale.pitches[3], ScalaPitch::Ratio(2, 1));
+        assert!((scale.degree_cents(2) - 386.3137).abs() < 1e-3);
+        assert!((scale.degree_cents(-1) - (696.57843 - 1200.0)).abs() < 1e-9);
+
+        assert_eq!(ScalaScale::parse("x\n2\n100.0\n").unwrap_err().kind, ScalaErrorKind::UnexpectedEof { expected: "the last pitch" });
+        assert_eq!(ScalaScale::parse("x\n1\n-3/2\n").unwrap_err(), ScalaError::new(3, ScalaErrorKind::BadPitch("-3/2".into())));
+    }
+}
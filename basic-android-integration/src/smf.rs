@@ -0,0 +1,303 @@
+// ---------------- [ File: basic-android-integration/src/smf.rs ]
+crate::ix!();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SmfFormat {
+    /// Format 0: one track holding every channel.
+    SingleTrack,
+    /// Format 1: simultaneous tracks sharing the first track's tempo map.
+    MultiTrack,
+    /// Format 2: independent single-track patterns.
+    MultiSong,
+}
+
+impl SmfFormat {
+    pub fn number(&self) -> u16 {
+        match self {
+            SmfFormat::SingleTrack => 0,
+            SmfFormat::MultiTrack  => 1,
+            SmfFormat::MultiSong   => 2,
+        }
+    }
+
+    pub fn from_number(format: u16) -> Option<Self> {
+        match format {
+            0 => Some(SmfFormat::SingleTrack),
+            1 => Some(SmfFormat::MultiTrack),
+            2 => Some(SmfFormat::MultiSong),
+            _ => None,
+        }
+    }
+}
+
+/// The header's division field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SmfTiming {
+    /// Ticks per quarter note (PPQ), 1..=0x7fff.
+    Metrical(u16),
+    /// Absolute time: `frames_per_second` is 24, 25, 29 (meaning 29.97 drop
+    /// frame) or 30, each frame split into `ticks_per_frame`.
+    Timecode { frames_per_second: u8, ticks_per_frame: u8 },
+}
+
+/// What happens at one point of a track.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SmfEventKind {
+    /// A channel message, or a complete SysEx (`F0 .. F7` in one event).
+    Midi(MidiMessage),
+    /// An `F0` event whose data does not end in `F7`: the first packet of a
+    /// SysEx continued by later `Escape` events.
+    SysExStart(Vec<u8>),
+    /// An `F7` event: a SysEx continuation packet or arbitrary bytes to send as-is.
+    Escape(Vec<u8>),
+    Meta(MetaEvent),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SmfEvent {
+    /// Ticks since the previous event on the same track.
+    pub delta: u32,
+    pub kind:  SmfEventKind,
+}
+
+impl SmfEvent {
+    pub fn new(delta: u32, kind: SmfEventKind) -> Self {
+        Self { delta, kind }
+    }
+
+    pub fn midi(delta: u32, message: MidiMessage) -> Self {
+        Self::new(delta, SmfEventKind::Midi(message))
+    }
+
+    pub fn meta(delta: u32, meta: MetaEvent) -> Self {
+        Self::new(delta, SmfEventKind::Meta(meta))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SmfTrack {
+    pub events: Vec<SmfEvent>,
+}
+
+impl SmfTrack {
+    pub fn new(events: Vec<SmfEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Events paired with their absolute tick.
+    pub fn absolute_events(&self) -> impl Iterator<Item = (u64, &SmfEvent)> + '_ {
+        self.events.iter().scan(0u64, |tick, event| {
+            *tick += event.delta as u64;
+            Some((*tick, event))
+        })
+    }
+
+    /// Tick of the last event.
+    pub fn length_ticks(&self) -> u64 {
+        self.events.iter().map(|e| e.delta as u64).sum()
+    }
+
+    /// The track name meta event, if any.
+    pub fn name(&self) -> Option<String> {
+        self.events.iter().find_map(|e| match &e.kind {
+            SmfEventKind::Meta(MetaEvent::Text { kind: MetaTextKind::TrackName, text }) => {
+                Some(String::from_utf8_lossy(text).into_owned())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// A Standard MIDI File.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Smf {
+    pub format: SmfFormat,
+    pub timing: SmfTiming,
+    pub tracks: Vec<SmfTrack>,
+}
+
+impl Smf {
+    pub fn new(format: SmfFormat, timing: SmfTiming) -> Self {
+        Self { format, timing, tracks: Vec::new() }
+    }
+
+    /// Parse a complete file image.
+    pub fn parse(bytes: &[u8]) -> Result<Self, SmfError> {
+        read_smf(bytes)
+    }
+
+    /// Serialize with running status; see [`write_smf`] for what fails.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SmfError> {
+        write_smf(self, &SmfWriteOptions::default())
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path.as_ref())?;
+        Ok(Self::parse(&bytes)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path.as_ref(), self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_smf {
+    use super::*;
+
+    fn ch(n: u8) -> Channel { Channel::new(n).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    fn sample() -> Smf {
+        let conductor = SmfTrack::new(vec![
+            SmfEvent::meta(0, MetaEvent::text(MetaTextKind::TrackName, "conductor")),
+            SmfEvent::meta(0, MetaEvent::Tempo(500_000)),
+            SmfEvent::meta(0, MetaEvent::TimeSignature { numerator: 6, denominator_log2: 3, clocks_per_click: 36, thirty_seconds_per_quarter: 8 }),
+            SmfEvent::meta(0, MetaEvent::KeySignature { sharps: -3, minor: true }),
+            SmfEvent::meta(0, MetaEvent::SmpteOffset { hours: 1, minutes: 0, seconds: 0, frames: 0, subframes: 0 }),
+            SmfEvent::meta(480, MetaEvent::text(MetaTextKind::Marker, "verse")),
+            SmfEvent::meta(0, MetaEvent::Tempo(400_000)),
+            SmfEvent::meta(0, MetaEvent::EndOfTrack),
+        ]);
+        let notes = SmfTrack::new(vec![
+            SmfEvent::meta(0, MetaEvent::text(MetaTextKind::Lyric, "la")),
+            SmfEvent::midi(0, MidiMessage::program_change(ch(0), u7(5))),
+            SmfEvent::midi(0, MidiMessage::note_on(ch(0), u7(60), u7(100))),
+            SmfEvent::midi(240, MidiMessage::note_on(ch(0), u7(64), u7(100))),
+            SmfEvent::midi(0, MidiMessage::sysex(vec![0x7e, 0x7f, 0x09, 0x01]).unwrap()),
+            SmfEvent::new(10, SmfEventKind::SysExStart(vec![0x43, 0x10])),
+            SmfEvent::new(10, SmfEventKind::Escape(vec![0x4c, 0xf7])),
+            SmfEvent::midi(220, MidiMessage::note_off(ch(0), u7(60), u7(0))),
+            SmfEvent::midi(0, MidiMessage::note_off(ch(0), u7(64), u7(0))),
+            SmfEvent::midi(0, MidiMessage::pitch_bend(ch(1), U14::MAX)),
+            SmfEvent::meta(0, MetaEvent::EndOfTrack),
+        ]);
+        Smf { format: SmfFormat::MultiTrack, timing: SmfTiming::Metrical(480), tracks: vec![conductor, notes] }
+    }
+
+    #[test]
+    fn test_round_trip_with_and_without_running_status() {
+        let smf = sample();
+        assert_eq!(Smf::parse(&smf.to_bytes().unwrap()).unwrap(), smf);
+
+        let plain = write_smf(&smf, &SmfWriteOptionsBuilder::default().running_status(false).build().unwrap()).unwrap();
+        assert!(plain.len() > smf.to_bytes().unwrap().len());
+        assert_eq!(Smf::parse(&plain).unwrap(), smf);
+
+        assert_eq!(smf.tracks[1].length_ticks(), 480);
+        assert_eq!(smf.tracks[0].name().as_deref(), Some("conductor"));
+    }
+
+    #[test]
+    fn test_json_round_trip_checks_ranges() {
+        let smf  = sample();
+        let json = serde_json::to_string(&smf).unwrap();
+        assert_eq!(serde_json::from_str::<Smf>(&json).unwrap(), smf);
+
+        let out_of_range = json.replacen("\"note\":60", "\"note\":128", 1);
+        assert!(serde_json::from_str::<Smf>(&out_of_range).is_err());
+
+        let sysex_status = json.replacen("[126,127,9,1]", "[126,247,9,1]", 1);
+        assert_ne!(sysex_status, json);
+        assert!(serde_json::from_str::<Smf>(&sysex_status).is_err());
+    }
+
+    #[test]
+    fn test_timecode_division_and_missing_end_of_track() {
+        let mut smf = Smf::new(SmfFormat::SingleTrack, SmfTiming::Timecode { frames_per_second: 25, ticks_per_frame: 40 });
+        smf.tracks.push(SmfTrack::new(vec![SmfEvent::midi(0, MidiMessage::note_on(ch(9), u7(36), u7(90)))]));
+
+        let parsed = Smf::parse(&smf.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.timing, smf.timing);
+        assert_eq!(parsed.tracks[0].events.last().unwrap().kind, SmfEventKind::Meta(MetaEvent::EndOfTrack));
+    }
+
+    #[test]
+    fn test_system_messages_are_written_as_escapes() {
+        let mut smf = Smf::new(SmfFormat::SingleTrack, SmfTiming::Metrical(96));
+        smf.tracks.push(SmfTrack::new(vec![
+            SmfEvent::midi(0, MidiMessage::note_on(ch(0), u7(60), u7(100))),
+            SmfEvent::midi(10, MidiMessage::TimingClock),
+            SmfEvent::midi(0, MidiMessage::SystemReset),
+            SmfEvent::midi(10, MidiMessage::note_on(ch(0), u7(60), u7(0))),
+            SmfEvent::meta(0, MetaEvent::EndOfTrack),
+        ]));
+
+        let parsed = Smf::parse(&smf.to_bytes().unwrap()).unwrap();
+        let kinds: Vec<_> = parsed.tracks[0].events.iter().map(|event| event.kind.clone()).collect();
+        assert_eq!(kinds[1], SmfEventKind::Escape(vec![0xf8]));
+        assert_eq!(kinds[2], SmfEventKind::Escape(vec![0xff]));
+        assert_eq!(kinds[3], SmfEventKind::Midi(MidiMessage::note_on(ch(0), u7(60), u7(0))));
+    }
+
+    #[test]
+    fn test_unwritable_values_are_rejected() {
+        let mut smf = Smf::new(SmfFormat::SingleTrack, SmfTiming::Metrical(0x8000));
+        assert_eq!(
+            smf.to_bytes().unwrap_err(),
+            SmfError::new(12, SmfErrorKind::ValueOutOfRange { what: "ticks per quarter note", value: 0x8000, max: 0x7fff })
+        );
+        smf.timing = SmfTiming::Timecode { frames_per_second: 60, ticks_per_frame: 4 };
+        assert_eq!(smf.to_bytes().unwrap_err(), SmfError::new(12, SmfErrorKind::InvalidFrameRate(60)));
+
+        smf.timing = SmfTiming::Metrical(96);
+        smf.tracks.push(SmfTrack::new(vec![SmfEvent::meta(0, MetaEvent::Tempo(0x0100_0000))]));
+        assert_eq!(
+            smf.to_bytes().unwrap_err(),
+            SmfError::new(23, SmfErrorKind::ValueOutOfRange { what: "tempo", value: 0x0100_0000, max: 0x00ff_ffff })
+        );
+        smf.tracks[0] = SmfTrack::new(vec![SmfEvent::midi(VLQ_MAX + 1, MidiMessage::TuneRequest)]);
+        assert!(matches!(smf.to_bytes().unwrap_err().kind, SmfErrorKind::ValueOutOfRange { what: "variable-length quantity", .. }));
+
+        smf.tracks = vec![SmfTrack::new(Vec::new()); 0x1_0000];
+        assert_eq!(
+            smf.to_bytes().unwrap_err(),
+            SmfError::new(10, SmfErrorKind::ValueOutOfRange { what: "track count", value: 0x1_0000, max: 0xffff })
+        );
+    }
+
+    #[test]
+    fn test_malformed_files_report_offsets() {
+        assert_eq!(Smf::parse(b"RIFF").unwrap_err(), SmfError::new(0, SmfErrorKind::UnexpectedEof));
+        assert_eq!(
+            Smf::parse(b"RIFF\0\0\0\x06\0\0\0\x01\x01\xe0").unwrap_err(),
+            SmfError::new(0, SmfErrorKind::MissingHeader)
+        );
+
+        let mut bytes = sample().to_bytes().unwrap();
+        // Claim the last track is longer than the file.
+        let last_mtrk = bytes.windows(4).rposition(|w| w == b"MTrk").unwrap();
+        bytes[last_mtrk + 7] = bytes[last_mtrk + 7].wrapping_add(10);
+        assert!(matches!(
+            Smf::parse(&bytes).unwrap_err(),
+            SmfError { offset, kind: SmfErrorKind::ChunkOverrun { .. } } if offset == last_mtrk + 4
+        ));
+
+        // A data byte as the very first event has no running status to use.
+        let mut smf = b"MThd\0\0\0\x06\0\0\0\x01\x01\xe0MTrk\0\0\0\x04\x00\x3c\x40\x00".to_vec();
+        assert_eq!(
+            Smf::parse(&smf).unwrap_err(),
+            SmfError::new(23, SmfErrorKind::MissingRunningStatus(0x3c))
+        );
+
+        // 0x80 is -128 as a negated frame rate, which has no positive form.
+        assert_eq!(
+            Smf::parse(b"MThd\0\0\0\x06\0\0\0\x01\x80\x00").unwrap_err(),
+            SmfError::new(12, SmfErrorKind::InvalidSmpteRate(-128))
+        );
+
+        // A tempo event must carry exactly three bytes.
+        smf.truncate(14);
+        smf.extend_from_slice(b"MTrk\0\0\0\x06\x00\xff\x51\x02\x07\xa1");
+        assert_eq!(
+            Smf::parse(&smf).unwrap_err(),
+            SmfError::new(23, SmfErrorKind::BadMetaLength { meta_type: 0x51, length: 2 })
+        );
+
+        // ... and a non-zero tempo, or time would stand still.
+        smf.truncate(14);
+        smf.extend_from_slice(b"MTrk\0\0\0\x07\x00\xff\x51\x03\x00\x00\x00");
+        assert_eq!(Smf::parse(&smf).unwrap_err(), SmfError::new(23, SmfErrorKind::ZeroTempo));
+    }
+}
@@ -0,0 +1,75 @@
+// ---------------- [ File: basic-android-integration/src/smf_error.rs ]
+crate::ix!();
+
+/// Why a Standard MIDI File could not be read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmfErrorKind {
+    UnexpectedEof,
+    /// The file does not start with an `MThd` chunk.
+    MissingHeader,
+    HeaderTooShort(u32),
+    UnsupportedFormat(u16),
+    /// An SMPTE division with a frame rate other than 24, 25, 29 or 30.
+    InvalidSmpteRate(i8),
+    /// A variable-length quantity longer than four bytes.
+    VlqTooLong,
+    /// A data byte where an event should start, with no running status in effect.
+    MissingRunningStatus(u8),
+    /// A status byte that cannot start an event inside a track (`F1`..`F6`, `F8`..`FE`).
+    InvalidStatus(u8),
+    /// A meta event whose payload has the wrong size for its type.
+    BadMetaLength { meta_type: u8, length: u32 },
+    /// A Set Tempo event of 0 microseconds per quarter note.
+    ZeroTempo,
+    /// A chunk claims to be longer than the bytes left in the file.
+    ChunkOverrun { declared: u32, available: usize },
+    /// An event runs past the end of its `MTrk` chunk.
+    TrackOverrun,
+    /// A value too large for the field it is written to.
+    ValueOutOfRange { what: &'static str, value: u64, max: u64 },
+    /// An SMPTE frame rate other than 24, 25, 29 or 30, on writing.
+    InvalidFrameRate(u8),
+}
+
+/// A [`SmfErrorKind`] plus the byte offset in the file where it was detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmfError {
+    pub offset: usize,
+    pub kind:   SmfErrorKind,
+}
+
+impl SmfError {
+    pub fn new(offset: usize, kind: SmfErrorKind) -> Self {
+        Self { offset, kind }
+    }
+}
+
+impl std::fmt::Display for SmfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid MIDI file at byte {:#x}: ", self.offset)?;
+        match &self.kind {
+            SmfErrorKind::UnexpectedEof                => write!(f, "unexpected end of file"),
+            SmfErrorKind::MissingHeader                => write!(f, "missing MThd header chunk"),
+            SmfErrorKind::HeaderTooShort(len)          => write!(f, "MThd chunk of {} bytes is shorter than 6", len),
+            SmfErrorKind::UnsupportedFormat(format)    => write!(f, "unsupported format {}", format),
+            SmfErrorKind::InvalidSmpteRate(rate)       => write!(f, "invalid SMPTE frame rate {}", rate),
+            SmfErrorKind::VlqTooLong                   => write!(f, "variable-length quantity longer than 4 bytes"),
+            SmfErrorKind::MissingRunningStatus(byte)   => write!(f, "data byte {:#04x} without running status", byte),
+            SmfErrorKind::InvalidStatus(byte)          => write!(f, "status byte {:#04x} is not allowed in a track", byte),
+            SmfErrorKind::BadMetaLength { meta_type, length } => {
+                write!(f, "meta event {:#04x} has invalid length {}", meta_type, length)
+            }
+            SmfErrorKind::ZeroTempo                    => write!(f, "tempo of 0 microseconds per quarter note"),
+            SmfErrorKind::ChunkOverrun { declared, available } => {
+                write!(f, "chunk declares {} bytes but only {} remain", declared, available)
+            }
+            SmfErrorKind::TrackOverrun                 => write!(f, "event runs past the end of its track"),
+            SmfErrorKind::ValueOutOfRange { what, value, max } => {
+                write!(f, "{} {} is larger than {}", what, value, max)
+            }
+            SmfErrorKind::InvalidFrameRate(rate)       => write!(f, "SMPTE frame rate {} cannot be written", rate),
+        }
+    }
+}
+
+impl std::error::Error for SmfError {}
@@ -0,0 +1,179 @@
+// ---------------- [ File: basic-android-integration/src/smf_meta_event.rs ]
+crate::ix!();
+
+/// The text-carrying meta events (`FF 01` .. `FF 09`). Text in MIDI files is
+/// not guaranteed to be UTF-8, so it is kept as bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaTextKind {
+    Text,
+    Copyright,
+    TrackName,
+    InstrumentName,
+    Lyric,
+    Marker,
+    CuePoint,
+    ProgramName,
+    DeviceName,
+}
+
+impl MetaTextKind {
+    pub fn meta_type(&self) -> u8 {
+        match self {
+            MetaTextKind::Text           => 0x01,
+            MetaTextKind::Copyright      => 0x02,
+            MetaTextKind::TrackName      => 0x03,
+            MetaTextKind::InstrumentName => 0x04,
+            MetaTextKind::Lyric          => 0x05,
+            MetaTextKind::Marker         => 0x06,
+            MetaTextKind::CuePoint       => 0x07,
+            MetaTextKind::ProgramName    => 0x08,
+            MetaTextKind::DeviceName     => 0x09,
+        }
+    }
+
+    pub fn from_meta_type(meta_type: u8) -> Option<Self> {
+        Some(match meta_type {
+            0x01 => MetaTextKind::Text,
+            0x02 => MetaTextKind::Copyright,
+            0x03 => MetaTextKind::TrackName,
+            0x04 => MetaTextKind::InstrumentName,
+            0x05 => MetaTextKind::Lyric,
+            0x06 => MetaTextKind::Marker,
+            0x07 => MetaTextKind::CuePoint,
+            0x08 => MetaTextKind::ProgramName,
+            0x09 => MetaTextKind::DeviceName,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaEvent {
+    SequenceNumber(u16),
+    Text { kind: MetaTextKind, text: Vec<u8> },
+    ChannelPrefix(Channel),
+    /// `FF 21`: the output port (cable) subsequent events are meant for.
+    Port(u8),
+    EndOfTrack,
+    /// Microseconds per quarter note.
+    Tempo(u32),
+    SmpteOffset { hours: u8, minutes: u8, seconds: u8, frames: u8, subframes: u8 },
+    /// `denominator_log2` is the power of two: 2 means a quarter note.
+    TimeSignature { numerator: u8, denominator_log2: u8, clocks_per_click: u8, thirty_seconds_per_quarter: u8 },
+    /// `sharps` is negative for flats.
+    KeySignature { sharps: i8, minor: bool },
+    SequencerSpecific(Vec<u8>),
+    Unknown { meta_type: u8, data: Vec<u8> },
+}
+
+impl MetaEvent {
+    /// 120 BPM, the tempo in effect before any tempo event.
+    pub const DEFAULT_TEMPO: u32 = 500_000;
+
+    pub fn text(kind: MetaTextKind, text: impl Into<String>) -> Self {
+        MetaEvent::Text { kind, text: text.into().into_bytes() }
+    }
+
+    pub fn meta_type(&self) -> u8 {
+        match self {
+            MetaEvent::SequenceNumber(_)      => 0x00,
+            MetaEvent::Text { kind, .. }      => kind.meta_type(),
+            MetaEvent::ChannelPrefix(_)       => 0x20,
+            MetaEvent::Port(_)                => 0x21,
+            MetaEvent::EndOfTrack             => 0x2f,
+            MetaEvent::Tempo(_)               => 0x51,
+            MetaEvent::SmpteOffset { .. }     => 0x54,
+            MetaEvent::TimeSignature { .. }   => 0x58,
+            MetaEvent::KeySignature { .. }    => 0x59,
+            MetaEvent::SequencerSpecific(_)   => 0x7f,
+            MetaEvent::Unknown { meta_type, .. } => *meta_type,
+        }
+    }
+
+    /// The payload following the length field. Fails for a tempo of 0 or
+    /// one above 24 bits. `offset` is only used for error reporting.
+    pub fn payload(&self, offset: usize) -> Result<Vec<u8>, SmfError> {
+        Ok(match self {
+            MetaEvent::SequenceNumber(n)  => n.to_be_bytes().to_vec(),
+            MetaEvent::Text { text, .. }  => text.clone(),
+            MetaEvent::ChannelPrefix(ch)  => vec![ch.get()],
+            MetaEvent::Port(port)         => vec![*port],
+            MetaEvent::EndOfTrack         => Vec::new(),
+            MetaEvent::Tempo(0)           => return Err(SmfError::new(offset, SmfErrorKind::ZeroTempo)),
+            MetaEvent::Tempo(us) if *us > 0x00ff_ffff => {
+                let kind = SmfErrorKind::ValueOutOfRange { what: "tempo", value: *us as u64, max: 0x00ff_ffff };
+                return Err(SmfError::new(offset, kind));
+            }
+            MetaEvent::Tempo(us)          => us.to_be_bytes()[1..].to_vec(),
+            MetaEvent::SmpteOffset { hours, minutes, seconds, frames, subframes } => {
+                vec![*hours, *minutes, *seconds, *frames, *subframes]
+            }
+            MetaEvent::TimeSignature { numerator, denominator_log2, clocks_per_click, thirty_seconds_per_quarter } => {
+                vec![*numerator, *denominator_log2, *clocks_per_click, *thirty_seconds_per_quarter]
+            }
+            MetaEvent::KeySignature { sharps, minor } => vec![*sharps as u8, *minor as u8],
+            MetaEvent::SequencerSpecific(data)    => data.clone(),
+            MetaEvent::Unknown { data, .. }       => data.clone(),
+        })
+    }
+
+    /// Decode a meta event payload. `offset` is only used for error reporting.
+    pub fn from_parts(meta_type: u8, data: &[u8], offset: usize) -> Result<Self, SmfError> {
+        let expect_len = |len: usize| {
+            if data.len() == len {
+                Ok(())
+            } else {
+                Err(SmfError::new(offset, SmfErrorKind::BadMetaLength { meta_type, length: data.len() as u32 }))
+            }
+        };
+
+        if let Some(kind) = MetaTextKind::from_meta_type(meta_type) {
+            return Ok(MetaEvent::Text { kind, text: data.to_vec() });
+        }
+
+        Ok(match meta_type {
+            0x00 => {
+                expect_len(2)?;
+                MetaEvent::SequenceNumber(u16::from_be_bytes([data[0], data[1]]))
+            }
+            0x20 => {
+                expect_len(1)?;
+                MetaEvent::ChannelPrefix(Channel::from_status(data[0]))
+            }
+            0x21 => {
+                expect_len(1)?;
+                MetaEvent::Port(data[0])
+            }
+            0x2f => {
+                expect_len(0)?;
+                MetaEvent::EndOfTrack
+            }
+            0x51 => {
+                expect_len(3)?;
+                match u32::from_be_bytes([0, data[0], data[1], data[2]]) {
+                    0 => return Err(SmfError::new(offset, SmfErrorKind::ZeroTempo)),
+                    us => MetaEvent::Tempo(us),
+                }
+            }
+            0x54 => {
+                expect_len(5)?;
+                MetaEvent::SmpteOffset { hours: data[0], minutes: data[1], seconds: data[2], frames: data[3], subframes: data[4] }
+            }
+            0x58 => {
+                expect_len(4)?;
+                MetaEvent::TimeSignature {
+                    numerator:                  data[0],
+                    denominator_log2:           data[1],
+                    clocks_per_click:           data[2],
+                    thirty_seconds_per_quarter: data[3],
+                }
+            }
+            0x59 => {
+                expect_len(2)?;
+                MetaEvent::KeySignature { sharps: data[0] as i8, minor: data[1] != 0 }
+            }
+            0x7f => MetaEvent::SequencerSpecific(data.to_vec()),
+            _    => MetaEvent::Unknown { meta_type, data: data.to_vec() },
+        })
+    }
+}
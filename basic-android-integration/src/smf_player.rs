@@ -0,0 +1,487 @@
+// ---------------- [ File: basic-android-integration/src/smf_player.rs ]
+crate::ix!();
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmfPlayerState {
+    Stopped,
+    Playing,
+    Paused,
+    /// Played past the last event with no loop set.
+    Finished,
+}
+
+/// One outgoing event, pre-serialized, with its song time.
+#[derive(Debug, Clone)]
+struct ScheduledSmfEvent {
+    tick:  u64,
+    nanos: i64,
+    bytes: Vec<u8>,
+}
+
+/// Plays a Standard MIDI File to a `MidiInputPort`.
+///
+/// The player does not own a thread. Call [`pump`](Self::pump) regularly
+/// (every few milliseconds); each call hands every event due within the
+/// lookahead window to `send_with_timestamp`, stamped with the exact time
+/// derived from the file's tempo map, so timing accuracy does not depend on
+/// how punctual the pump is.
+///
+/// Format 0 and 1 files have all tracks merged; format 2 files play one
+/// pattern at a time (see [`for_pattern`](Self::for_pattern)).
+pub struct SmfPlayer<C: MonotonicClock = SystemMonotonicClock> {
+    events:      Vec<ScheduledSmfEvent>,
+    tempo_map:   SmfTempoMap,
+    clock:       C,
+    state:       SmfPlayerState,
+    next_index:  usize,
+    /// Song time and wall time of the last (re)start; wall time advances at
+    /// song speed divided by `tempo_scale`.
+    anchor_song: i64,
+    anchor_wall: i64,
+    /// Song time while not playing.
+    position:    i64,
+    tempo_scale: f64,
+    loop_ticks:  Option<(u64, u64)>,
+    lookahead:   i64,
+    send_policy: SendAllPolicy,
+    /// Notes with a note-on handed to the port, keyed by (channel, note),
+    /// with the wall time of their scheduled note-off if any. A flush drops
+    /// note-offs that were still pending, so those notes count as sounding.
+    sounding:    BTreeMap<(u8, u8), Option<i64>>,
+}
+
+impl<C: MonotonicClock> SmfPlayer<C> {
+    pub const DEFAULT_LOOKAHEAD: Duration = Duration::from_millis(50);
+
+    /// Player for every track of a format 0 or 1 file (format 2: first pattern).
+    pub fn new(smf: &Smf, clock: C) -> Self {
+        match smf.format {
+            SmfFormat::MultiSong => Self::for_pattern(smf, 0, clock),
+            _ => Self::from_tracks(smf.timing, smf.tracks.iter().collect(), clock),
+        }
+    }
+
+    /// Player for one pattern (track) of a format 2 file.
+    pub fn for_pattern(smf: &Smf, pattern: usize, clock: C) -> Self {
+        Self::from_tracks(smf.timing, smf.tracks.get(pattern).into_iter().collect(), clock)
+    }
+
+    fn from_tracks(timing: SmfTiming, tracks: Vec<&SmfTrack>, clock: C) -> Self {
+        let tempo_map = SmfTempoMap::from_tracks(timing, tracks.iter().copied());
+
+        let mut events: Vec<(u64, usize, Vec<u8>)> = Vec::new();
+        for (track_index, track) in tracks.iter().enumerate() {
+            for (tick, event) in track.absolute_events() {
+                let bytes = match &event.kind {
+                    SmfEventKind::Midi(message) => message.to_bytes(),
+                    SmfEventKind::SysExStart(data) => std::iter::once(0xf0).chain(data.iter().copied()).collect(),
+                    SmfEventKind::Escape(data) => data.clone(),
+                    SmfEventKind::Meta(_) => continue,
+                };
+                events.push((tick, track_index, bytes));
+            }
+        }
+        // Stable merge: same tick keeps track order, then file order.
+        events.sort_by_key(|(tick, track, _)| (*tick, *track));
+
+        let events = events
+            .into_iter()
+            .map(|(tick, _, bytes)| ScheduledSmfEvent { tick, nanos: tempo_map.ticks_to_nanos(tick), bytes })
+            .collect();
+
+        Self {
+            events,
+            tempo_map,
+            clock,
+            state:       SmfPlayerState::Stopped,
+            next_index:  0,
+            anchor_song: 0,
+            anchor_wall: 0,
+            position:    0,
+            tempo_scale: 1.0,
+            loop_ticks:  None,
+            lookahead:   Self::DEFAULT_LOOKAHEAD.as_nanos() as i64,
+            send_policy: SendAllPolicy::default(),
+            sounding:    BTreeMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> SmfPlayerState {
+        self.state
+    }
+
+    pub fn tempo_map(&self) -> &SmfTempoMap {
+        &self.tempo_map
+    }
+
+    /// How far ahead of the clock events are handed to the port. Larger
+    /// values tolerate a lazier pump; smaller values make pause, seek and
+    /// tempo changes take effect sooner.
+    pub fn set_lookahead(&mut self, lookahead: Duration) {
+        self.lookahead = lookahead.as_nanos() as i64;
+    }
+
+    pub fn set_send_policy(&mut self, policy: SendAllPolicy) {
+        self.send_policy = policy;
+    }
+
+    /// Song time in nanoseconds.
+    pub fn position_nanos(&self) -> i64 {
+        match self.state {
+            SmfPlayerState::Playing => self.wall_to_song(self.clock.now_nanos()),
+            _ => self.position,
+        }
+    }
+
+    pub fn position_ticks(&self) -> u64 {
+        self.tempo_map.nanos_to_ticks(self.position_nanos())
+    }
+
+    /// Last event tick.
+    pub fn length_ticks(&self) -> u64 {
+        self.events.last().map(|e| e.tick).unwrap_or(0)
+    }
+
+    /// Playback speed relative to the file: 2.0 plays twice as fast. Takes
+    /// effect from the current position; events already inside the lookahead
+    /// window keep their old timestamps.
+    pub fn set_tempo_scale(&mut self, scale: f64) {
+        let scale = if scale.is_finite() && scale > 0.0 { scale } else { 1.0 };
+        if self.state == SmfPlayerState::Playing {
+            let now = self.clock.now_nanos();
+            self.anchor_song = self.wall_to_song(now);
+            self.anchor_wall = now;
+        }
+        self.tempo_scale = scale;
+    }
+
+    pub fn tempo_scale(&self) -> f64 {
+        self.tempo_scale
+    }
+
+    /// Loop between two ticks (end exclusive), or stop looping with `None`.
+    /// Empty or inverted ranges are ignored.
+    pub fn set_loop(&mut self, range: Option<std::ops::Range<u64>>) {
+        self.loop_ticks = range.filter(|r| r.start < r.end).map(|r| (r.start, r.end));
+    }
+
+    /// Start or resume from the current position.
+    pub fn play(&mut self) {
+        if self.state == SmfPlayerState::Playing {
+            return;
+        }
+        if self.state == SmfPlayerState::Finished {
+            self.position = 0;
+        }
+        info!("SmfPlayer: play from {} ns", self.position);
+        self.anchor_song = self.position;
+        self.anchor_wall = self.clock.now_nanos();
+        self.next_index  = self.first_event_at_or_after(self.position);
+        self.state       = SmfPlayerState::Playing;
+    }
+
+    /// Stop where we are: drop anything already scheduled and release
+    /// sounding notes. `play` resumes from here.
+    pub fn pause(&mut self, port: &MidiInputPort) -> Result<(), SendAllError> {
+        if self.state != SmfPlayerState::Playing {
+            return Ok(());
+        }
+        self.position = self.wall_to_song(self.clock.now_nanos());
+        self.state    = SmfPlayerState::Paused;
+        info!("SmfPlayer: paused at {} ns", self.position);
+        self.silence(port)
+    }
+
+    /// Stop, rewind to the start, and send all-notes-off on every channel.
+    pub fn stop(&mut self, port: &MidiInputPort) -> Result<(), SendAllError> {
+        info!("SmfPlayer: stop");
+        self.state      = SmfPlayerState::Stopped;
+        self.position   = 0;
+        self.next_index = 0;
+        self.silence(port)
+    }
+
+    /// Jump to `tick`. While playing, scheduled events are dropped and
+    /// sounding notes released first.
+    pub fn seek(&mut self, tick: u64, port: &MidiInputPort) -> Result<(), SendAllError> {
+        let song = self.tempo_map.ticks_to_nanos(tick);
+        debug!("SmfPlayer: seek to tick {} ({} ns)", tick, song);
+        if self.state == SmfPlayerState::Playing {
+            self.silence(port)?;
+            self.anchor_song = song;
+            self.anchor_wall = self.clock.now_nanos();
+            self.next_index  = self.first_event_at_or_after(song);
+        } else {
+            self.position = song;
+            if self.state == SmfPlayerState::Finished {
+                self.state = SmfPlayerState::Stopped;
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedule everything due before `now + lookahead`. Returns the number
+    /// of events handed to the port.
+    pub fn pump(&mut self, port: &MidiInputPort) -> Result<usize, SendAllError> {
+        if self.state != SmfPlayerState::Playing {
+            return Ok(0);
+        }
+
+        let now     = self.clock.now_nanos();
+        let horizon = now + self.lookahead;
+        let mut sent = 0;
+
+        self.sounding.retain(|_, off| off.is_none_or(|off| off > now));
+
+        loop {
+            if let Some((loop_start, loop_end)) = self.loop_ticks {
+                let at_loop_end = self
+                    .events
+                    .get(self.next_index)
+                    .is_none_or(|e| e.tick >= loop_end);
+                if at_loop_end {
+                    let (start_song, end_song) = (self.tempo_map.ticks_to_nanos(loop_start), self.tempo_map.ticks_to_nanos(loop_end));
+                    if end_song <= start_song {
+                        // Looping back would never move time forward.
+                        warn!("SmfPlayer: loop {}..{} spans no time, looping disabled", loop_start, loop_end);
+                        self.loop_ticks = None;
+                        continue;
+                    }
+                    let end_wall = self.song_to_wall(end_song);
+                    if end_wall > horizon {
+                        break;
+                    }
+                    self.release_sounding_notes(port, Some(end_wall), end_wall)?;
+                    trace!("SmfPlayer: loop back to tick {}", loop_start);
+                    self.anchor_song = start_song;
+                    self.anchor_wall = end_wall;
+                    self.next_index  = self.first_event_at_or_after(self.anchor_song);
+                    continue;
+                }
+            }
+
+            let Some(event) = self.events.get(self.next_index) else {
+                let end_wall = self.song_to_wall(self.events.last().map(|e| e.nanos).unwrap_or(0));
+                if now >= end_wall {
+                    info!("SmfPlayer: finished");
+                    self.state    = SmfPlayerState::Finished;
+                    self.position = self.anchor_song.max(self.wall_to_song(end_wall));
+                }
+                break;
+            };
+
+            let wall = self.song_to_wall(event.nanos);
+            if wall > horizon {
+                break;
+            }
+
+            port.send_all_at(&event.bytes, Some(wall.max(now)), &self.send_policy)?;
+            track_sounding_notes(&mut self.sounding, &event.bytes, wall.max(now));
+            self.next_index += 1;
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+
+    fn silence(&mut self, port: &MidiInputPort) -> Result<(), SendAllError> {
+        // Discard whatever is still waiting in the port's schedule, then
+        // release notes immediately.
+        if let Err(status) = port.send_flush() {
+            warn!("SmfPlayer: send_flush failed with {:?}", status);
+        }
+        self.release_sounding_notes(port, None, self.clock.now_nanos())?;
+
+        let mut all_notes_off = Vec::with_capacity(16 * 3);
+        for channel in Channel::all() {
+            MidiMessage::control_change(channel, U7::new(123).unwrap(), U7::MIN).write_to(&mut all_notes_off);
+        }
+        port.send_all_at(&all_notes_off, None, &self.send_policy)?;
+        Ok(())
+    }
+
+    /// Note-offs for every note still sounding at wall time `as_of`.
+    fn release_sounding_notes(&mut self, port: &MidiInputPort, at: Option<i64>, as_of: i64) -> Result<(), SendAllError> {
+        let bytes: Vec<u8> = std::mem::take(&mut self.sounding)
+            .into_iter()
+            .filter(|(_, off)| off.is_none_or(|off| off > as_of))
+            .flat_map(|((channel, note), _)| [0x80 | channel, note, 0])
+            .collect();
+        if !bytes.is_empty() {
+            port.send_all_at(&bytes, at, &self.send_policy)?;
+        }
+        Ok(())
+    }
+
+    fn first_event_at_or_after(&self, song_nanos: i64) -> usize {
+        self.events.partition_point(|e| e.nanos < song_nanos)
+    }
+
+    fn song_to_wall(&self, song: i64) -> i64 {
+        self.anchor_wall + ((song - self.anchor_song) as f64 / self.tempo_scale) as i64
+    }
+
+    fn wall_to_song(&self, wall: i64) -> i64 {
+        self.anchor_song + ((wall - self.anchor_wall) as f64 * self.tempo_scale) as i64
+    }
+}
+
+fn track_sounding_notes(sounding: &mut BTreeMap<(u8, u8), Option<i64>>, bytes: &[u8], wall: i64) {
+    if let [status, note, velocity] = *bytes {
+        match status & 0xf0 {
+            0x90 if velocity > 0 => {
+                sounding.insert((status & 0x0f, note), None);
+            }
+            0x80 | 0x90 => {
+                if let Some(off) = sounding.get_mut(&(status & 0x0f, note)) {
+                    *off = Some(wall);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_smf_player {
+    use super::*;
+
+    const MS: i64 = 1_000_000;
+
+    fn ch0() -> Channel { Channel::new(0).unwrap() }
+    fn u7(n: u8) -> U7 { U7::new(n).unwrap() }
+
+    /// 96 PPQ, 120 BPM then 60 BPM from tick 192. Notes every quarter.
+    fn song() -> Smf {
+        let tempo = SmfTrack::new(vec![
+            SmfEvent::meta(0, MetaEvent::Tempo(500_000)),
+            SmfEvent::meta(192, MetaEvent::Tempo(1_000_000)),
+        ]);
+        let notes = SmfTrack::new(vec![
+            SmfEvent::midi(0, MidiMessage::note_on(ch0(), u7(60), u7(100))),
+            SmfEvent::midi(96, MidiMessage::note_off(ch0(), u7(60), u7(0))),
+            SmfEvent::midi(96, MidiMessage::note_on(ch0(), u7(62), u7(100))),
+            SmfEvent::midi(96, MidiMessage::note_off(ch0(), u7(62), u7(0))),
+        ]);
+        Smf { format: SmfFormat::MultiTrack, timing: SmfTiming::Metrical(96), tracks: vec![tempo, notes] }
+    }
+
+    fn sent(virtual_device: &VirtualMidiDevice) -> Vec<(Vec<u8>, Option<i64>)> {
+        virtual_device.input_port(0).sent().into_iter().map(|p| (p.bytes, p.timestamp)).collect()
+    }
+
+    #[test]
+    fn test_tempo_map_timestamps_and_lookahead() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(1_000 * MS);
+
+        let mut player = SmfPlayer::new(&song(), clock.clone());
+        player.set_lookahead(Duration::from_millis(100));
+        player.play();
+
+        assert_eq!(player.pump(&port).unwrap(), 1);
+        clock.advance(450 * MS);
+        assert_eq!(player.pump(&port).unwrap(), 1);
+        clock.advance(500 * MS);
+        assert_eq!(player.pump(&port).unwrap(), 1);
+        clock.advance(1_000 * MS);
+        assert_eq!(player.pump(&port).unwrap(), 1);
+        assert_eq!(player.state(), SmfPlayerState::Playing);
+        clock.advance(100 * MS);
+        assert_eq!(player.pump(&port).unwrap(), 0);
+        assert_eq!(player.state(), SmfPlayerState::Finished);
+
+        assert_eq!(
+            sent(&virtual_device),
+            vec![
+                (vec![0x90, 60, 100], Some(1_000 * MS)),
+                (vec![0x80, 60, 0], Some(1_500 * MS)),
+                (vec![0x90, 62, 100], Some(2_000 * MS)),
+                // 60 BPM from here on
+                (vec![0x80, 62, 0], Some(3_000 * MS)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tempo_scale_and_loop() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(0);
+
+        let mut player = SmfPlayer::new(&song(), clock.clone());
+        player.set_tempo_scale(2.0);
+        player.set_loop(Some(0..192));
+        player.set_lookahead(Duration::from_millis(600));
+        player.play();
+        player.pump(&port).unwrap();
+
+        assert_eq!(
+            sent(&virtual_device),
+            vec![
+                (vec![0x90, 60, 100], Some(0)),
+                (vec![0x80, 60, 0], Some(250 * MS)),
+                // first bar again after 192 ticks = 1 s of song time = 500 ms
+                (vec![0x90, 60, 100], Some(500 * MS)),
+            ]
+        );
+        assert_eq!(player.state(), SmfPlayerState::Playing);
+    }
+
+    #[test]
+    fn test_loop_spanning_no_time_is_dropped() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(0);
+
+        // At 32767 PPQ and 1 us per quarter a tick is a fraction of a
+        // nanosecond, so a one-tick loop spans no time at all.
+        let mut smf = song();
+        smf.timing = SmfTiming::Metrical(0x7fff);
+        smf.tracks[0] = SmfTrack::new(vec![SmfEvent::meta(0, MetaEvent::Tempo(1))]);
+        let mut player = SmfPlayer::new(&smf, clock.clone());
+        player.set_loop(Some(0..1));
+        player.play();
+        player.pump(&port).unwrap();
+
+        assert_eq!(sent(&virtual_device)[0], (vec![0x90, 60, 100], Some(0)));
+    }
+
+    #[test]
+    fn test_pause_releases_notes_and_resume_resends_flushed_events() {
+        let virtual_device = VirtualMidiDevice::new(1, 0);
+        let device = virtual_device.open();
+        let port   = device.open_input_port(0).unwrap();
+        let clock  = VirtualMonotonicClock::starting_at(0);
+
+        let mut player = SmfPlayer::new(&song(), clock.clone());
+        player.set_lookahead(Duration::from_millis(600));
+        player.play();
+        player.pump(&port).unwrap();
+
+        clock.advance(100 * MS);
+        player.pause(&port).unwrap();
+        assert_eq!(virtual_device.input_port(0).flush_count(), 1);
+        assert_eq!(player.position_ticks(), 19);
+
+        let packets = sent(&virtual_device);
+        // note on, its note off (scheduled, then flushed), targeted release, all-notes-off
+        assert_eq!(packets[2], (vec![0x80, 60, 0], None));
+        assert_eq!(packets[3].0.len(), 48);
+        assert_eq!(&packets[3].0[..3], &[0xb0, 123, 0]);
+
+        virtual_device.input_port(0).clear_sent();
+        clock.advance(1_000 * MS);
+        player.play();
+        player.pump(&port).unwrap();
+        // the flushed note-off at tick 96 is sent again, relative to the resume
+        assert_eq!(sent(&virtual_device)[0], (vec![0x80, 60, 0], Some(1_100 * MS + 400 * MS)));
+    }
+}
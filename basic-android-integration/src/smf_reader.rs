@@ -0,0 +1,168 @@
+// ---------------- [ File: basic-android-integration/src/smf_reader.rs ]
+crate::ix!();
+
+/// Parse a Standard MIDI File image. Chunks other than `MThd`/`MTrk` are
+/// skipped, as the spec requires; a track count that disagrees with the
+/// header is tolerated with a warning.
+pub fn read_smf(bytes: &[u8]) -> Result<Smf, SmfError> {
+    let mut pos = 0;
+
+    let (id, header) = read_chunk(bytes, &mut pos)?;
+    if id != *b"MThd" {
+        return Err(SmfError::new(0, SmfErrorKind::MissingHeader));
+    }
+    if header.len() < 6 {
+        return Err(SmfError::new(4, SmfErrorKind::HeaderTooShort(header.len() as u32)));
+    }
+
+    let format_number = u16::from_be_bytes([header[0], header[1]]);
+    let format = SmfFormat::from_number(format_number)
+        .ok_or_else(|| SmfError::new(8, SmfErrorKind::UnsupportedFormat(format_number)))?;
+    let declared_tracks = u16::from_be_bytes([header[2], header[3]]);
+    let timing = read_division([header[4], header[5]])?;
+
+    debug!("SMF header: format={:?} tracks={} timing={:?}", format, declared_tracks, timing);
+
+    let mut tracks = Vec::with_capacity(declared_tracks as usize);
+    while pos < bytes.len() {
+        let chunk_start = pos;
+        let (id, body) = read_chunk(bytes, &mut pos)?;
+        if id == *b"MTrk" {
+            tracks.push(read_track(body, chunk_start + 8)?);
+        } else {
+            debug!("Skipping unknown chunk {:?} at {:#x}", String::from_utf8_lossy(&id), chunk_start);
+        }
+    }
+
+    if tracks.len() != declared_tracks as usize {
+        warn!("SMF header declares {} tracks but {} were found", declared_tracks, tracks.len());
+    }
+
+    Ok(Smf { format, timing, tracks })
+}
+
+fn read_division(division: [u8; 2]) -> Result<SmfTiming, SmfError> {
+    if division[0] & 0x80 == 0 {
+        return Ok(SmfTiming::Metrical(u16::from_be_bytes(division)));
+    }
+    // The rate is stored negated (two's complement); 0x80 has no positive
+    // counterpart, so match the raw byte rather than negating.
+    let frames_per_second = match division[0] {
+        0xe8 => 24,
+        0xe7 => 25,
+        0xe3 => 29,
+        0xe2 => 30,
+        byte => return Err(SmfError::new(12, SmfErrorKind::InvalidSmpteRate(byte as i8))),
+    };
+    Ok(SmfTiming::Timecode { frames_per_second, ticks_per_frame: division[1] })
+}
+
+fn read_chunk<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<([u8; 4], &'a [u8]), SmfError> {
+    let start = *pos;
+    let header = bytes
+        .get(start..start + 8)
+        .ok_or_else(|| SmfError::new(start, SmfErrorKind::UnexpectedEof))?;
+    let id: [u8; 4] = header[..4].try_into().unwrap();
+    let len = u32::from_be_bytes(header[4..8].try_into().unwrap());
+
+    let body_start = start + 8;
+    let available  = bytes.len() - body_start;
+    if len as usize > available {
+        return Err(SmfError::new(start + 4, SmfErrorKind::ChunkOverrun { declared: len, available }));
+    }
+    *pos = body_start + len as usize;
+    Ok((id, &bytes[body_start..*pos]))
+}
+
+/// `base` is the file offset of `body[0]`, so errors point into the file.
+fn read_track(body: &[u8], base: usize) -> Result<SmfTrack, SmfError> {
+    let mut events  = Vec::new();
+    let mut pos     = 0;
+    let mut running: Option<u8> = None;
+
+    let overrun = |at: usize| SmfError::new(base + at, SmfErrorKind::TrackOverrun);
+    let rebase  = |e: SmfError| match e.kind {
+        SmfErrorKind::UnexpectedEof => SmfError::new(base + e.offset, SmfErrorKind::TrackOverrun),
+        kind => SmfError::new(base + e.offset, kind),
+    };
+
+    while pos < body.len() {
+        let delta = read_vlq(body, &mut pos).map_err(rebase)?;
+
+        let event_start = pos;
+        let first = *body.get(pos).ok_or_else(|| overrun(pos))?;
+
+        let kind = match first {
+            0xff => {
+                pos += 1;
+                let meta_type = *body.get(pos).ok_or_else(|| overrun(pos))?;
+                pos += 1;
+                let len  = read_vlq(body, &mut pos).map_err(rebase)? as usize;
+                let data = body.get(pos..pos + len).ok_or_else(|| overrun(pos))?;
+                pos += len;
+                running = None;
+                let meta = MetaEvent::from_parts(meta_type, data, base + event_start)?;
+                let end  = meta == MetaEvent::EndOfTrack;
+                events.push(SmfEvent::meta(delta, meta));
+                if end {
+                    if pos < body.len() {
+                        debug!("Ignoring {} bytes after End of Track", body.len() - pos);
+                    }
+                    break;
+                }
+                continue;
+            }
+            0xf0 | 0xf7 => {
+                pos += 1;
+                let len  = read_vlq(body, &mut pos).map_err(rebase)? as usize;
+                let data = body.get(pos..pos + len).ok_or_else(|| overrun(pos))?;
+                pos += len;
+                running = None;
+                if first == 0xf7 {
+                    SmfEventKind::Escape(data.to_vec())
+                } else {
+                    match data.split_last() {
+                        Some((0xf7, payload)) if payload.iter().all(|b| *b < 0x80) => {
+                            SmfEventKind::Midi(MidiMessage::SysEx(SysExData::from_data_bytes(payload.to_vec())))
+                        }
+                        _ => SmfEventKind::SysExStart(data.to_vec()),
+                    }
+                }
+            }
+            0x80..=0xef => {
+                pos += 1;
+                running = Some(first);
+                SmfEventKind::Midi(read_channel_message(body, &mut pos, first, base)?)
+            }
+            0x00..=0x7f => {
+                let status = running
+                    .ok_or_else(|| SmfError::new(base + pos, SmfErrorKind::MissingRunningStatus(first)))?;
+                SmfEventKind::Midi(read_channel_message(body, &mut pos, status, base)?)
+            }
+            _ => return Err(SmfError::new(base + pos, SmfErrorKind::InvalidStatus(first))),
+        };
+
+        events.push(SmfEvent::new(delta, kind));
+    }
+
+    Ok(SmfTrack { events })
+}
+
+fn read_channel_message(body: &[u8], pos: &mut usize, status: u8, base: usize) -> Result<MidiMessage, SmfError> {
+    let needed = status_message_len(status).unwrap_or(1) - 1;
+    let data = body
+        .get(*pos..*pos + needed)
+        .ok_or_else(|| SmfError::new(base + *pos, SmfErrorKind::TrackOverrun))?;
+
+    if let Some(bad) = data.iter().position(|b| *b >= 0x80) {
+        return Err(SmfError::new(base + *pos + bad, SmfErrorKind::InvalidStatus(data[bad])));
+    }
+    *pos += needed;
+
+    let mut parser = MidiStreamParser::new();
+    let mut message = parser.push(status);
+    for byte in data {
+        message = parser.push(*byte);
+    }
+    Ok(message.expect("a status byte followed by its data bytes always yields a message"))
+}
@@ -0,0 +1,351 @@
+// ---------------- [ File: basic-android-integration/src/smf_recorder.rs ]
+crate::ix!();
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct SmfRecordOptions {
+    /// Ticks per quarter note of the written file.
+    ppq:              u16,
+    /// Microseconds per quarter note used to turn timestamps into ticks.
+    tempo:            u32,
+    /// Write a format 1 file with one track per channel instead of format 0.
+    split_by_channel: bool,
+    /// Timestamp (AMidi nanoseconds) recording starts at; `None` records
+    /// from the first event.
+    punch_in:         Option<i64>,
+    /// Timestamp recording stops at; events at or after it are ignored,
+    /// and notes still sounding get a note-off there.
+    punch_out:        Option<i64>,
+    /// How far before `punch_in` events are still kept, so a note played a
+    /// little early isn't lost.
+    pre_roll:         Duration,
+    /// Keep realtime messages (clock, start/stop, active sensing).
+    keep_realtime:    bool,
+}
+
+impl Default for SmfRecordOptions {
+    fn default() -> Self {
+        Self {
+            ppq:              480,
+            tempo:            MetaEvent::DEFAULT_TEMPO,
+            split_by_channel: false,
+            punch_in:         None,
+            punch_out:        None,
+            pre_roll:         Duration::from_millis(500),
+            keep_realtime:    false,
+        }
+    }
+}
+
+/// Records timestamped MIDI into an [`Smf`].
+///
+/// Feed it what a `MidiOutputPort` delivers, either directly with
+/// [`poll`](Self::poll) or packet by packet with [`record`](Self::record),
+/// and call [`finish`](Self::finish) to get the file. Bytes go through a
+/// [`MidiStreamParser`], so running status and SysEx split across packets
+/// are handled; a message is stamped with the packet it completes in.
+///
+/// Tick zero is the punch-in time, or the earliest pre-roll event if one
+/// came before it, or the first event when there is no punch-in.
+#[derive(Debug)]
+pub struct SmfRecorder {
+    options:  SmfRecordOptions,
+    parser:   MidiStreamParser,
+    /// Events before punch-in, trimmed to the pre-roll window.
+    pre_roll: VecDeque<(i64, MidiMessage)>,
+    events:   Vec<(i64, MidiMessage)>,
+}
+
+impl SmfRecorder {
+    pub fn new(options: SmfRecordOptions) -> Self {
+        Self {
+            options,
+            parser:   MidiStreamParser::new(),
+            pre_roll: VecDeque::new(),
+            events:   Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &SmfRecordOptions {
+        &self.options
+    }
+
+    /// Messages recorded so far, not counting pre-roll still on hold.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Record everything pending on `port`. Returns the number of data
+    /// packets read.
+    pub fn poll(&mut self, port: &MidiOutputPort) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, timestamp } => self.record(bytes, timestamp),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+
+    /// Record one packet of raw bytes received at `timestamp`.
+    pub fn record(&mut self, bytes: &[u8], timestamp: i64) {
+        let mut parsed = Vec::new();
+        self.parser.feed(bytes, |message| parsed.push(message));
+        for message in parsed {
+            self.record_message(message, timestamp);
+        }
+    }
+
+    /// Record an already parsed message.
+    pub fn record_message(&mut self, message: MidiMessage, timestamp: i64) {
+        if message.is_realtime() && !self.options.keep_realtime() {
+            return;
+        }
+        if self.options.punch_out().is_some_and(|out| timestamp >= out) {
+            trace!("SmfRecorder: past punch-out, dropping {:?}", message);
+            return;
+        }
+        match self.options.punch_in() {
+            Some(punch_in) if timestamp < punch_in => {
+                let window_start = punch_in - self.options.pre_roll().as_nanos() as i64;
+                if timestamp >= window_start {
+                    self.pre_roll.push_back((timestamp, message));
+                }
+            }
+            _ => self.events.push((timestamp, message)),
+        }
+    }
+
+    /// Build the file from everything recorded.
+    pub fn finish(self) -> Smf {
+        let options = self.options;
+        let mut events: Vec<(i64, MidiMessage)> = self.pre_roll.into_iter().chain(self.events).collect();
+        // Packets normally arrive in order, but don't rely on it.
+        events.sort_by_key(|(timestamp, _)| *timestamp);
+        if let Some(punch_out) = options.punch_out() {
+            // Their note-offs came after punch-out and were dropped.
+            let held = held_notes(&events);
+            events.extend(held.into_iter().map(|(channel, note)| (punch_out, MidiMessage::note_off(channel, note, U7::MIN))));
+        }
+
+        let first  = events.first().map(|(timestamp, _)| *timestamp);
+        let origin = match (options.punch_in(), first) {
+            (Some(punch_in), Some(first)) => punch_in.min(first),
+            (Some(punch_in), None) => punch_in,
+            (None, first) => first.unwrap_or(0),
+        };
+
+        let timing    = SmfTiming::Metrical(options.ppq());
+        let tempo_map = SmfTempoMap::from_tempo_changes(timing, &[(0, options.tempo())]);
+        let conductor = vec![(0, SmfEventKind::Meta(MetaEvent::Tempo(options.tempo())))];
+
+        let mut smf = if options.split_by_channel() {
+            let mut tracks: Vec<(Option<Channel>, Vec<TickedEvent>)> = vec![(None, conductor)];
+            for (timestamp, message) in events {
+                let tick    = tempo_map.nanos_to_ticks(timestamp - origin);
+                let channel = message.channel();
+                let index = match tracks.iter().position(|(c, _)| c.is_some() && *c == channel) {
+                    Some(index) => index,
+                    None if channel.is_some() => {
+                        tracks.push((channel, Vec::new()));
+                        tracks.len() - 1
+                    }
+                    None => 0,
+                };
+                tracks[index].1.push((tick, smf_event_kind(message)));
+            }
+            tracks[1..].sort_by_key(|(channel, _)| channel.map(|c| c.get()));
+
+            let mut smf = Smf::new(SmfFormat::MultiTrack, timing);
+            for (channel, events) in tracks {
+                let mut events = events;
+                if let Some(channel) = channel {
+                    let name = MetaEvent::text(MetaTextKind::TrackName, format!("Channel {}", channel.get() + 1));
+                    events.insert(0, (0, SmfEventKind::Meta(name)));
+                }
+                smf.tracks.push(track_from_ticks(events));
+            }
+            smf
+        } else {
+            let mut all = conductor;
+            all.extend(events.into_iter().map(|(timestamp, message)| {
+                (tempo_map.nanos_to_ticks(timestamp - origin), smf_event_kind(message))
+            }));
+            let mut smf = Smf::new(SmfFormat::SingleTrack, timing);
+            smf.tracks.push(track_from_ticks(all));
+            smf
+        };
+
+        for track in &mut smf.tracks {
+            track.events.push(SmfEvent::meta(0, MetaEvent::EndOfTrack));
+        }
+        info!("SmfRecorder: finished with {} track(s)", smf.tracks.len());
+        smf
+    }
+}
+
+/// Notes left on at the end of `events`, by channel then note.
+fn held_notes(events: &[(i64, MidiMessage)]) -> Vec<(Channel, U7)> {
+    let mut held = [0u128; 16];
+    for (_, message) in events {
+        match *message {
+            MidiMessage::NoteOn { channel, note, velocity } if velocity.get() > 0 => {
+                held[channel.get() as usize] |= 1 << note.get();
+            }
+            MidiMessage::NoteOn { channel, note, .. } | MidiMessage::NoteOff { channel, note, .. } => {
+                held[channel.get() as usize] &= !(1 << note.get());
+            }
+            _ => {}
+        }
+    }
+    Channel::all()
+        .flat_map(|channel| {
+            let bits = held[channel.get() as usize];
+            (0..128u8).filter(move |note| bits & (1 << note) != 0).map(move |note| (channel, U7::saturating(note)))
+        })
+        .collect()
+}
+
+/// An event at an absolute tick.
+type TickedEvent = (u64, SmfEventKind);
+
+/// System common and realtime messages have no status of their own in an
+/// SMF, so they are stored as escapes.
+fn smf_event_kind(message: MidiMessage) -> SmfEventKind {
+    match message {
+        MidiMessage::SysEx(_) => SmfEventKind::Midi(message),
+        _ if message.channel().is_none() => SmfEventKind::Escape(message.to_bytes()),
+        _ => SmfEventKind::Midi(message),
+    }
+}
+
+fn track_from_ticks(events: Vec<TickedEvent>) -> SmfTrack {
+    let mut previous = 0;
+    SmfTrack::new(
+        events
+            .into_iter()
+            .map(|(tick, kind)| {
+                let delta = (tick - previous) as u32;
+                previous  = tick;
+                SmfEvent::new(delta, kind)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test_smf_recorder {
+    use super::*;
+
+    const MS: i64 = 1_000_000;
+
+    fn absolute(track: &SmfTrack) -> Vec<(u64, SmfEventKind)> {
+        track.absolute_events().map(|(tick, e)| (tick, e.kind.clone())).collect()
+    }
+
+    #[test]
+    fn test_records_port_with_punch_and_pre_roll() {
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let device = virtual_device.open();
+        let port   = device.open_output_port(0).unwrap();
+        let output = virtual_device.output_port(0);
+
+        // 10 s in; punch in at 10 s, out at 12 s; 96 PPQ at 120 BPM = 192 ticks/s
+        output.push(vec![0x90, 60, 100], 9_000 * MS); // before the pre-roll window
+        output.push(vec![0x90, 62, 100], 9_900 * MS); // early, kept by pre-roll
+        output.push(vec![0xf8], 10_000 * MS);
+        output.push(vec![0x80, 62, 0, 0xf0, 0x7e, 0x01], 10_500 * MS);
+        output.push(vec![0x02, 0xf7], 10_750 * MS);
+        output.push(vec![0x80, 60, 0], 12_000 * MS);
+
+        let options = SmfRecordOptionsBuilder::default()
+            .ppq(96u16)
+            .punch_in(Some(10_000 * MS))
+            .punch_out(Some(12_000 * MS))
+            .pre_roll(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let mut recorder = SmfRecorder::new(options);
+        assert_eq!(recorder.poll(&port).unwrap(), 6);
+        assert_eq!(output.pending(), 0);
+
+        let smf = recorder.finish();
+        assert_eq!(smf.format, SmfFormat::SingleTrack);
+        let ch0 = Channel::new(0).unwrap();
+        assert_eq!(
+            absolute(&smf.tracks[0]),
+            vec![
+                (0, SmfEventKind::Meta(MetaEvent::Tempo(500_000))),
+                (0, SmfEventKind::Midi(MidiMessage::note_on(ch0, U7::new(62).unwrap(), U7::new(100).unwrap()))),
+                (115, SmfEventKind::Midi(MidiMessage::note_off(ch0, U7::new(62).unwrap(), U7::MIN))),
+                (163, SmfEventKind::Midi(MidiMessage::sysex(vec![0x7e, 0x01, 0x02]).unwrap())),
+                (163, SmfEventKind::Meta(MetaEvent::EndOfTrack)),
+            ]
+        );
+        assert_eq!(Smf::parse(&smf.to_bytes().unwrap()).unwrap(), smf);
+    }
+
+    #[test]
+    fn test_note_held_across_punch_out_is_released() {
+        let virtual_device = VirtualMidiDevice::new(0, 1);
+        let device = virtual_device.open();
+        let port   = device.open_output_port(0).unwrap();
+        let output = virtual_device.output_port(0);
+
+        output.push(vec![0x90, 60, 100, 0x90, 64], 0);
+        // The rest of the second note-on is lost; a flush resets running
+        // status, so the stray data bytes after it are not a note.
+        output.push_packet(VirtualReceivedPacket { opcode: AMIDI_OPCODE_FLUSH, bytes: Vec::new(), timestamp: 0 });
+        output.push(vec![67, 100], 100 * MS);
+        output.push(vec![0x91, 48, 100], 250 * MS);
+        output.push(vec![0x81, 48, 0, 0x80, 60, 0], 1_500 * MS);
+
+        let options = SmfRecordOptionsBuilder::default()
+            .ppq(96u16)
+            .punch_in(Some(0))
+            .punch_out(Some(1_000 * MS))
+            .build()
+            .unwrap();
+        let mut recorder = SmfRecorder::new(options);
+        assert_eq!(recorder.poll(&port).unwrap(), 4);
+        assert_eq!(output.pending(), 0);
+
+        let smf = recorder.finish();
+        let ch = |n| Channel::new(n).unwrap();
+        let u7 = |n| U7::new(n).unwrap();
+        assert_eq!(
+            absolute(&smf.tracks[0]),
+            vec![
+                (0, SmfEventKind::Meta(MetaEvent::Tempo(500_000))),
+                (0, SmfEventKind::Midi(MidiMessage::note_on(ch(0), u7(60), u7(100)))),
+                (48, SmfEventKind::Midi(MidiMessage::note_on(ch(1), u7(48), u7(100)))),
+                (192, SmfEventKind::Midi(MidiMessage::note_off(ch(0), u7(60), U7::MIN))),
+                (192, SmfEventKind::Midi(MidiMessage::note_off(ch(1), u7(48), U7::MIN))),
+                (192, SmfEventKind::Meta(MetaEvent::EndOfTrack)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_channel() {
+        let options = SmfRecordOptionsBuilder::default().ppq(96u16).split_by_channel(true).build().unwrap();
+        let mut recorder = SmfRecorder::new(options);
+        recorder.record(&[0x99, 36, 100], 1_000 * MS);
+        recorder.record(&[0x90, 60, 100, 0xf2, 0x10, 0x00], 1_500 * MS);
+        recorder.record(&[0x89, 36, 0], 2_000 * MS);
+
+        let smf = recorder.finish();
+        assert_eq!(smf.format, SmfFormat::MultiTrack);
+        assert_eq!(smf.tracks.len(), 3);
+        assert_eq!(smf.tracks[1].name().as_deref(), Some("Channel 1"));
+        assert_eq!(smf.tracks[2].name().as_deref(), Some("Channel 10"));
+        assert_eq!(absolute(&smf.tracks[0])[1], (96, SmfEventKind::Escape(vec![0xf2, 0x10, 0x00])));
+        assert_eq!(smf.tracks[2].length_ticks(), 192);
+        assert_eq!(Smf::parse(&smf.to_bytes().unwrap()).unwrap(), smf);
+    }
+}
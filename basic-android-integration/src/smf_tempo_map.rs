@@ -0,0 +1,149 @@
+// ---------------- [ File: basic-android-integration/src/smf_tempo_map.rs ]
+crate::ix!();
+
+/// Converts between ticks and nanoseconds of song time for one file.
+///
+/// For metrical files it is built from the Set Tempo meta events (120 BPM
+/// until the first one); for SMPTE files ticks map to time directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmfTempoMap {
+    timing:   SmfTiming,
+    /// (tick, nanos at that tick, microseconds per quarter from there on),
+    /// sorted by tick, always starting at tick 0.
+    segments: Vec<(u64, i64, u32)>,
+}
+
+impl SmfTempoMap {
+    /// Tempo map from the tempo events of all tracks (formats 0 and 1).
+    pub fn from_smf(smf: &Smf) -> Self {
+        Self::from_tracks(smf.timing, smf.tracks.iter())
+    }
+
+    /// Tempo map from the tempo events of the given tracks only, e.g. a
+    /// single format 2 pattern.
+    pub fn from_tracks<'t>(timing: SmfTiming, tracks: impl Iterator<Item = &'t SmfTrack>) -> Self {
+        let mut changes: Vec<(u64, u32)> = tracks
+            .flat_map(|track| track.absolute_events())
+            .filter_map(|(tick, event)| match event.kind {
+                SmfEventKind::Meta(MetaEvent::Tempo(us)) => Some((tick, us)),
+                _ => None,
+            })
+            .collect();
+        changes.sort_by_key(|(tick, _)| *tick);
+        Self::from_tempo_changes(timing, &changes)
+    }
+
+    /// Tempo map from explicit `(tick, microseconds per quarter)` changes,
+    /// sorted by tick. Changes to a tempo of 0 are ignored, so time always
+    /// advances.
+    pub fn from_tempo_changes(timing: SmfTiming, changes: &[(u64, u32)]) -> Self {
+        let mut map = Self {
+            timing,
+            segments: vec![(0, 0, MetaEvent::DEFAULT_TEMPO)],
+        };
+        for &(tick, us) in changes {
+            if us == 0 {
+                warn!("ignoring tempo of 0 at tick {}", tick);
+                continue;
+            }
+            let last = map.segments.last_mut().expect("segments start non-empty");
+            if last.0 == tick {
+                // A later change at the same tick wins.
+                last.2 = us;
+            } else {
+                let nanos = map.ticks_to_nanos(tick);
+                map.segments.push((tick, nanos, us));
+            }
+        }
+        map
+    }
+
+    pub fn timing(&self) -> SmfTiming {
+        self.timing
+    }
+
+    /// Microseconds per quarter note in effect at `tick`.
+    pub fn tempo_at(&self, tick: u64) -> u32 {
+        self.segment_for_tick(tick).2
+    }
+
+    pub fn ticks_to_nanos(&self, tick: u64) -> i64 {
+        match self.timing {
+            SmfTiming::Metrical(ppq) => {
+                let (seg_tick, seg_nanos, us) = self.segment_for_tick(tick);
+                let delta = (tick - seg_tick) as i128 * us as i128 * 1000 / ppq.max(1) as i128;
+                seg_nanos + delta as i64
+            }
+            SmfTiming::Timecode { frames_per_second, ticks_per_frame } => {
+                let (num, den) = timecode_ticks_per_second(frames_per_second, ticks_per_frame);
+                (tick as i128 * 1_000_000_000 * den / num) as i64
+            }
+        }
+    }
+
+    pub fn nanos_to_ticks(&self, nanos: i64) -> u64 {
+        let nanos = nanos.max(0);
+        match self.timing {
+            SmfTiming::Metrical(ppq) => {
+                let (seg_tick, seg_nanos, us) = *self
+                    .segments
+                    .iter()
+                    .rev()
+                    .find(|(_, n, _)| *n <= nanos)
+                    .unwrap_or(&self.segments[0]);
+                let delta = (nanos - seg_nanos) as i128 * ppq as i128 / (us.max(1) as i128 * 1000);
+                seg_tick + delta as u64
+            }
+            SmfTiming::Timecode { frames_per_second, ticks_per_frame } => {
+                let (num, den) = timecode_ticks_per_second(frames_per_second, ticks_per_frame);
+                (nanos as i128 * num / (den * 1_000_000_000)) as u64
+            }
+        }
+    }
+
+    fn segment_for_tick(&self, tick: u64) -> (u64, i64, u32) {
+        *self
+            .segments
+            .iter()
+            .rev()
+            .find(|(t, _, _)| *t <= tick)
+            .unwrap_or(&self.segments[0])
+    }
+}
+
+/// Ticks per second as a fraction; 29 means 29.97 (30000/1001) frames per second.
+fn timecode_ticks_per_second(frames_per_second: u8, ticks_per_frame: u8) -> (i128, i128) {
+    let tpf = ticks_per_frame.max(1) as i128;
+    match frames_per_second {
+        29 => (30_000 * tpf, 1001),
+        fps => (fps as i128 * tpf, 1),
+    }
+}
+
+#[cfg(test)]
+mod test_smf_tempo_map {
+    use super::*;
+
+    #[test]
+    fn test_tempo_changes() {
+        // 120 BPM for one bar of 4/4 at 96 PPQ, then 60 BPM.
+        let map = SmfTempoMap::from_tempo_changes(SmfTiming::Metrical(96), &[(0, 500_000), (384, 1_000_000)]);
+        assert_eq!(map.ticks_to_nanos(96), 500_000_000);
+        assert_eq!(map.ticks_to_nanos(384), 2_000_000_000);
+        assert_eq!(map.ticks_to_nanos(480), 3_000_000_000);
+        assert_eq!(map.nanos_to_ticks(3_000_000_000), 480);
+        assert_eq!(map.nanos_to_ticks(1_000_000_000), 192);
+        assert_eq!(map.tempo_at(400), 1_000_000);
+
+        let map = SmfTempoMap::from_tempo_changes(SmfTiming::Metrical(96), &[(96, 0)]);
+        assert_eq!(map.tempo_at(200), MetaEvent::DEFAULT_TEMPO);
+        assert_eq!(map.ticks_to_nanos(192), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_timecode() {
+        let map = SmfTempoMap::from_tempo_changes(SmfTiming::Timecode { frames_per_second: 25, ticks_per_frame: 40 }, &[]);
+        assert_eq!(map.ticks_to_nanos(1000), 1_000_000_000);
+        assert_eq!(map.nanos_to_ticks(500_000_000), 500);
+    }
+}
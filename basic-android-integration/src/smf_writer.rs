@@ -0,0 +1,137 @@
+// ---------------- [ File: basic-android-integration/src/smf_writer.rs ]
+crate::ix!();
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct SmfWriteOptions {
+    /// Omit repeated channel status bytes.
+    running_status: bool,
+
+    /// Append an End of Track meta event to tracks that lack one.
+    terminate_tracks: bool,
+}
+
+impl Default for SmfWriteOptions {
+    fn default() -> Self {
+        Self {
+            running_status:   true,
+            terminate_tracks: true,
+        }
+    }
+}
+
+/// Serialize `smf`. Values the format has no room for (more than 65535
+/// tracks, a PPQ above 0x7fff, an unknown frame rate, a tempo above 24
+/// bits, a delta or length above [`VLQ_MAX`]) are errors rather than being
+/// truncated; the offset is where the value would have been written.
+pub fn write_smf(smf: &Smf, options: &SmfWriteOptions) -> Result<Vec<u8>, SmfError> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&smf.format.number().to_be_bytes());
+    let tracks = u16::try_from(smf.tracks.len()).map_err(|_| {
+        let kind = SmfErrorKind::ValueOutOfRange { what: "track count", value: smf.tracks.len() as u64, max: u16::MAX as u64 };
+        SmfError::new(out.len(), kind)
+    })?;
+    out.extend_from_slice(&tracks.to_be_bytes());
+    out.extend_from_slice(&write_division(smf.timing, out.len())?);
+
+    for track in &smf.tracks {
+        out.extend_from_slice(b"MTrk");
+        let length_at = out.len();
+        out.extend_from_slice(&[0; 4]);
+        write_track(track, options, &mut out)?;
+        let length = out.len() - length_at - 4;
+        let length = u32::try_from(length).map_err(|_| {
+            let kind = SmfErrorKind::ValueOutOfRange { what: "track length", value: length as u64, max: u32::MAX as u64 };
+            SmfError::new(length_at, kind)
+        })?;
+        out[length_at..length_at + 4].copy_from_slice(&length.to_be_bytes());
+    }
+
+    Ok(out)
+}
+
+fn write_division(timing: SmfTiming, offset: usize) -> Result<[u8; 2], SmfError> {
+    match timing {
+        SmfTiming::Metrical(ppq) if ppq > 0x7fff => {
+            let kind = SmfErrorKind::ValueOutOfRange { what: "ticks per quarter note", value: ppq as u64, max: 0x7fff };
+            Err(SmfError::new(offset, kind))
+        }
+        SmfTiming::Metrical(ppq) => Ok(ppq.to_be_bytes()),
+        SmfTiming::Timecode { frames_per_second, ticks_per_frame } => match frames_per_second {
+            24 | 25 | 29 | 30 => Ok([(-(frames_per_second as i8)) as u8, ticks_per_frame]),
+            rate => Err(SmfError::new(offset, SmfErrorKind::InvalidFrameRate(rate))),
+        },
+    }
+}
+
+/// A length written as a variable-length quantity.
+fn write_length(length: usize, out: &mut Vec<u8>) -> Result<(), SmfError> {
+    match u32::try_from(length) {
+        Ok(length) => write_vlq(length, out),
+        Err(_) => {
+            let kind = SmfErrorKind::ValueOutOfRange { what: "variable-length quantity", value: length as u64, max: VLQ_MAX as u64 };
+            Err(SmfError::new(out.len(), kind))
+        }
+    }
+}
+
+fn write_track(track: &SmfTrack, options: &SmfWriteOptions, out: &mut Vec<u8>) -> Result<(), SmfError> {
+    let mut encoder = RunningStatusEncoder::new();
+    let mut message = Vec::new();
+
+    for event in &track.events {
+        write_vlq(event.delta, out)?;
+        match &event.kind {
+            SmfEventKind::Midi(MidiMessage::SysEx(data)) => {
+                encoder.reset();
+                out.push(0xf0);
+                write_length(data.len() + 1, out)?;
+                out.extend_from_slice(data);
+                out.push(0xf7);
+            }
+            // System common and realtime messages have no status of their
+            // own in an SMF; write them as escapes, as the recorder does.
+            SmfEventKind::Midi(midi) if midi.channel().is_none() => {
+                encoder.reset();
+                let bytes = midi.to_bytes();
+                out.push(0xf7);
+                write_length(bytes.len(), out)?;
+                out.extend_from_slice(&bytes);
+            }
+            SmfEventKind::Midi(midi) => {
+                message.clear();
+                if options.running_status() {
+                    encoder.encode(midi, &mut message);
+                } else {
+                    midi.write_to(&mut message);
+                }
+                out.extend_from_slice(&message);
+            }
+            SmfEventKind::SysExStart(data) | SmfEventKind::Escape(data) => {
+                encoder.reset();
+                out.push(if matches!(event.kind, SmfEventKind::SysExStart(_)) { 0xf0 } else { 0xf7 });
+                write_length(data.len(), out)?;
+                out.extend_from_slice(data);
+            }
+            SmfEventKind::Meta(meta) => {
+                encoder.reset();
+                let payload = meta.payload(out.len())?;
+                out.push(0xff);
+                out.push(meta.meta_type());
+                write_length(payload.len(), out)?;
+                out.extend_from_slice(&payload);
+            }
+        }
+    }
+
+    let terminated = matches!(track.events.last(), Some(SmfEvent { kind: SmfEventKind::Meta(MetaEvent::EndOfTrack), .. }));
+    if options.terminate_tracks() && !terminated {
+        out.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,251 @@
+// ---------------- [ File: basic-android-integration/src/smpte_time.rs ]
+crate::ix!();
+
+/// The four MTC frame rates, in the order of their 2-bit rate code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum SmpteRate {
+    Fps24,
+    Fps25,
+    /// 29.97 fps drop-frame: frame numbers 0 and 1 are skipped at the start
+    /// of every minute except each tenth.
+    Fps2997DropFrame,
+    #[default]
+    Fps30,
+}
+
+impl SmpteRate {
+    pub fn from_code(code: u8) -> Self {
+        match code & 3 {
+            0 => SmpteRate::Fps24,
+            1 => SmpteRate::Fps25,
+            2 => SmpteRate::Fps2997DropFrame,
+            _ => SmpteRate::Fps30,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Frame numbers per second in the time code (30 for drop-frame).
+    pub fn nominal_fps(self) -> u32 {
+        match self {
+            SmpteRate::Fps24 => 24,
+            SmpteRate::Fps25 => 25,
+            SmpteRate::Fps2997DropFrame | SmpteRate::Fps30 => 30,
+        }
+    }
+
+    pub fn is_drop_frame(self) -> bool {
+        self == SmpteRate::Fps2997DropFrame
+    }
+
+    /// Frames in 24 hours of time code.
+    pub fn frames_per_day(self) -> u64 {
+        let dropped = if self.is_drop_frame() { 24 * 54 * 2 } else { 0 };
+        24 * 3600 * self.nominal_fps() as u64 - dropped
+    }
+
+    /// Real duration of one frame.
+    pub fn frame_nanos(self) -> f64 {
+        match self {
+            SmpteRate::Fps2997DropFrame => 1e9 * 1001.0 / 30000.0,
+            _ => 1e9 / self.nominal_fps() as f64,
+        }
+    }
+}
+
+/// A SMPTE time code position, `hh:mm:ss:ff` at a given rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SmpteTime {
+    hours:   u8,
+    minutes: u8,
+    seconds: u8,
+    frames:  u8,
+    rate:    SmpteRate,
+}
+
+/// Frames per ten minutes and per (dropping) minute at 29.97df.
+const DF_FRAMES_PER_10_MINUTES: u64 = 17982;
+const DF_FRAMES_PER_MINUTE:     u64 = 1798;
+
+impl SmpteTime {
+    pub fn new(hours: u8, minutes: u8, seconds: u8, frames: u8, rate: SmpteRate) -> Result<Self, MidiValueError> {
+        let check = |what, value: u8, min: u8, max: u8| {
+            if (min..=max).contains(&value) {
+                Ok(())
+            } else {
+                Err(MidiValueError::OutOfRange { what, value: value as i64, min: min as i64, max: max as i64 })
+            }
+        };
+        check("smpte hours", hours, 0, 23)?;
+        check("smpte minutes", minutes, 0, 59)?;
+        check("smpte seconds", seconds, 0, 59)?;
+        let first = if rate.is_drop_frame() && seconds == 0 && !minutes.is_multiple_of(10) { 2 } else { 0 };
+        check("smpte frames", frames, first, rate.nominal_fps() as u8 - 1)?;
+        Ok(Self { hours, minutes, seconds, frames, rate })
+    }
+
+    pub fn zero(rate: SmpteRate) -> Self {
+        Self { hours: 0, minutes: 0, seconds: 0, frames: 0, rate }
+    }
+
+    pub fn hours(&self) -> u8 { self.hours }
+    pub fn minutes(&self) -> u8 { self.minutes }
+    pub fn seconds(&self) -> u8 { self.seconds }
+    pub fn frames(&self) -> u8 { self.frames }
+    pub fn rate(&self) -> SmpteRate { self.rate }
+
+    /// Frames since 00:00:00:00.
+    pub fn frame_count(&self) -> u64 {
+        let fps     = self.rate.nominal_fps() as u64;
+        let minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let count   = (minutes * 60 + self.seconds as u64) * fps + self.frames as u64;
+        match self.rate.is_drop_frame() {
+            true => count - 2 * (minutes - minutes / 10),
+            false => count,
+        }
+    }
+
+    /// Inverse of [`frame_count`](Self::frame_count); wraps at 24 hours.
+    pub fn from_frame_count(count: u64, rate: SmpteRate) -> Self {
+        let fps   = rate.nominal_fps() as u64;
+        let mut n = count % rate.frames_per_day();
+        if rate.is_drop_frame() {
+            let (tens, rest) = (n / DF_FRAMES_PER_10_MINUTES, n % DF_FRAMES_PER_10_MINUTES);
+            n += 18 * tens + if rest >= 2 { 2 * ((rest - 2) / DF_FRAMES_PER_MINUTE) } else { 0 };
+        }
+        Self {
+            hours:   (n / (3600 * fps)) as u8,
+            minutes: (n / (60 * fps) % 60) as u8,
+            seconds: (n / fps % 60) as u8,
+            frames:  (n % fps) as u8,
+            rate,
+        }
+    }
+
+    /// Step by `frames` (negative goes back), wrapping at 24 hours.
+    pub fn add_frames(&self, frames: i64) -> Self {
+        let day = self.rate.frames_per_day() as i64;
+        Self::from_frame_count((self.frame_count() as i64 + frames).rem_euclid(day) as u64, self.rate)
+    }
+
+    /// Real time since 00:00:00:00 (29.97df runs 0.1% slower than its labels).
+    pub fn to_nanos(&self) -> i64 {
+        (self.frame_count() as f64 * self.rate.frame_nanos()).round() as i64
+    }
+
+    /// The frame playing `nanos` after 00:00:00:00.
+    pub fn from_nanos(nanos: i64, rate: SmpteRate) -> Self {
+        Self::from_frame_count((nanos.max(0) as f64 / rate.frame_nanos()).floor() as u64, rate)
+    }
+
+    /// Data byte of MTC quarter frame `piece` (0..=7) for this time.
+    pub fn quarter_frame(&self, piece: u8) -> U7 {
+        let nibble = match piece & 7 {
+            0 => self.frames & 0xf,
+            1 => self.frames >> 4,
+            2 => self.seconds & 0xf,
+            3 => self.seconds >> 4,
+            4 => self.minutes & 0xf,
+            5 => self.minutes >> 4,
+            6 => self.hours & 0xf,
+            _ => self.hours >> 4 | self.rate.code() << 1,
+        };
+        U7::saturating((piece & 7) << 4 | nibble)
+    }
+
+    /// Reassemble a time from the eight quarter frame nibbles, in piece order.
+    pub fn from_quarter_frames(nibbles: [u8; 8]) -> Result<Self, MidiValueError> {
+        let join = |lo: u8, hi: u8| (nibbles[lo as usize] & 0xf) | (nibbles[hi as usize] & 0xf) << 4;
+        let rate = SmpteRate::from_code(nibbles[7] >> 1);
+        Self::new(join(6, 7) & 0x1f, join(4, 5), join(2, 3), join(0, 1), rate)
+    }
+
+    /// SysEx data (between F0 and F7) of an MTC full frame message.
+    pub fn full_frame_sysex(&self, device_id: U7) -> Vec<u8> {
+        vec![0x7f, device_id.get(), 0x01, 0x01, self.rate.code() << 5 | self.hours, self.minutes, self.seconds, self.frames]
+    }
+
+    /// Parse MTC full frame SysEx data; `None` if `data` is some other message.
+    pub fn from_full_frame_sysex(data: &[u8]) -> Option<Self> {
+        match *data {
+            [0x7f, _, 0x01, 0x01, hr, mn, sc, fr] => Self::new(hr & 0x1f, mn, sc, fr, SmpteRate::from_code(hr >> 5)).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A time code with subframes (1/100 frame), as MMC and MSC carry it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SmptePosition {
+    pub time:      SmpteTime,
+    pub subframes: u8,
+}
+
+impl SmptePosition {
+    pub fn new(time: SmpteTime, subframes: u8) -> Self {
+        Self { time, subframes: subframes.min(99) }
+    }
+
+    /// The 5-byte "standard time code" form: `0tthhhhh mm ss ff subframes`.
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let time = &self.time;
+        [time.rate().code() << 5 | time.hours(), time.minutes(), time.seconds(), time.frames(), self.subframes]
+    }
+
+    /// Parse the standard time code form. Color frame, blank, sign and
+    /// status flags are ignored; a status byte in place of subframes reads
+    /// as 0 subframes.
+    pub fn from_bytes(bytes: [u8; 5]) -> Result<Self, MidiValueError> {
+        let [hr, mn, sc, fr, last] = bytes;
+        let time      = SmpteTime::new(hr & 0x1f, mn & 0x3f, sc & 0x3f, fr & 0x1f, SmpteRate::from_code(hr >> 5))?;
+        let subframes = if fr & 0x20 == 0 { last } else { 0 };
+        Ok(Self::new(time, subframes))
+    }
+}
+
+impl std::fmt::Display for SmpteTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let separator = if self.rate.is_drop_frame() { ';' } else { ':' };
+        write!(f, "{:02}:{:02}:{:02}{}{:02}", self.hours, self.minutes, self.seconds, separator, self.frames)
+    }
+}
+
+#[cfg(test)]
+mod test_smpte_time {
+    use super::*;
+
+    #[test]
+    fn test_drop_frame_counting() {
+        let df = SmpteRate::Fps2997DropFrame;
+        assert!(SmpteTime::new(0, 1, 0, 0, df).is_err());
+        assert!(SmpteTime::new(0, 10, 0, 0, df).is_ok());
+
+        let before = SmpteTime::new(0, 0, 59, 29, df).unwrap();
+        assert_eq!(before.add_frames(1), SmpteTime::new(0, 1, 0, 2, df).unwrap());
+        assert_eq!(SmpteTime::new(0, 10, 0, 0, df).unwrap().frame_count(), 17982);
+        assert_eq!(SmpteTime::new(1, 0, 0, 0, df).unwrap().frame_count(), 107892);
+        for count in [0, 1799, 1800, 17981, 17982, 107891, 2_000_000] {
+            assert_eq!(SmpteTime::from_frame_count(count, df).frame_count(), count);
+        }
+        // An hour of 29.97df labels is 3600s of real time, give or take a frame.
+        assert!((SmpteTime::new(1, 0, 0, 0, df).unwrap().to_nanos() - 3_600_000_000_000).abs() < 33_366_667);
+        assert_eq!(SmpteTime::zero(SmpteRate::Fps24).add_frames(-1).to_string(), "23:59:59:23");
+    }
+
+    #[test]
+    fn test_quarter_and_full_frame_encoding() {
+        let time = SmpteTime::new(23, 59, 58, 24, SmpteRate::Fps25).unwrap();
+        let nibbles: Vec<u8> = (0..8).map(|piece| time.quarter_frame(piece).get()).collect();
+        assert_eq!(nibbles, vec![0x08, 0x11, 0x2a, 0x33, 0x4b, 0x53, 0x67, 0x73]);
+        assert_eq!(SmpteTime::from_quarter_frames(std::array::from_fn(|n| nibbles[n])).unwrap(), time);
+
+        let sysex = time.full_frame_sysex(U7::MAX);
+        assert_eq!(sysex, vec![0x7f, 0x7f, 0x01, 0x01, 0x37, 59, 58, 24]);
+        assert_eq!(SmpteTime::from_full_frame_sysex(&sysex), Some(time));
+
+        let position = SmptePosition::new(time, 50);
+        assert_eq!(SmptePosition::from_bytes(position.to_bytes()), Ok(position));
+    }
+}
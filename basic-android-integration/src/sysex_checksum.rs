@@ -0,0 +1,132 @@
+// ---------------- [ File: basic-android-integration/src/sysex_checksum.rs ]
+crate::ix!();
+
+/// Roland's manufacturer id.
+pub const ROLAND_ID: u8 = 0x41;
+
+/// Roland-style checksum: the value that brings the 7-bit sum of `bytes`
+/// to zero. Yamaha, Korg and others use the same arithmetic over their own
+/// ranges.
+pub fn roland_checksum(bytes: &[u8]) -> u8 {
+    let sum: u32 = bytes.iter().map(|&b| (b & 0x7f) as u32).sum();
+    ((128 - sum % 128) % 128) as u8
+}
+
+/// Yamaha bulk dump checksum, taken over the byte count, address and data.
+/// The arithmetic is Roland's; only the range differs.
+pub fn yamaha_checksum(bytes: &[u8]) -> u8 {
+    roland_checksum(bytes)
+}
+
+/// Whether `bytes`, ending with their checksum byte, sum to zero (mod 128).
+pub fn checksum_valid(bytes_with_checksum: &[u8]) -> bool {
+    bytes_with_checksum.iter().map(|&b| (b & 0x7f) as u32).sum::<u32>() % 128 == 0
+}
+
+/// XOR checksum used by the MIDI Tuning Standard bulk dump: every byte of
+/// `bytes` XORed together, masked to 7 bits.
+pub fn xor_checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &b| sum ^ b) & 0x7f
+}
+
+/// Roland command ids.
+pub const ROLAND_RQ1: u8 = 0x11;
+pub const ROLAND_DT1: u8 = 0x12;
+
+/// A Roland exclusive message (`DT1`, `RQ1`, ...), split into its parts.
+///
+/// Layout of the data between `F0` and `F7`: `41`, device id, model id
+/// (any number of `00` bytes then one non-zero byte), command id, address
+/// and data, checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RolandSysEx<'a> {
+    pub device_id: u8,
+    pub model_id:  &'a [u8],
+    pub command:   u8,
+    /// Address and data, the bytes the checksum covers.
+    pub body:      &'a [u8],
+    pub checksum:  u8,
+}
+
+impl<'a> RolandSysEx<'a> {
+    /// `data` may include or omit the surrounding `F0`/`F7`.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let data = data.strip_prefix(&[0xf0]).unwrap_or(data);
+        let data = data.strip_suffix(&[0xf7]).unwrap_or(data);
+
+        let [ROLAND_ID, device_id, rest @ ..] = data else {
+            return None;
+        };
+        let model_len = rest.iter().position(|&b| b != 0)? + 1;
+        let (model_id, rest) = rest.split_at(model_len);
+        let [command, body @ .., checksum] = rest else {
+            return None;
+        };
+        Some(Self { device_id: *device_id, model_id, command: *command, body, checksum: *checksum })
+    }
+
+    /// SysEx data (without `F0`/`F7`) for a Roland message, checksum
+    /// appended. For `DT1` the body is address then data; for `RQ1` address
+    /// then size.
+    pub fn encode(device_id: u8, model_id: &[u8], command: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![ROLAND_ID, device_id];
+        out.extend_from_slice(model_id);
+        out.push(command);
+        out.extend_from_slice(body);
+        out.push(roland_checksum(body));
+        out
+    }
+
+    pub fn expected_checksum(&self) -> u8 {
+        roland_checksum(self.body)
+    }
+
+    pub fn checksum_ok(&self) -> bool {
+        self.checksum == self.expected_checksum()
+    }
+}
+
+#[cfg(test)]
+mod test_sysex_checksum {
+    use super::*;
+
+    #[test]
+    fn test_gs_reset() {
+        // GS reset: F0 41 10 42 12 40 00 7F 00 41 F7
+        let message = [0xf0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7f, 0x00, 0x41, 0xf7];
+        let parsed  = RolandSysEx::parse(&message).unwrap();
+        assert_eq!(parsed.model_id, &[0x42]);
+        assert_eq!(parsed.command, 0x12);
+        assert_eq!(parsed.body, &[0x40, 0x00, 0x7f, 0x00]);
+        assert!(parsed.checksum_ok());
+
+        let mut corrupt = message;
+        corrupt[8] = 0x01;
+        assert_eq!(RolandSysEx::parse(&corrupt).unwrap().expected_checksum(), 0x40);
+        assert!(!RolandSysEx::parse(&corrupt).unwrap().checksum_ok());
+    }
+
+    #[test]
+    fn test_encode_and_yamaha_checksum() {
+        let gs_reset = RolandSysEx::encode(0x10, &[0x42], ROLAND_DT1, &[0x40, 0x00, 0x7f, 0x00]);
+        assert_eq!(gs_reset, vec![0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7f, 0x00, 0x41]);
+        assert!(checksum_valid(&gs_reset[4..]));
+
+        // One-byte bulk dump: count 00 01, address 00 00 7E, data 00.
+        let bulk = [0x00, 0x01, 0x00, 0x00, 0x7e, 0x00];
+        assert_eq!(yamaha_checksum(&bulk), 0x01);
+    }
+
+    #[test]
+    fn test_extended_model_id() {
+        let body    = [0x18, 0x00, 0x00, 0x00, 0x05];
+        let mut msg = vec![0x41, 0x10, 0x00, 0x00, 0x3b, 0x12];
+        msg.extend_from_slice(&body);
+        msg.push(roland_checksum(&body));
+
+        let parsed = RolandSysEx::parse(&msg).unwrap();
+        assert_eq!(parsed.model_id, &[0x00, 0x00, 0x3b]);
+        assert!(parsed.checksum_ok());
+        assert_eq!(RolandSysEx::parse(&[0x43, 0x10, 0x4c]), None);
+    }
+}
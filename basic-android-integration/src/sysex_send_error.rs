@@ -0,0 +1,32 @@
+// ---------------- [ File: basic-android-integration/src/sysex_send_error.rs ]
+crate::ix!();
+
+/// A SysEx message that could not be encoded, or not sent in full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SysExSendError {
+    Invalid(MidiValueError),
+    Send(SendAllError),
+}
+
+impl std::fmt::Display for SysExSendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SysExSendError::Invalid(err) => write!(f, "cannot encode SysEx: {}", err),
+            SysExSendError::Send(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SysExSendError {}
+
+impl From<MidiValueError> for SysExSendError {
+    fn from(err: MidiValueError) -> Self {
+        SysExSendError::Invalid(err)
+    }
+}
+
+impl From<SendAllError> for SysExSendError {
+    fn from(err: SendAllError) -> Self {
+        SysExSendError::Send(err)
+    }
+}
@@ -0,0 +1,382 @@
+// ---------------- [ File: basic-android-integration/src/sysex_transaction.rs ]
+crate::ix!();
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[derive(Builder, CopyGetters, Debug, Clone)]
+#[builder(setter(into), default)]
+#[getset(get_copy = "pub")]
+pub struct SysExTransactionOptions {
+    /// How long each attempt waits for a reply before the request is sent
+    /// again.
+    timeout: Duration,
+    /// Extra attempts after the first one times out.
+    retries: u32,
+    /// Sleep (sync) or wake-up delay (async) between looks at the port.
+    poll_interval: Duration,
+}
+
+impl Default for SysExTransactionOptions {
+    fn default() -> Self {
+        Self {
+            timeout:       Duration::from_secs(1),
+            retries:       2,
+            poll_interval: Duration::from_millis(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SysExTransactionError {
+    Send(SendAllError),
+    /// `MidiOutputPort::receive` failed with this status.
+    Receive(isize),
+    /// No matching reply after this many sends.
+    TimedOut { attempts: u32 },
+    Cancelled,
+}
+
+impl std::fmt::Display for SysExTransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SysExTransactionError::Send(err) => write!(f, "sending the request failed: {}", err),
+            SysExTransactionError::Receive(code) => write!(f, "receiving the reply failed with {}", code),
+            SysExTransactionError::TimedOut { attempts } => write!(f, "no reply after {} attempts", attempts),
+            SysExTransactionError::Cancelled => write!(f, "transaction cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SysExTransactionError {}
+
+impl From<SendAllError> for SysExTransactionError {
+    fn from(err: SendAllError) -> Self {
+        SysExTransactionError::Send(err)
+    }
+}
+
+/// Cancels a transaction from anywhere, including another thread.
+#[derive(Debug, Clone, Default)]
+pub struct SysExCancelToken(Arc<AtomicBool>);
+
+impl SysExCancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// One request in flight, created by [`SysExSession::begin`].
+pub struct SysExTransaction<M> {
+    request:  Vec<u8>,
+    matcher:  M,
+    attempts: u32,
+    deadline: i64,
+    cancel:   SysExCancelToken,
+}
+
+impl<M> SysExTransaction<M> {
+    /// Times the request has been sent so far.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn cancel_token(&self) -> SysExCancelToken {
+        self.cancel.clone()
+    }
+}
+
+/// Messages kept for [`SysExSession::take_unsolicited`]; older ones are dropped.
+const MAX_UNSOLICITED: usize = 1024;
+
+/// Request/response exchanges with one device over a pair of its ports:
+/// send a request on the input port, wait on the output port for the reply
+/// a predicate accepts, resend on timeout.
+///
+/// Everything else received meanwhile is kept for
+/// [`take_unsolicited`](Self::take_unsolicited), so a transaction can run
+/// on a port that also carries notes or clock.
+pub struct SysExSession<'p, 'lib, C: MonotonicClock = SystemMonotonicClock> {
+    input:       &'p MidiInputPort<'lib>,
+    output:      &'p MidiOutputPort<'lib>,
+    clock:       C,
+    options:     SysExTransactionOptions,
+    parser:      MidiStreamParser,
+    unsolicited: VecDeque<MidiMessage>,
+}
+
+impl<'p, 'lib, C: MonotonicClock> SysExSession<'p, 'lib, C> {
+    pub fn new(input: &'p MidiInputPort<'lib>, output: &'p MidiOutputPort<'lib>, options: SysExTransactionOptions, clock: C) -> Self {
+        Self { input, output, clock, options, parser: MidiStreamParser::new(), unsolicited: VecDeque::new() }
+    }
+
+    pub fn options(&self) -> &SysExTransactionOptions {
+        &self.options
+    }
+
+    /// Messages received that no transaction asked for, oldest first.
+    pub fn take_unsolicited(&mut self) -> Vec<MidiMessage> {
+        self.unsolicited.drain(..).collect()
+    }
+
+    /// Send `request` and start waiting for a reply `matcher` accepts.
+    /// Anything already queued on the output port counts as unsolicited,
+    /// so a stale reply can't answer a fresh request.
+    pub fn begin<M>(&mut self, request: &MidiMessage, matcher: M) -> Result<SysExTransaction<M>, SysExTransactionError>
+    where
+        M: FnMut(&MidiMessage) -> bool,
+    {
+        self.receive(&mut |_| false)?;
+        let mut transaction = SysExTransaction {
+            request:  request.to_bytes(),
+            matcher,
+            attempts: 0,
+            deadline: 0,
+            cancel:   SysExCancelToken::default(),
+        };
+        self.send(&mut transaction)?;
+        Ok(transaction)
+    }
+
+    /// Look at the port once without blocking: the reply, an error, or
+    /// `Pending`. Resends the request when an attempt times out.
+    pub fn step<M>(&mut self, transaction: &mut SysExTransaction<M>) -> Poll<Result<MidiMessage, SysExTransactionError>>
+    where
+        M: FnMut(&MidiMessage) -> bool,
+    {
+        if transaction.cancel.is_cancelled() {
+            return Poll::Ready(Err(SysExTransactionError::Cancelled));
+        }
+        match self.receive(&mut transaction.matcher) {
+            Err(err) => return Poll::Ready(Err(err)),
+            Ok(Some(reply)) => return Poll::Ready(Ok(reply)),
+            Ok(None) => {}
+        }
+        if self.clock.now_nanos() >= transaction.deadline {
+            if transaction.attempts > self.options.retries {
+                return Poll::Ready(Err(SysExTransactionError::TimedOut { attempts: transaction.attempts }));
+            }
+            debug!("sysex transaction: no reply after attempt {}, resending", transaction.attempts);
+            if let Err(err) = self.send(transaction) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        Poll::Pending
+    }
+
+    /// Block until `transaction` finishes.
+    pub fn wait<M>(&mut self, transaction: &mut SysExTransaction<M>) -> Result<MidiMessage, SysExTransactionError>
+    where
+        M: FnMut(&MidiMessage) -> bool,
+    {
+        loop {
+            match self.step(transaction) {
+                Poll::Ready(result) => return result,
+                Poll::Pending => std::thread::sleep(self.options.poll_interval),
+            }
+        }
+    }
+
+    /// [`begin`](Self::begin) then [`wait`](Self::wait).
+    pub fn transact<M>(&mut self, request: &MidiMessage, matcher: M) -> Result<MidiMessage, SysExTransactionError>
+    where
+        M: FnMut(&MidiMessage) -> bool,
+    {
+        let mut transaction = self.begin(request, matcher)?;
+        self.wait(&mut transaction)
+    }
+
+    /// A future finishing with `transaction`. Dropping it abandons the
+    /// transaction; it works on any executor.
+    pub fn wait_async<M>(&mut self, transaction: SysExTransaction<M>) -> SysExTransactionFuture<'_, 'p, 'lib, C, M>
+    where
+        M: FnMut(&MidiMessage) -> bool + Unpin,
+    {
+        SysExTransactionFuture { session: self, transaction }
+    }
+
+    /// [`begin`](Self::begin) then [`wait_async`](Self::wait_async).
+    pub async fn transact_async<M>(&mut self, request: &MidiMessage, matcher: M) -> Result<MidiMessage, SysExTransactionError>
+    where
+        M: FnMut(&MidiMessage) -> bool + Unpin,
+    {
+        let transaction = self.begin(request, matcher)?;
+        self.wait_async(transaction).await
+    }
+
+    fn send<M>(&mut self, transaction: &mut SysExTransaction<M>) -> Result<(), SysExTransactionError> {
+        self.input.send_all(&transaction.request)?;
+        transaction.attempts += 1;
+        transaction.deadline = self.clock.now_nanos() + self.options.timeout.as_nanos() as i64;
+        Ok(())
+    }
+
+    /// Drain the output port up to the first message `matcher` accepts,
+    /// stashing everything else.
+    fn receive(&mut self, matcher: &mut impl FnMut(&MidiMessage) -> bool) -> Result<Option<MidiMessage>, SysExTransactionError> {
+        let mut buffer = [0u8; AMIDI_MAX_PACKET_PAYLOAD];
+        loop {
+            match self.output.receive_packet(&mut buffer).map_err(SysExTransactionError::Receive)? {
+                None => return Ok(None),
+                Some(ReceivedPacket::Data { bytes, .. }) => {
+                    let mut messages = Vec::new();
+                    self.parser.feed(bytes, |message| messages.push(message));
+                    let mut reply = None;
+                    for message in messages {
+                        if reply.is_none() && matcher(&message) {
+                            reply = Some(message);
+                        } else {
+                            if self.unsolicited.len() == MAX_UNSOLICITED {
+                                self.unsolicited.pop_front();
+                            }
+                            self.unsolicited.push_back(message);
+                        }
+                    }
+                    if reply.is_some() {
+                        return Ok(reply);
+                    }
+                }
+                Some(ReceivedPacket::Flush) => self.parser.reset(),
+            }
+        }
+    }
+}
+
+/// See [`SysExSession::wait_async`].
+pub struct SysExTransactionFuture<'s, 'p, 'lib, C: MonotonicClock, M> {
+    session:     &'s mut SysExSession<'p, 'lib, C>,
+    transaction: SysExTransaction<M>,
+}
+
+impl<C: MonotonicClock, M> Future for SysExTransactionFuture<'_, '_, '_, C, M>
+where
+    M: FnMut(&MidiMessage) -> bool + Unpin,
+{
+    type Output = Result<MidiMessage, SysExTransactionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let poll = this.session.step(&mut this.transaction);
+        if poll.is_pending() {
+            wake_after(this.session.options.poll_interval, cx.waker().clone());
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test_sysex_transaction {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::task::{Wake, Waker};
+
+    const DUMP_REQUEST: [u8; 5] = [0x43, 0x20, 0x7f, 0x1c, 0x00];
+
+    /// A device answering its `n`th dump request (counting from 1), and
+    /// sending a note before every answer.
+    fn scripted_device(answer_on: usize) -> (VirtualMidiDevice, Arc<AtomicUsize>) {
+        let virtual_device = VirtualMidiDevice::new(1, 1);
+        let replies  = virtual_device.output_port(0);
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count    = requests.clone();
+        virtual_device.input_port(0).set_responder(move |packet| {
+            if packet.bytes[1..packet.bytes.len() - 1] == DUMP_REQUEST && count.fetch_add(1, Ordering::SeqCst) + 1 >= answer_on {
+                replies.push(vec![0x90, 60, 100], 0);
+                replies.push(vec![0xf0, 0x43, 0x00, 0x7f, 0x1c, 0x01, 0x02], 0);
+                replies.push(vec![0x03, 0xf7], 0);
+            }
+        });
+        (virtual_device, requests)
+    }
+
+    fn is_dump(message: &MidiMessage) -> bool {
+        matches!(message, MidiMessage::SysEx(data) if data.starts_with(&[0x43, 0x00, 0x7f, 0x1c]))
+    }
+
+    fn request() -> MidiMessage {
+        MidiMessage::sysex(DUMP_REQUEST).unwrap()
+    }
+
+    #[test]
+    fn test_sync_transaction_keeps_unsolicited_messages() {
+        let (virtual_device, _) = scripted_device(1);
+        virtual_device.output_port(0).push(vec![0xf0, 0x43, 0x00, 0x7f, 0x1c, 0x09, 0xf7], 0);
+        let device = virtual_device.open();
+        let (input, output) = (device.open_input_port(0).unwrap(), device.open_output_port(0).unwrap());
+        let mut session = SysExSession::new(&input, &output, SysExTransactionOptions::default(), SystemMonotonicClock);
+
+        let reply = session.transact(&request(), is_dump).unwrap();
+        assert_eq!(reply, MidiMessage::sysex([0x43, 0x00, 0x7f, 0x1c, 0x01, 0x02, 0x03]).unwrap());
+        // The stale dump queued before the request doesn't count as the reply.
+        assert_eq!(session.take_unsolicited(), vec![
+            MidiMessage::sysex([0x43, 0x00, 0x7f, 0x1c, 0x09]).unwrap(),
+            MidiMessage::note_on(Channel::MIN, U7::new(60).unwrap(), U7::new(100).unwrap()),
+        ]);
+    }
+
+    #[test]
+    fn test_retries_timeout_and_cancel() {
+        let (virtual_device, requests) = scripted_device(3);
+        let device = virtual_device.open();
+        let (input, output) = (device.open_input_port(0).unwrap(), device.open_output_port(0).unwrap());
+        let clock   = VirtualMonotonicClock::starting_at(0);
+        let options = SysExTransactionOptionsBuilder::default().retries(1u32).build().unwrap();
+        let mut session = SysExSession::new(&input, &output, options, clock.clone());
+
+        let mut transaction = session.begin(&request(), is_dump).unwrap();
+        assert!(session.step(&mut transaction).is_pending());
+        clock.advance(1_000_000_000);
+        assert!(session.step(&mut transaction).is_pending());
+        assert_eq!(transaction.attempts(), 2);
+        clock.advance(1_000_000_000);
+        assert_eq!(session.step(&mut transaction), Poll::Ready(Err(SysExTransactionError::TimedOut { attempts: 2 })));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // The third request is answered.
+        let mut transaction = session.begin(&request(), is_dump).unwrap();
+        assert!(matches!(session.step(&mut transaction), Poll::Ready(Ok(_))));
+
+        let mut transaction = session.begin(&request(), |_: &MidiMessage| false).unwrap();
+        transaction.cancel_token().cancel();
+        assert_eq!(session.wait(&mut transaction), Err(SysExTransactionError::Cancelled));
+    }
+
+    #[test]
+    fn test_async_transaction_retries_until_answered() {
+        struct ThreadWaker(std::thread::Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let waker   = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+            let mut cx  = Context::from_waker(&waker);
+            let mut future = std::pin::pin!(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                std::thread::park();
+            }
+        }
+
+        let (virtual_device, requests) = scripted_device(2);
+        let device = virtual_device.open();
+        let (input, output) = (device.open_input_port(0).unwrap(), device.open_output_port(0).unwrap());
+        let options = SysExTransactionOptionsBuilder::default().timeout(Duration::from_millis(20)).build().unwrap();
+        let mut session = SysExSession::new(&input, &output, options, SystemMonotonicClock);
+
+        let reply = block_on(session.transact_async(&request(), is_dump)).unwrap();
+        assert!(is_dump(&reply));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,235 @@
+// ---------------- [ File: basic-android-integration/src/universal_sysex.rs ]
+crate::ix!();
+
+/// First data byte of Universal Non-Real Time and Real Time SysEx.
+pub const UNIVERSAL_NON_REAL_TIME: u8 = 0x7e;
+pub const UNIVERSAL_REAL_TIME:     u8 = 0x7f;
+
+/// Device id every device answers to.
+pub const ALL_CALL: U7 = U7::MAX;
+
+/// What a device says about itself in an Identity Reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdentityReply {
+    pub device_id:    U7,
+    pub manufacturer: ManufacturerId,
+    pub family:       U14,
+    pub model:        U14,
+    /// Software revision, format up to the manufacturer.
+    pub version:      [u8; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeneralMidiMode {
+    Gm1On,
+    Off,
+    Gm2On,
+}
+
+/// The Universal SysEx messages nearly every integration needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UniversalSysEx {
+    IdentityRequest { device_id: U7 },
+    IdentityReply(IdentityReply),
+    GeneralMidi { device_id: U7, mode: GeneralMidiMode },
+    MasterVolume { device_id: U7, volume: U14 },
+    /// `U14::CENTER` is centred.
+    MasterBalance { device_id: U7, balance: U14 },
+}
+
+impl UniversalSysEx {
+    pub fn identity_request() -> Self {
+        UniversalSysEx::IdentityRequest { device_id: ALL_CALL }
+    }
+
+    pub fn device_id(&self) -> U7 {
+        match self {
+            UniversalSysEx::IdentityReply(reply) => reply.device_id,
+            UniversalSysEx::IdentityRequest { device_id }
+            | UniversalSysEx::GeneralMidi { device_id, .. }
+            | UniversalSysEx::MasterVolume { device_id, .. }
+            | UniversalSysEx::MasterBalance { device_id, .. } => *device_id,
+        }
+    }
+
+    /// SysEx data, without `F0`/`F7`. Fails if the manufacturer id or
+    /// version in an identity reply isn't 7-bit.
+    pub fn to_sysex(&self) -> Result<Vec<u8>, MidiValueError> {
+        Ok(SysExData::new(self.encode())?.into_vec())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let device_id = self.device_id().get();
+        match self {
+            UniversalSysEx::IdentityRequest { .. } => vec![UNIVERSAL_NON_REAL_TIME, device_id, 0x06, 0x01],
+            UniversalSysEx::IdentityReply(reply) => {
+                let mut out = vec![UNIVERSAL_NON_REAL_TIME, device_id, 0x06, 0x02];
+                out.extend(reply.manufacturer.to_bytes());
+                for value in [reply.family, reply.model] {
+                    out.extend_from_slice(&[value.lsb().get(), value.msb().get()]);
+                }
+                out.extend_from_slice(&reply.version);
+                out
+            }
+            UniversalSysEx::GeneralMidi { mode, .. } => {
+                let code = match mode {
+                    GeneralMidiMode::Gm1On => 0x01,
+                    GeneralMidiMode::Off => 0x02,
+                    GeneralMidiMode::Gm2On => 0x03,
+                };
+                vec![UNIVERSAL_NON_REAL_TIME, device_id, 0x09, code]
+            }
+            UniversalSysEx::MasterVolume { volume: value, .. } | UniversalSysEx::MasterBalance { balance: value, .. } => {
+                let sub_id = if matches!(self, UniversalSysEx::MasterVolume { .. }) { 0x01 } else { 0x02 };
+                vec![UNIVERSAL_REAL_TIME, device_id, 0x04, sub_id, value.lsb().get(), value.msb().get()]
+            }
+        }
+    }
+
+    /// `None` for anything that is not one of these messages.
+    pub fn from_sysex(data: &[u8]) -> Option<Self> {
+        let u14 = |lsb: u8, msb: u8| Some(U14::from_msb_lsb(U7::new(msb).ok()?, U7::new(lsb).ok()?));
+        let (kind, device_id, rest) = match data {
+            [kind, device_id, rest @ ..] => (*kind, U7::new(*device_id).ok()?, rest),
+            _ => return None,
+        };
+        Some(match (kind, rest) {
+            (UNIVERSAL_NON_REAL_TIME, [0x06, 0x01]) => UniversalSysEx::IdentityRequest { device_id },
+            (UNIVERSAL_NON_REAL_TIME, [0x06, 0x02, rest @ ..]) => {
+                let (manufacturer, rest) = ManufacturerId::parse(rest)?;
+                let [f_lsb, f_msb, m_lsb, m_msb, v0, v1, v2, v3] = *rest else {
+                    return None;
+                };
+                UniversalSysEx::IdentityReply(IdentityReply {
+                    device_id,
+                    manufacturer,
+                    family:  u14(f_lsb, f_msb)?,
+                    model:   u14(m_lsb, m_msb)?,
+                    version: [v0, v1, v2, v3],
+                })
+            }
+            (UNIVERSAL_NON_REAL_TIME, [0x09, code]) => UniversalSysEx::GeneralMidi {
+                device_id,
+                mode: match code {
+                    0x01 => GeneralMidiMode::Gm1On,
+                    0x02 => GeneralMidiMode::Off,
+                    0x03 => GeneralMidiMode::Gm2On,
+                    _ => return None,
+                },
+            },
+            (UNIVERSAL_REAL_TIME, [0x04, 0x01, lsb, msb]) => UniversalSysEx::MasterVolume { device_id, volume: u14(*lsb, *msb)? },
+            (UNIVERSAL_REAL_TIME, [0x04, 0x02, lsb, msb]) => UniversalSysEx::MasterBalance { device_id, balance: u14(*lsb, *msb)? },
+            _ => return None,
+        })
+    }
+
+    pub fn from_message(message: &MidiMessage) -> Option<Self> {
+        match message {
+            MidiMessage::SysEx(data) => Self::from_sysex(data),
+            _ => None,
+        }
+    }
+
+    pub fn to_message(&self) -> Result<MidiMessage, MidiValueError> {
+        MidiMessage::sysex(self.to_sysex()?)
+    }
+
+    pub fn send(&self, port: &MidiInputPort) -> Result<usize, SysExSendError> {
+        Ok(port.send_all(&self.to_message()?.to_bytes())?)
+    }
+}
+
+/// Picks [`UniversalSysEx`] messages out of a port's stream, reassembling
+/// SysEx split across packets.
+#[derive(Debug, Clone, Default)]
+pub struct UniversalSysExReceiver {
+    parser: MidiStreamParser,
+}
+
+impl UniversalSysExReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut sink: impl FnMut(UniversalSysEx)) {
+        self.parser.feed(bytes, |message| {
+            if let Some(universal) = UniversalSysEx::from_message(&message) {
+                sink(universal);
+            }
+        });
+    }
+
+    /// Pick universal messages out of everything pending on `port`.
+    pub fn poll(&mut self, port: &MidiOutputPort, mut sink: impl FnMut(UniversalSysEx)) -> Result<usize, isize> {
+        port.drain(|packet| match packet {
+            ReceivedPacket::Data { bytes, .. } => self.feed(bytes, &mut sink),
+            ReceivedPacket::Flush => self.parser.reset(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_universal_sysex {
+    use super::*;
+
+    #[test]
+    fn test_identity_request_and_reply() {
+        let virtual_device = VirtualMidiDevice::new(1, 1);
+        let replies = virtual_device.output_port(0);
+        virtual_device.input_port(0).set_responder(move |packet| {
+            if packet.bytes == [0xf0, 0x7e, 0x7f, 0x06, 0x01, 0xf7] {
+                // A Roland device, id 0x10, split over two packets.
+                replies.push(vec![0xf0, 0x7e, 0x10, 0x06, 0x02, 0x41], 1);
+                replies.push(vec![0x2b, 0x02, 0x02, 0x00, 0x00, 0x01, 0x00, 0x00, 0xf7], 2);
+            }
+        });
+        let device = virtual_device.open();
+        let input  = device.open_input_port(0).unwrap();
+        let output = device.open_output_port(0).unwrap();
+
+        UniversalSysEx::identity_request().send(&input).unwrap();
+        let mut received = Vec::new();
+        UniversalSysExReceiver::new().poll(&output, |message| received.push(message)).unwrap();
+
+        let reply = IdentityReply {
+            device_id:    U7::new(0x10).unwrap(),
+            manufacturer: ManufacturerId::ROLAND,
+            family:       U14::new(0x2b | 0x02 << 7).unwrap(),
+            model:        U14::new(0x02).unwrap(),
+            version:      [0, 1, 0, 0],
+        };
+        assert_eq!(received, vec![UniversalSysEx::IdentityReply(reply)]);
+        assert_eq!(reply.manufacturer.to_string(), "Roland (41)");
+    }
+
+    #[test]
+    fn test_gm_and_master_messages_round_trip() {
+        let extended = ManufacturerId::by_name("behringer").unwrap();
+        assert_eq!(ManufacturerId::parse(&[0x00, 0x20, 0x32, 0x01]), Some((extended, &[0x01][..])));
+
+        for message in [
+            UniversalSysEx::GeneralMidi { device_id: ALL_CALL, mode: GeneralMidiMode::Gm2On },
+            UniversalSysEx::MasterVolume { device_id: ALL_CALL, volume: U14::MAX },
+            UniversalSysEx::MasterBalance { device_id: U7::MIN, balance: U14::CENTER },
+            UniversalSysEx::IdentityReply(IdentityReply {
+                device_id:    U7::MIN,
+                manufacturer: extended,
+                family:       U14::MIN,
+                model:        U14::CENTER,
+                version:      [1, 2, 3, 4],
+            }),
+        ] {
+            assert_eq!(UniversalSysEx::from_sysex(&message.to_sysex().unwrap()), Some(message));
+        }
+        assert_eq!(UniversalSysEx::MasterVolume { device_id: ALL_CALL, volume: U14::MAX }.to_message().unwrap().to_bytes(), vec![0xf0, 0x7f, 0x7f, 0x04, 0x01, 0x7f, 0x7f, 0xf7]);
+
+        let bad_reply = IdentityReply {
+            device_id:    U7::MIN,
+            manufacturer: ManufacturerId::Short(0x80),
+            family:       U14::MIN,
+            model:        U14::MIN,
+            version:      [0; 4],
+        };
+        assert_eq!(UniversalSysEx::IdentityReply(bad_reply).to_message(), Err(MidiValueError::SysExDataByte { index: 4, byte: 0x80 }));
+    }
+}
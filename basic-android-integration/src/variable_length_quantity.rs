@@ -0,0 +1,94 @@
+// ---------------- [ File: basic-android-integration/src/variable_length_quantity.rs ]
+crate::ix!();
+
+/// Largest value a four-byte variable-length quantity can carry.
+pub const VLQ_MAX: u32 = 0x0fff_ffff;
+
+/// Append `value` as a MIDI variable-length quantity: 7 bits per byte, most
+/// significant group first, high bit set on all but the last byte.
+/// Values above [`VLQ_MAX`] are rejected, with `out.len()` as the offset.
+pub fn write_vlq(value: u32, out: &mut Vec<u8>) -> Result<(), SmfError> {
+    if value > VLQ_MAX {
+        let kind = SmfErrorKind::ValueOutOfRange { what: "variable-length quantity", value: value as u64, max: VLQ_MAX as u64 };
+        return Err(SmfError::new(out.len(), kind));
+    }
+    let mut groups = [0u8; 4];
+    let mut n = 0;
+    let mut v = value;
+    loop {
+        groups[n] = (v & 0x7f) as u8;
+        n += 1;
+        v >>= 7;
+        if v == 0 {
+            break;
+        }
+    }
+    for i in (0..n).rev() {
+        out.push(if i > 0 { groups[i] | 0x80 } else { groups[i] });
+    }
+    Ok(())
+}
+
+/// Read a variable-length quantity at `*pos`, advancing `*pos` past it.
+pub fn read_vlq(bytes: &[u8], pos: &mut usize) -> Result<u32, SmfError> {
+    let start = *pos;
+    let mut value = 0u32;
+    for i in 0..4 {
+        let byte = *bytes
+            .get(*pos)
+            .ok_or_else(|| SmfError::new(*pos, SmfErrorKind::UnexpectedEof))?;
+        *pos += 1;
+        value = (value << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        if i == 3 {
+            break;
+        }
+    }
+    Err(SmfError::new(start, SmfErrorKind::VlqTooLong))
+}
+
+#[cfg(test)]
+mod test_variable_length_quantity {
+    use super::*;
+
+    #[test]
+    fn test_spec_examples() {
+        for (value, encoded) in [
+            (0x00u32, vec![0x00u8]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x81, 0x00]),
+            (0x2000, vec![0xc0, 0x00]),
+            (0x3fff, vec![0xff, 0x7f]),
+            (0x10_0000, vec![0xc0, 0x80, 0x00]),
+            (VLQ_MAX, vec![0xff, 0xff, 0xff, 0x7f]),
+        ] {
+            let mut out = Vec::new();
+            write_vlq(value, &mut out).unwrap();
+            assert_eq!(out, encoded);
+            let mut pos = 0;
+            assert_eq!(read_vlq(&encoded, &mut pos), Ok(value));
+            assert_eq!(pos, encoded.len());
+        }
+    }
+
+    #[test]
+    fn test_too_large_value_is_rejected() {
+        let mut out = vec![0x00];
+        assert_eq!(
+            write_vlq(VLQ_MAX + 1, &mut out),
+            Err(SmfError::new(1, SmfErrorKind::ValueOutOfRange { what: "variable-length quantity", value: 0x1000_0000, max: 0x0fff_ffff }))
+        );
+        assert_eq!(out, vec![0x00]);
+    }
+
+    #[test]
+    fn test_five_byte_quantity_is_rejected() {
+        let mut pos = 0;
+        assert_eq!(
+            read_vlq(&[0x81, 0x81, 0x81, 0x81, 0x01], &mut pos),
+            Err(SmfError::new(0, SmfErrorKind::VlqTooLong))
+        );
+    }
+}
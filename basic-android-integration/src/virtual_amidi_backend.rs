@@ -0,0 +1,390 @@
+// ---------------- [ File: basic-android-integration/src/virtual_amidi_backend.rs ]
+crate::ix!();
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// `AMidiOutputPort_receive` opcode for a data packet.
+pub const AMIDI_OPCODE_DATA:  i32 = 1;
+/// `AMidiOutputPort_receive` opcode for a flush notification.
+pub const AMIDI_OPCODE_FLUSH: i32 = 2;
+
+/// An in-process MIDI device that plugs into the same `AmidiLibrary` function
+/// pointer table as libamidi.
+///
+/// `MidiDevice`, `MidiInputPort` and `MidiOutputPort` built on top of it run
+/// exactly the code they run on android, so everything layered on the ports
+/// can be exercised on a Linux host: bytes sent to an input port are recorded
+/// (and optionally answered by a responder), packets queued on an output port
+/// are handed out by `receive`.
+#[derive(Debug, Clone)]
+pub struct VirtualMidiDevice {
+    state: Arc<VirtualDeviceState>,
+}
+
+#[derive(Debug)]
+struct VirtualDeviceState {
+    device_type: i32,
+    inputs:      Vec<Arc<VirtualInputPort>>,
+    outputs:     Vec<Arc<VirtualOutputPort>>,
+}
+
+impl VirtualMidiDevice {
+    /// `AMIDI_DEVICE_TYPE_USB` is 1.
+    pub const DEFAULT_DEVICE_TYPE: i32 = 1;
+
+    pub fn new(num_input_ports: usize, num_output_ports: usize) -> Self {
+        Self {
+            state: Arc::new(VirtualDeviceState {
+                device_type: Self::DEFAULT_DEVICE_TYPE,
+                inputs:      (0..num_input_ports).map(|_| Arc::new(VirtualInputPort::default())).collect(),
+                outputs:     (0..num_output_ports).map(|_| Arc::new(VirtualOutputPort::default())).collect(),
+            }),
+        }
+    }
+
+    /// The port we send to, as seen from the device side.
+    pub fn input_port(&self, port_number: usize) -> Arc<VirtualInputPort> {
+        self.state.inputs[port_number].clone()
+    }
+
+    /// The port we receive from, as seen from the device side.
+    pub fn output_port(&self, port_number: usize) -> Arc<VirtualOutputPort> {
+        self.state.outputs[port_number].clone()
+    }
+
+    /// Open this device through the virtual `AmidiLibrary`, the same way
+    /// `MidiDevice::from_java` opens a real one.
+    pub fn open(&self) -> MidiDevice<'static> {
+        let obj = Arc::as_ptr(&self.state) as jobject;
+        // SAFETY: the virtual AMidiDevice_fromJava only reads `obj`, which
+        // points at our device state, and never touches `env`.
+        unsafe { MidiDevice::from_java(virtual_amidi_library(), std::ptr::null_mut(), obj) }
+            .expect("virtual AMidiDevice_fromJava cannot fail")
+    }
+}
+
+/// One packet the host sent to a [`VirtualInputPort`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualSentPacket {
+    pub bytes:     Vec<u8>,
+    /// `None` for `send`, `Some` for `send_with_timestamp`.
+    pub timestamp: Option<i64>,
+}
+
+/// Scripted answer to one send call on a [`VirtualInputPort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualSendResult {
+    /// Accept at most this many bytes (0 simulates a full pipe).
+    AcceptUpTo(usize),
+    /// Fail with this (negative) status.
+    Fail(isize),
+}
+
+type VirtualResponder = Box<dyn Fn(&VirtualSentPacket) + Send + Sync>;
+
+/// Device-side view of a port the host sends to.
+#[derive(Default)]
+pub struct VirtualInputPort {
+    sent:      Mutex<Vec<VirtualSentPacket>>,
+    script:    Mutex<VecDeque<VirtualSendResult>>,
+    flushes:   Mutex<usize>,
+    responder: Mutex<Option<VirtualResponder>>,
+}
+
+impl std::fmt::Debug for VirtualInputPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualInputPort")
+            .field("sent", &self.sent)
+            .field("script", &self.script)
+            .field("flushes", &self.flushes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtualInputPort {
+    /// Every packet accepted so far (only the accepted prefix of partial sends).
+    pub fn sent(&self) -> Vec<VirtualSentPacket> {
+        lock(&self.sent).clone()
+    }
+
+    /// All accepted bytes concatenated.
+    pub fn sent_bytes(&self) -> Vec<u8> {
+        lock(&self.sent).iter().flat_map(|p| p.bytes.iter().copied()).collect()
+    }
+
+    pub fn clear_sent(&self) {
+        lock(&self.sent).clear();
+    }
+
+    pub fn flush_count(&self) -> usize {
+        *lock(&self.flushes)
+    }
+
+    /// Queue answers for the next send calls; once the script runs out every
+    /// send is accepted in full.
+    pub fn script_sends(&self, results: impl IntoIterator<Item = VirtualSendResult>) {
+        lock(&self.script).extend(results);
+    }
+
+    /// Run `responder` on every accepted packet, e.g. to push a reply onto
+    /// one of the device's output ports.
+    pub fn set_responder(&self, responder: impl Fn(&VirtualSentPacket) + Send + Sync + 'static) {
+        *lock(&self.responder) = Some(Box::new(responder));
+    }
+
+    fn accept(&self, bytes: &[u8], timestamp: Option<i64>) -> isize {
+        let accepted = match lock(&self.script).pop_front() {
+            Some(VirtualSendResult::Fail(code))      => return code,
+            Some(VirtualSendResult::AcceptUpTo(max)) => bytes.len().min(max),
+            None                                     => bytes.len(),
+        };
+        if accepted > 0 {
+            let packet = VirtualSentPacket { bytes: bytes[..accepted].to_vec(), timestamp };
+            lock(&self.sent).push(packet.clone());
+            if let Some(responder) = lock(&self.responder).as_ref() {
+                responder(&packet);
+            }
+        }
+        accepted as isize
+    }
+}
+
+/// One packet waiting to be received from a [`VirtualOutputPort`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualReceivedPacket {
+    pub opcode:    i32,
+    pub bytes:     Vec<u8>,
+    pub timestamp: i64,
+}
+
+/// Device-side view of a port the host receives from.
+#[derive(Debug, Default)]
+pub struct VirtualOutputPort {
+    pending: Mutex<VecDeque<VirtualReceivedPacket>>,
+}
+
+impl VirtualOutputPort {
+    /// Queue a data packet for the host's next `receive`.
+    pub fn push(&self, bytes: impl Into<Vec<u8>>, timestamp: i64) {
+        self.push_packet(VirtualReceivedPacket { opcode: AMIDI_OPCODE_DATA, bytes: bytes.into(), timestamp });
+    }
+
+    pub fn push_packet(&self, packet: VirtualReceivedPacket) {
+        lock(&self.pending).push_back(packet);
+    }
+
+    pub fn pending(&self) -> usize {
+        lock(&self.pending).len()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// An `AmidiLibrary` whose function pointers drive [`VirtualMidiDevice`]s
+/// instead of libamidi.
+pub fn virtual_amidi_library() -> Arc<AmidiLibrary> {
+    Arc::new(AmidiLibrary {
+        library: None,
+
+        amidi_device_from_java:               virtual_device_from_java,
+        amidi_device_release:                 virtual_device_release,
+        amidi_device_get_type:                virtual_device_get_type,
+        amidi_device_get_num_input_ports:     virtual_device_get_num_input_ports,
+        amidi_device_get_num_output_ports:    virtual_device_get_num_output_ports,
+        amidi_device_get_default_protocol:    virtual_device_get_default_protocol,
+
+        amidi_output_port_open:               virtual_output_port_open,
+        amidi_output_port_close:              virtual_output_port_close,
+        amidi_output_port_receive:            virtual_output_port_receive,
+
+        amidi_input_port_open:                virtual_input_port_open,
+        amidi_input_port_send:                virtual_input_port_send,
+        amidi_input_port_send_with_timestamp: virtual_input_port_send_with_timestamp,
+        amidi_input_port_send_flush:          virtual_input_port_send_flush,
+        amidi_input_port_close:               virtual_input_port_close,
+    })
+}
+
+// The raw pointers handed out below are `Arc::into_raw` of the virtual state,
+// so the state outlives the `MidiDevice`/port even if the `VirtualMidiDevice`
+// handle is dropped first. release/close give the reference back.
+
+const AMEDIA_OK: media_status_t = media_status_t(0);
+/// `AMEDIA_ERROR_INVALID_PARAMETER`
+const AMEDIA_ERROR_INVALID_PARAMETER: media_status_t = media_status_t(-10010);
+
+unsafe fn device_state<'a>(device: *const AMidiDevice) -> &'a VirtualDeviceState {
+    unsafe { &*(device as *const VirtualDeviceState) }
+}
+
+unsafe extern "C" fn virtual_device_from_java(
+    _env:           *mut JNIEnv,
+    midi_device_obj: jobject,
+    out_device_ptr: *mut *mut AMidiDevice,
+) -> media_status_t {
+    let state = midi_device_obj as *const VirtualDeviceState;
+    unsafe {
+        Arc::increment_strong_count(state);
+        *out_device_ptr = state as *mut AMidiDevice;
+    }
+    AMEDIA_OK
+}
+
+unsafe extern "C" fn virtual_device_release(midi_device: *const AMidiDevice) -> media_status_t {
+    unsafe { drop(Arc::from_raw(midi_device as *const VirtualDeviceState)) };
+    AMEDIA_OK
+}
+
+unsafe extern "C" fn virtual_device_get_type(device: *const AMidiDevice) -> i32 {
+    unsafe { device_state(device).device_type }
+}
+
+unsafe extern "C" fn virtual_device_get_num_input_ports(device: *const AMidiDevice) -> isize {
+    unsafe { device_state(device).inputs.len() as isize }
+}
+
+unsafe extern "C" fn virtual_device_get_num_output_ports(device: *const AMidiDevice) -> isize {
+    unsafe { device_state(device).outputs.len() as isize }
+}
+
+unsafe extern "C" fn virtual_device_get_default_protocol(_device: *const AMidiDevice) -> AMidiDevice_Protocol {
+    AMidiDevice_Protocol::AMIDI_DEVICE_PROTOCOL_UNKNOWN
+}
+
+unsafe extern "C" fn virtual_output_port_open(
+    device:       *const AMidiDevice,
+    port_number:  i32,
+    out_port_ptr: *mut *mut AMidiOutputPort,
+) -> media_status_t {
+    let state = unsafe { device_state(device) };
+    match usize::try_from(port_number).ok().and_then(|n| state.outputs.get(n)) {
+        Some(port) => {
+            unsafe { *out_port_ptr = Arc::into_raw(port.clone()) as *mut AMidiOutputPort };
+            AMEDIA_OK
+        }
+        None => AMEDIA_ERROR_INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "C" fn virtual_output_port_close(output_port: *const AMidiOutputPort) {
+    unsafe { drop(Arc::from_raw(output_port as *const VirtualOutputPort)) };
+}
+
+unsafe extern "C" fn virtual_output_port_receive(
+    output_port:            *const AMidiOutputPort,
+    opcode_ptr:             *mut i32,
+    buffer:                 *mut u8,
+    max_bytes:              usize,
+    num_bytes_received_ptr: *mut usize,
+    out_timestamp_ptr:      *mut i64,
+) -> isize {
+    let port = unsafe { &*(output_port as *const VirtualOutputPort) };
+    let Some(packet) = lock(&port.pending).pop_front() else {
+        unsafe { *num_bytes_received_ptr = 0 };
+        return 0;
+    };
+    let n = packet.bytes.len().min(max_bytes);
+    unsafe {
+        std::ptr::copy_nonoverlapping(packet.bytes.as_ptr(), buffer, n);
+        *opcode_ptr = packet.opcode;
+        *num_bytes_received_ptr = n;
+        *out_timestamp_ptr = packet.timestamp;
+    }
+    1
+}
+
+unsafe extern "C" fn virtual_input_port_open(
+    device:       *const AMidiDevice,
+    port_number:  i32,
+    out_port_ptr: *mut *mut AMidiInputPort,
+) -> media_status_t {
+    let state = unsafe { device_state(device) };
+    match usize::try_from(port_number).ok().and_then(|n| state.inputs.get(n)) {
+        Some(port) => {
+            unsafe { *out_port_ptr = Arc::into_raw(port.clone()) as *mut AMidiInputPort };
+            AMEDIA_OK
+        }
+        None => AMEDIA_ERROR_INVALID_PARAMETER,
+    }
+}
+
+unsafe fn input_port<'a>(input_port: *const AMidiInputPort) -> &'a VirtualInputPort {
+    unsafe { &*(input_port as *const VirtualInputPort) }
+}
+
+unsafe extern "C" fn virtual_input_port_send(
+    input_port_ptr: *const AMidiInputPort,
+    buffer:         *const u8,
+    num_bytes:      usize,
+) -> isize {
+    unsafe { input_port(input_port_ptr).accept(std::slice::from_raw_parts(buffer, num_bytes), None) }
+}
+
+unsafe extern "C" fn virtual_input_port_send_with_timestamp(
+    input_port_ptr: *const AMidiInputPort,
+    buffer:         *const u8,
+    num_bytes:      usize,
+    timestamp:      i64,
+) -> isize {
+    unsafe { input_port(input_port_ptr).accept(std::slice::from_raw_parts(buffer, num_bytes), Some(timestamp)) }
+}
+
+unsafe extern "C" fn virtual_input_port_send_flush(input_port_ptr: *const AMidiInputPort) -> media_status_t {
+    unsafe { *lock(&input_port(input_port_ptr).flushes) += 1 };
+    AMEDIA_OK
+}
+
+unsafe extern "C" fn virtual_input_port_close(input_port_ptr: *const AMidiInputPort) {
+    unsafe { drop(Arc::from_raw(input_port_ptr as *const VirtualInputPort)) };
+}
+
+#[cfg(test)]
+mod test_virtual_amidi_backend {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_real_port_types() {
+        let virtual_device = VirtualMidiDevice::new(1, 1);
+        let device = virtual_device.open();
+        assert_eq!(device.num_input_ports(), 1);
+        assert_eq!(device.num_output_ports(), 1);
+
+        let input = device.open_input_port(0).expect("input port opens");
+        assert_eq!(input.send(&[0x90, 60, 100]), Ok(3));
+        assert_eq!(input.send_with_timestamp(&[0x80, 60, 0], 42), Ok(3));
+        assert_eq!(
+            virtual_device.input_port(0).sent(),
+            vec![
+                VirtualSentPacket { bytes: vec![0x90, 60, 100], timestamp: None },
+                VirtualSentPacket { bytes: vec![0x80, 60, 0], timestamp: Some(42) },
+            ]
+        );
+
+        let output = device.open_output_port(0).expect("output port opens");
+        virtual_device.output_port(0).push(vec![0xf8], 7);
+
+        let mut opcode    = 0;
+        let mut buffer    = [0u8; 16];
+        let mut received  = 0;
+        let mut timestamp = 0;
+        output.receive(&mut opcode, &mut buffer, &mut received, &mut timestamp).unwrap();
+        assert_eq!((opcode, &buffer[..received], timestamp), (AMIDI_OPCODE_DATA, &[0xf8u8][..], 7));
+    }
+
+    #[test]
+    fn test_open_port_validation() {
+        let virtual_device = VirtualMidiDevice::new(2, 0);
+        let device = virtual_device.open();
+
+        let _port = device.open_input_port(1).unwrap();
+        assert!(matches!(device.open_input_port(1), Err(MidiPortError::AlreadyOpen { port_number: 1, .. })));
+        assert!(matches!(device.open_input_port(2), Err(MidiPortError::PortOutOfRange { num_ports: 2, .. })));
+        assert!(matches!(device.open_output_port(0), Err(MidiPortError::PortOutOfRange { num_ports: 0, .. })));
+
+        let states: Vec<_> = device.ports().map(|p| (p.direction(), p.port_number(), p.open())).collect();
+        assert_eq!(states, vec![(MidiPortDirection::Input, 0, false), (MidiPortDirection::Input, 1, true)]);
+    }
+}
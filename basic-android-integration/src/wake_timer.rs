@@ -0,0 +1,79 @@
+// ---------------- [ File: basic-android-integration/src/wake_timer.rs ]
+crate::ix!();
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::task::Waker;
+use std::time::Instant;
+
+/// A waker due at `at`; `seq` keeps the heap ordering total.
+struct TimerEntry {
+    at:    Instant,
+    seq:   u64,
+    waker: Waker,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// Wake `waker` after `delay`, from one shared background thread.
+///
+/// AMidi has no readiness notification, so futures over its ports poll:
+/// they check the port and ask to be polled again shortly. This keeps that
+/// independent of any particular async runtime.
+pub(crate) fn wake_after(delay: std::time::Duration, waker: Waker) {
+    static TIMER: OnceLock<Sender<(Instant, Waker)>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<(Instant, Waker)>();
+        std::thread::Builder::new()
+            .name("midi-wake-timer".into())
+            .spawn(move || {
+                let mut due = BinaryHeap::new();
+                let mut seq = 0;
+                loop {
+                    let now = Instant::now();
+                    while due.peek().is_some_and(|Reverse(entry): &Reverse<TimerEntry>| entry.at <= now) {
+                        let Reverse(entry) = due.pop().unwrap();
+                        entry.waker.wake();
+                    }
+                    let next = match due.peek() {
+                        Some(Reverse(entry)) => receiver.recv_timeout(entry.at - now),
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    match next {
+                        Ok((at, waker)) => {
+                            seq += 1;
+                            due.push(Reverse(TimerEntry { at, seq, waker }));
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+            .expect("spawning the MIDI wake timer thread");
+        sender
+    });
+    if timer.send((Instant::now() + delay, waker.clone())).is_err() {
+        // The timer thread is gone; a spurious immediate wake beats a hang.
+        waker.wake();
+    }
+}
@@ -0,0 +1,65 @@
+// ---------------- [ File: xtask/src/android_abi.rs ]
+crate::ix!();
+
+/// The Android ABIs gradle understands under `src/main/jniLibs/<abi>/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AndroidAbi {
+    #[serde(rename = "armeabi-v7a")]
+    ArmeabiV7a,
+    #[serde(rename = "arm64-v8a")]
+    Arm64V8a,
+    #[serde(rename = "x86")]
+    X86,
+    #[serde(rename = "x86_64")]
+    X86_64,
+}
+
+impl AndroidAbi {
+    pub const ALL: [AndroidAbi; 4] = [
+        AndroidAbi::ArmeabiV7a,
+        AndroidAbi::Arm64V8a,
+        AndroidAbi::X86,
+        AndroidAbi::X86_64,
+    ];
+
+    /// The directory name gradle expects under `jniLibs/`.
+    pub fn jni_dir_name(&self) -> &'static str {
+        match self {
+            AndroidAbi::ArmeabiV7a => "armeabi-v7a",
+            AndroidAbi::Arm64V8a   => "arm64-v8a",
+            AndroidAbi::X86        => "x86",
+            AndroidAbi::X86_64     => "x86_64",
+        }
+    }
+
+    /// The rust target triple cargo-ndk builds for this ABI.
+    pub fn rust_triple(&self) -> &'static str {
+        match self {
+            AndroidAbi::ArmeabiV7a => "armv7-linux-androideabi",
+            AndroidAbi::Arm64V8a   => "aarch64-linux-android",
+            AndroidAbi::X86        => "i686-linux-android",
+            AndroidAbi::X86_64     => "x86_64-linux-android",
+        }
+    }
+
+    /// The folder under `sysroot/usr/lib/` in the NDK holding this ABI's libraries.
+    /// Rust says armv7, the NDK folder says arm.
+    pub fn ndk_sysroot_subdir(&self) -> &'static str {
+        match self {
+            AndroidAbi::ArmeabiV7a => "arm-linux-androideabi",
+            AndroidAbi::Arm64V8a   => "aarch64-linux-android",
+            AndroidAbi::X86        => "i686-linux-android",
+            AndroidAbi::X86_64     => "x86_64-linux-android",
+        }
+    }
+
+    pub fn from_jni_dir_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|abi| abi.jni_dir_name() == name)
+    }
+}
+
+impl std::fmt::Display for AndroidAbi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.jni_dir_name())
+    }
+}
@@ -0,0 +1,194 @@
+// ---------------- [ File: xtask/src/elf_needed.rs ]
+crate::ix!();
+
+const PT_LOAD:    u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const DT_NULL:    u64 = 0;
+const DT_NEEDED:  u64 = 1;
+const DT_STRTAB:  u64 = 5;
+
+/// Return the `DT_NEEDED` entries of a little-endian ELF shared object, i.e.
+/// the sonames the dynamic loader will look for when the library is loaded.
+/// Every Android ABI is little-endian, so big-endian objects are rejected.
+pub fn elf_needed_libraries(bytes: &[u8]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if bytes.len() < 0x34 || &bytes[..4] != b"\x7fELF" {
+        return Err("not an ELF file".into());
+    }
+    if bytes[5] != 1 {
+        return Err("big-endian ELF files are not supported".into());
+    }
+
+    let is_64 = match bytes[4] {
+        1 => false,
+        2 => true,
+        c => return Err(format!("unknown ELF class {}", c).into()),
+    };
+
+    let reader = ElfReader { bytes, is_64 };
+
+    let (phoff, phentsize, phnum) = if is_64 {
+        (reader.u64_at(0x20)?, reader.u16_at(0x36)? as u64, reader.u16_at(0x38)? as u64)
+    } else {
+        (reader.u32_at(0x1c)? as u64, reader.u16_at(0x2a)? as u64, reader.u16_at(0x2c)? as u64)
+    };
+
+    let mut loads   = Vec::new();
+    let mut dynamic = None;
+
+    for i in 0..phnum {
+        let ph = phoff + i * phentsize;
+        let p_type = reader.u32_at(ph)?;
+        let (offset, vaddr, filesz) = if is_64 {
+            (reader.u64_at(ph + 8)?, reader.u64_at(ph + 16)?, reader.u64_at(ph + 32)?)
+        } else {
+            (reader.u32_at(ph + 4)? as u64, reader.u32_at(ph + 8)? as u64, reader.u32_at(ph + 16)? as u64)
+        };
+        match p_type {
+            PT_LOAD    => loads.push((offset, vaddr, filesz)),
+            PT_DYNAMIC => dynamic = Some((offset, filesz)),
+            _ => {}
+        }
+    }
+
+    let Some((dyn_offset, dyn_size)) = dynamic else {
+        debug!("ELF file has no PT_DYNAMIC segment; assuming no dependencies");
+        return Ok(Vec::new());
+    };
+
+    let entry_size = if is_64 { 16 } else { 8 };
+    let mut needed_offsets = Vec::new();
+    let mut strtab_vaddr   = None;
+
+    for i in 0..dyn_size / entry_size {
+        let entry = dyn_offset + i * entry_size;
+        let (tag, val) = if is_64 {
+            (reader.u64_at(entry)?, reader.u64_at(entry + 8)?)
+        } else {
+            (reader.u32_at(entry)? as u64, reader.u32_at(entry + 4)? as u64)
+        };
+        match tag {
+            DT_NULL   => break,
+            DT_NEEDED => needed_offsets.push(val),
+            DT_STRTAB => strtab_vaddr = Some(val),
+            _ => {}
+        }
+    }
+
+    if needed_offsets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let strtab_vaddr = strtab_vaddr.ok_or("DT_NEEDED entries present without a DT_STRTAB")?;
+
+    let strtab_offset = loads
+        .iter()
+        .find(|(_, vaddr, filesz)| strtab_vaddr >= *vaddr && strtab_vaddr < vaddr + filesz)
+        .map(|(offset, vaddr, _)| strtab_vaddr - vaddr + offset)
+        .ok_or("DT_STRTAB does not fall inside any PT_LOAD segment")?;
+
+    needed_offsets
+        .into_iter()
+        .map(|name_offset| reader.c_str_at(strtab_offset + name_offset))
+        .collect()
+}
+
+struct ElfReader<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+}
+
+impl ElfReader<'_> {
+    fn slice(&self, at: u64, len: usize) -> Result<&[u8], Box<dyn std::error::Error>> {
+        let start = usize::try_from(at)?;
+        self.bytes
+            .get(start..start + len)
+            .ok_or_else(|| format!("ELF read of {} bytes at {:#x} is out of bounds (64-bit: {})", len, at, self.is_64).into())
+    }
+
+    fn u16_at(&self, at: u64) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(u16::from_le_bytes(self.slice(at, 2)?.try_into()?))
+    }
+
+    fn u32_at(&self, at: u64) -> Result<u32, Box<dyn std::error::Error>> {
+        Ok(u32::from_le_bytes(self.slice(at, 4)?.try_into()?))
+    }
+
+    fn u64_at(&self, at: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(u64::from_le_bytes(self.slice(at, 8)?.try_into()?))
+    }
+
+    fn c_str_at(&self, at: u64) -> Result<String, Box<dyn std::error::Error>> {
+        let start = usize::try_from(at)?;
+        let tail  = self.bytes.get(start..).ok_or("string table offset out of bounds")?;
+        let end   = tail.iter().position(|b| *b == 0).ok_or("unterminated string in string table")?;
+        Ok(String::from_utf8(tail[..end].to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod test_elf_needed {
+    use super::*;
+
+    /// Build a minimal 64-bit shared object with one PT_LOAD covering the
+    /// whole file and a PT_DYNAMIC listing the given sonames.
+    fn synthetic_elf64(needed: &[&str]) -> Vec<u8> {
+        let phoff:  u64 = 0x40;
+        let dynoff: u64 = phoff + 2 * 56;
+        let dyn_entries = needed.len() as u64 + 2;
+        let stroff: u64 = dynoff + dyn_entries * 16;
+
+        let mut strtab = vec![0u8];
+        let mut name_offsets = Vec::new();
+        for name in needed {
+            name_offsets.push(strtab.len() as u64);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+
+        let total = stroff + strtab.len() as u64;
+        let mut elf = vec![0u8; total as usize];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        elf[0x38..0x3a].copy_from_slice(&2u16.to_le_bytes());
+
+        let mut write_ph = |index: u64, p_type: u32, offset: u64, filesz: u64| {
+            let ph = (phoff + index * 56) as usize;
+            elf[ph..ph + 4].copy_from_slice(&p_type.to_le_bytes());
+            elf[ph + 8..ph + 16].copy_from_slice(&offset.to_le_bytes());
+            elf[ph + 16..ph + 24].copy_from_slice(&offset.to_le_bytes());
+            elf[ph + 32..ph + 40].copy_from_slice(&filesz.to_le_bytes());
+        };
+        write_ph(0, PT_LOAD, 0, total);
+        write_ph(1, PT_DYNAMIC, dynoff, dyn_entries * 16);
+
+        let mut entry = dynoff as usize;
+        let mut write_dyn = |tag: u64, val: u64| {
+            elf[entry..entry + 8].copy_from_slice(&tag.to_le_bytes());
+            elf[entry + 8..entry + 16].copy_from_slice(&val.to_le_bytes());
+            entry += 16;
+        };
+        for offset in name_offsets {
+            write_dyn(DT_NEEDED, offset);
+        }
+        write_dyn(DT_STRTAB, stroff);
+        write_dyn(DT_NULL, 0);
+
+        elf[stroff as usize..].copy_from_slice(&strtab);
+        elf
+    }
+
+    #[test]
+    fn test_reads_needed_sonames() {
+        let elf = synthetic_elf64(&["libc++_shared.so", "libamidi.so", "liblog.so"]);
+        let needed = elf_needed_libraries(&elf).expect("synthetic ELF should parse");
+        assert_eq!(needed, vec!["libc++_shared.so", "libamidi.so", "liblog.so"]);
+    }
+
+    #[test]
+    fn test_rejects_non_elf() {
+        assert!(elf_needed_libraries(b"definitely not an elf file, but long enough to look at").is_err());
+    }
+}
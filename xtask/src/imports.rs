@@ -0,0 +1,6 @@
+// ---------------- [ File: xtask/src/imports.rs ]
+pub(crate) use export_magic::*;
+pub(crate) use tracing::*;
+pub(crate) use serde::{Deserialize, Serialize};
+pub(crate) use std::path::{Path, PathBuf};
+pub(crate) use std::{env, fs};
@@ -0,0 +1,194 @@
+// ---------------- [ File: xtask/src/jni_libs.rs ]
+crate::ix!();
+
+#[derive(Debug, Clone)]
+pub struct JniLibsOptions {
+    pub release:    bool,
+    pub abis:       Vec<AndroidAbi>,
+    pub package:    String,
+    pub out_dir:    PathBuf,
+    pub target_dir: PathBuf,
+}
+
+impl Default for JniLibsOptions {
+    fn default() -> Self {
+        Self {
+            release:    false,
+            abis:       AndroidAbi::ALL.to_vec(),
+            package:    "basic-android-integration".into(),
+            out_dir:    PathBuf::from("target/jniLibs"),
+            target_dir: PathBuf::from("target"),
+        }
+    }
+}
+
+impl JniLibsOptions {
+    pub fn from_args(args: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut opts = Self::default();
+        let mut abis = Vec::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| format!("{} expects a value", arg));
+            match arg.as_str() {
+                "--release"    => opts.release = true,
+                "--package"    => opts.package = value()?.clone(),
+                "--out"        => opts.out_dir = PathBuf::from(value()?),
+                "--target-dir" => opts.target_dir = PathBuf::from(value()?),
+                "--abi" => {
+                    let name = value()?;
+                    let abi  = AndroidAbi::from_jni_dir_name(name)
+                        .ok_or_else(|| format!("unknown ABI {:?}", name))?;
+                    abis.push(abi);
+                }
+                other => return Err(format!("unrecognized argument {:?}", other).into()),
+            }
+        }
+
+        if !abis.is_empty() {
+            opts.abis = abis;
+        }
+        Ok(opts)
+    }
+
+    pub fn profile(&self) -> &'static str {
+        if self.release { "release" } else { "debug" }
+    }
+
+    /// `basic-android-integration` builds `libbasic_android_integration.so`.
+    pub fn cdylib_file_name(&self) -> String {
+        format!("lib{}.so", self.package.replace('-', "_"))
+    }
+}
+
+/// Lay out the crate's cdylib plus the non-system shared libraries it needs
+/// as `<out>/<abi>/*.so`, the layout gradle consumes from `src/main/jniLibs`.
+///
+/// Dependencies are discovered from the cdylib's `DT_NEEDED` entries (and
+/// theirs, transitively). Anything in [`SYSTEM_LIBRARIES`] (libamidi, liblog,
+/// libc, ...) is recorded in the manifest but never copied: those must come
+/// from the device image. Everything else is looked up in the NDK sysroot for
+/// that ABI.
+///
+/// The cdylibs must already be built, e.g. `cargo ndk -t arm64-v8a build`.
+pub fn package_jni_libs(opts: &JniLibsOptions) -> Result<PackagingManifest, Box<dyn std::error::Error>> {
+    let sysroot_lib = ndk_sysroot_lib_dir()?;
+    debug!(?sysroot_lib, "Resolved NDK sysroot library directory");
+
+    let mut manifest = PackagingManifest {
+        package: opts.package.clone(),
+        profile: opts.profile().into(),
+        abis:    Vec::new(),
+    };
+
+    for abi in &opts.abis {
+        manifest.abis.push(package_abi(opts, *abi, &sysroot_lib)?);
+    }
+
+    fs::create_dir_all(&opts.out_dir)?;
+    manifest.write_to(&opts.out_dir.join("manifest.json"))?;
+    Ok(manifest)
+}
+
+fn package_abi(
+    opts:        &JniLibsOptions,
+    abi:         AndroidAbi,
+    sysroot_lib: &Path,
+) -> Result<AbiPackagingEntry, Box<dyn std::error::Error>> {
+    info!(%abi, "Packaging jniLibs");
+
+    let cdylib = opts
+        .target_dir
+        .join(abi.rust_triple())
+        .join(opts.profile())
+        .join(opts.cdylib_file_name());
+
+    if !cdylib.exists() {
+        return Err(format!(
+            "{:?} not found; build it first with `cargo ndk -t {} build{}`",
+            cdylib,
+            abi,
+            if opts.release { " --release" } else { "" },
+        ).into());
+    }
+
+    // Start from a clean directory so stale libraries (e.g. a previously
+    // bundled libamidi.so) never linger in the APK.
+    let abi_dir = opts.out_dir.join(abi.jni_dir_name());
+    if abi_dir.exists() {
+        fs::remove_dir_all(&abi_dir)?;
+    }
+    fs::create_dir_all(&abi_dir)?;
+
+    let mut entry = AbiPackagingEntry {
+        abi,
+        copied:           Vec::new(),
+        system_libraries: Vec::new(),
+    };
+
+    let mut pending = vec![cdylib];
+    let mut seen    = std::collections::HashSet::new();
+
+    while let Some(source) = pending.pop() {
+        let file = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("bad library path {:?}", source))?
+            .to_string();
+
+        let bytes = fs::read(&source)?;
+        fs::write(abi_dir.join(&file), &bytes)?;
+        debug!(%abi, %file, ?source, "Copied library");
+
+        for needed in elf_needed_libraries(&bytes)? {
+            if !seen.insert(needed.clone()) {
+                continue;
+            }
+            if is_system_library(&needed) {
+                trace!(%abi, %needed, "Skipping system library");
+                entry.system_libraries.push(needed);
+                continue;
+            }
+            let candidate = sysroot_lib.join(abi.ndk_sysroot_subdir()).join(&needed);
+            if !candidate.exists() {
+                return Err(format!(
+                    "{} needs {} which is neither a system library nor present at {:?}",
+                    file, needed, candidate
+                ).into());
+            }
+            pending.push(candidate);
+        }
+
+        entry.copied.push(CopiedLibrary {
+            file,
+            source,
+            bytes: bytes.len() as u64,
+        });
+    }
+
+    entry.system_libraries.sort();
+    info!(%abi, copied = entry.copied.len(), system = entry.system_libraries.len(), "Packaged ABI");
+    Ok(entry)
+}
+
+/// `$ANDROID_NDK_HOME/toolchains/llvm/prebuilt/<host>/sysroot/usr/lib`
+fn ndk_sysroot_lib_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let ndk_home = env::var("ANDROID_NDK_HOME")
+        .or_else(|_| env::var("ANDROID_NDK_ROOT"))
+        .map_err(|_| "Missing ANDROID_NDK_HOME")?;
+
+    let host_prebuilt = if cfg!(target_os = "macos") {
+        "darwin-x86_64"
+    } else if cfg!(target_os = "linux") {
+        "linux-x86_64"
+    } else if cfg!(target_os = "windows") {
+        "windows-x86_64"
+    } else {
+        return Err("Unsupported build host for prebuilt toolchain".into());
+    };
+
+    Ok(PathBuf::from(ndk_home)
+        .join("toolchains/llvm/prebuilt")
+        .join(host_prebuilt)
+        .join("sysroot/usr/lib"))
+}
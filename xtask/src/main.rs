@@ -0,0 +1,44 @@
+// ---------------- [ File: xtask/src/main.rs ]
+#[macro_use] mod imports; use imports::*;
+
+x!{android_abi}
+x!{elf_needed}
+x!{jni_libs}
+x!{packaging_manifest}
+x!{system_libraries}
+
+const USAGE: &str = "\
+usage: cargo xtask jnilibs [options]
+
+options:
+    --release             package artifacts from target/<triple>/release (default: debug)
+    --abi <abi>           armeabi-v7a | arm64-v8a | x86 | x86_64 (repeatable, default: all four)
+    --package <name>      cdylib package to bundle (default: basic-android-integration)
+    --out <dir>           output directory (default: target/jniLibs)
+    --target-dir <dir>    cargo target directory (default: target)
+";
+
+fn main() {
+    tracing_setup::configure_tracing();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("jnilibs") => JniLibsOptions::from_args(&args[1..]).and_then(|opts| package_jni_libs(&opts)),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match result {
+        Ok(manifest) => {
+            info!(abis = manifest.abis.len(), "jniLibs packaging complete");
+        }
+        Err(e) => {
+            error!(error = %e, "xtask failed");
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
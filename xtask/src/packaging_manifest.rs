@@ -0,0 +1,38 @@
+// ---------------- [ File: xtask/src/packaging_manifest.rs ]
+crate::ix!();
+
+/// Record of everything `cargo xtask jnilibs` placed into the jniLibs tree.
+/// Written next to the ABI directories as `manifest.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackagingManifest {
+    pub package: String,
+    pub profile: String,
+    pub abis:    Vec<AbiPackagingEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbiPackagingEntry {
+    pub abi: AndroidAbi,
+
+    /// Libraries copied into `jniLibs/<abi>/`, the crate's cdylib first.
+    pub copied: Vec<CopiedLibrary>,
+
+    /// Dependencies resolved from the OS at runtime and deliberately not bundled.
+    pub system_libraries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopiedLibrary {
+    pub file:   String,
+    pub source: PathBuf,
+    pub bytes:  u64,
+}
+
+impl PackagingManifest {
+    pub fn write_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")?;
+        info!(?path, "Wrote jniLibs manifest");
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+// ---------------- [ File: xtask/src/system_libraries.rs ]
+crate::ix!();
+
+/// Libraries provided by the Android OS image (the NDK "stable APIs").
+/// These are linked against the NDK stubs at build time but must always be
+/// loaded from the device at runtime, so they are never copied into jniLibs.
+pub const SYSTEM_LIBRARIES: &[&str] = &[
+    "libaaudio.so",
+    "libamidi.so",
+    "libandroid.so",
+    "libbinder_ndk.so",
+    "libc.so",
+    "libcamera2ndk.so",
+    "libdl.so",
+    "libEGL.so",
+    "libGLESv1_CM.so",
+    "libGLESv2.so",
+    "libGLESv3.so",
+    "libjnigraphics.so",
+    "liblog.so",
+    "libm.so",
+    "libmediandk.so",
+    "libnativewindow.so",
+    "libneuralnetworks.so",
+    "libOpenMAXAL.so",
+    "libOpenSLES.so",
+    "libstdc++.so",
+    "libsync.so",
+    "libvulkan.so",
+    "libz.so",
+];
+
+pub fn is_system_library(name: &str) -> bool {
+    SYSTEM_LIBRARIES.contains(&name)
+}